@@ -1,8 +1,20 @@
 create table users(id bigserial primary key, reputation bigint default 0, display_name varchar(64), website_url varchar(128), 
 				   location varchar(128), about_me text, views bigint, upvotes int, downvotes int, profile_image_url varchar(512),
-				   email varchar(256) not null, password_hash varchar(128) not null, salt varchar(256) not null,
+				   email varchar(256) not null, password_hash varchar(256) not null, salt varchar(256) not null,
 				   creation_date timestamp default now(), last_access_date timestamp default now(), title varchar(8),
-				   designation varchar(64), git varchar(256), twitter varchar(256), email_verified boolean default false);
+				   designation varchar(64), git varchar(256), twitter varchar(256), email_verified boolean default false,
+				   is_moderator boolean default false, status varchar(16) not null default 'active',
+				   suspended_until timestamp, suspension_reason text, password_changed_date timestamp);
+
+-- status
+-- active
+-- suspended = until suspended_until, the user is reinstated once it passes
+-- deleted
+
+-- Community (-1) takes the actions of the site itself, such as deleting posts on flags,
+-- no password matches its empty hash
+insert into users(id, display_name, email, password_hash, salt, email_verified)
+	values(-1, 'Community', 'community@localhost', '', '', true);
 
 -- Class
 -- 1 = Gold
@@ -12,6 +24,9 @@ create table users(id bigserial primary key, reputation bigint default 0, displa
 create table badges(id bigserial primary key, user_id bigserial references users(id), name varchar(64), date timestamp default now(),
 					class smallint not null, tag_based boolean default false);
 
+-- a user holds a badge once, tag badges are named after the tag and held once per class
+create unique index badges_user_name on badges(user_id, name, class, tag_based);
+
 
 create table post_types(id smallint primary key, name varchar(128));
 
@@ -30,30 +45,38 @@ insert into post_types(id, name) values(1, 'question'), (2, 'answer'), (3, 'orph
 -- 7 = "Wiki placeholder" (seems to only be the election description)
 -- 8 = Privilege wiki
 -- we are fixing body to 100KB
+-- locked posts take no votes, edits nor comments, protected questions take no answers from new users
 create table posts(id bigserial primary key, post_type_id smallint references post_types(id), accepted_answer_id bigint references posts(id),
 	parent_id bigint references posts(id), creation_date timestamp default now(),
 	deletion_date timestamp default null, score bigint, view_count bigint, body varchar(102400),
 	owner_user_id bigint references users(id), owner_display_name varchar(64), last_editor_user_id bigint references users(id),
 	last_editor_display_name varchar(64), last_edit_date timestamp default null, last_activity_date timestamp default now(),
 	title varchar(512), tags varchar(256), answer_count int default 0, comment_count int default 0,
-	favorite_count int default 0, closed_date timestamp, community_owned_date timestamp, content_license varchar(128));
+	favorite_count int default 0, closed_date timestamp, community_owned_date timestamp, content_license varchar(128),
+	locked_date timestamp, protected_date timestamp);
 
+-- deleted posts keep their row in posts with deletion_date set, and a copy of the row as it was when
+-- deleted is archived here until the post is undeleted
 -- we are fixing body to 100KB
-create table posts_with_deleted(id bigserial primary key, post_type_id smallint references post_types(id),
-	accepted_answer_id bigint references posts_with_deleted(id), parent_id bigint references posts_with_deleted(id),
+create table posts_with_deleted(id bigint primary key references posts(id), post_type_id smallint references post_types(id),
+	accepted_answer_id bigint references posts(id), parent_id bigint references posts(id),
 	creation_date timestamp default now(), deletion_date timestamp default null, score bigint, view_count bigint,
 	body varchar(102400), owner_user_id bigint references users(id), owner_display_name varchar(64), last_editor_user_id bigint
 	references users(id), last_editor_display_name varchar(64), last_edit_date timestamp default null, last_activity_date
 	timestamp default now(), title varchar(512), tags varchar(256), answer_count int default 0, comment_count int default 0,
-	favorite_count int default 0, closed_date timestamp, community_owned_date timestamp, content_license varchar(128));
+	favorite_count int default 0, closed_date timestamp, community_owned_date timestamp, content_license varchar(128),
+	locked_date timestamp, protected_date timestamp);
 
-create table comments(id bigserial primary key, post_id bigint, score int, text varchar(1024), creation_date timestamp default now(),
-	user_display_name varchar(64), user_id bigint);
+create table comments(id bigserial primary key, post_id bigint references posts(id), score int default 0, text varchar(1024),
+	creation_date timestamp default now(), user_display_name varchar(64), user_id bigint references users(id),
+	deletion_date timestamp default null);
 
+create index comments_post_id on comments(post_id);
+
+-- comments only carry upvotes, one per user
+create table comment_votes(comment_id bigint references comments(id), user_id bigint references users(id),
+	creation_date timestamp default now(), primary key(comment_id, user_id));
 
-create table post_notices(id bigserial primary key, post_id bigint references posts(id), post_notice_type_id bigint
-	references post_history_types(id), creation_date timestamp default now(), deletion_date timestamp, expiry_date timestamp,
-	body varchar(1024), owner_user_id bigint references users(id), deletion_user_id bigint references users(id));
 
 -- post notice type id
 -- 1 citation needed
@@ -84,11 +107,40 @@ create table post_notices(id bigserial primary key, post_id bigint references po
 create table post_notice_types(id bigserial primary key, class_id int, name varchar(128),
 	body varchar(1024), is_hidden boolean default false, predefined boolean default false, post_notice_duration_id int);
 
+insert into post_notice_types(id, class_id, name, body, predefined, post_notice_duration_id) values
+	(1, 4, 'Citation needed', 'This post needs references to reliable sources to back up its claims.', true, -1),
+	(2, 4, 'Current event', 'This post covers a current event and its contents may change quickly.', true, -1),
+	(3, 4, 'Insufficient explanation', 'This post lacks an explanation of how and why it answers the question.', true, -1),
+	(10, 4, 'Current answers are outdated', 'The answers to this question are outdated and need updating.', true, -1),
+	(11, 2, 'Draw attention', 'This question has not received enough attention.', true, 1),
+	(12, 2, 'Improve details', 'The current answers do not contain enough detail.', true, 1),
+	(13, 2, 'Authoritative reference needed', 'Looking for an answer drawing from credible and/or official sources.', true, 1),
+	(14, 2, 'Canonical answer required', 'Looking for a canonical answer that covers the topic in full.', true, 1),
+	(15, 2, 'Reward existing answer', 'One or more of the answers is exemplary and worthy of an additional bounty.', true, 1),
+	(20, 4, 'Content dispute', 'This post is subject to a content dispute and is being looked at by moderators.', true, -1),
+	(21, 4, 'Off-topic comments', 'Comments on this post have been cleaned up as they were off-topic.', true, -1),
+	(22, 1, 'Historical significance', 'This question is kept for its historical significance but is not a good example of an on-topic question.', true, -1),
+	(23, 4, 'Wiki answer', 'This answer is a community wiki, anyone may improve it.', true, -1),
+	(24, 1, 'Policy lock', 'This question is locked by policy, its content is settled.', true, -1),
+	(25, 4, 'Recommended answer', 'This answer is recommended by the community.', true, -1),
+	(26, 4, 'Posted by Recognized Member/Admin', 'This post was written by a recognized member of the community.', true, -1),
+	(27, 4, 'Endorsed edit', 'An edit to this post was endorsed by a moderator.', true, -1),
+	(28, 4, 'Obsolete', 'The content of this post is obsolete.', true, -1);
+
+-- body overrides the text of the notice type, expiry_date follows post_notice_duration_id
+create table post_notices(id bigserial primary key, post_id bigint references posts(id), post_notice_type_id bigint
+	references post_notice_types(id), creation_date timestamp default now(), deletion_date timestamp, expiry_date timestamp,
+	body varchar(1024), owner_user_id bigint references users(id), deletion_user_id bigint references users(id));
+
+create index post_notices_post_id on post_notices(post_id);
+
 
 create table tags(id bigserial primary key, tag_name varchar(64) unique, count bigint default 0, excerpt_post_id bigint references posts(id),
 	wiki_post_id bigint references posts(id), is_moderator_only boolean default false, is_required boolean default false);
 
-create table post_tags(post_id bigint primary key references posts(id), tag_id bigint references tags(id));
+create table post_tags(post_id bigint references posts(id), tag_id bigint references tags(id), primary key(post_id, tag_id));
+
+create index post_tags_tag_id on post_tags(tag_id);
 
 
 create table tag_synonyms(id bigserial primary key, source_tag_name varchar(64) references tags(tag_name),
@@ -96,13 +148,27 @@ create table tag_synonyms(id bigserial primary key, source_tag_name varchar(64)
 	auto_rename_count int default 0, last_auto_rename timestamp, score int, approved_by_user_id bigint references users(id),
 	approval_date timestamp);
 
+-- a tag is the source of at most one synonym
+create unique index tag_synonyms_source on tag_synonyms(source_tag_name);
+
+-- vote is 1 or -1, one per user and synonym
+create table tag_synonym_votes(tag_synonym_id bigint references tag_synonyms(id), user_id bigint references users(id),
+	vote smallint not null, creation_date timestamp default now(), primary key(tag_synonym_id, user_id));
+
 -- post_id source post id
 -- related_post_id target/related post id
 -- 1 = Linked (PostId contains a link to RelatedPostId)
 -- 3 = Duplicate (PostId is a duplicate of RelatedPostId)
+-- 4 = Merged (PostId was merged into RelatedPostId and redirects to it)
 create table post_links(id bigserial primary key, creation_date timestamp, post_id bigint references posts(id),
 	related_post_id bigint references posts(id), link_type_id int);
 
+-- pending close and reopen votes on a question, cleared once the question is closed or reopened
+-- close_reason_id is one of Config::close_reasons, duplicate_of is set for duplicate close votes
+create table close_votes(post_id bigint references posts(id), user_id bigint references users(id),
+	reopen boolean default false, close_reason_id int, duplicate_of bigint references posts(id),
+	creation_date timestamp default now(), primary key(post_id, user_id, reopen));
+
 create table post_history_types(id serial primary key, name varchar(128));
 
 -- 1 = Initial Title - initial title (questions only)
@@ -145,8 +211,8 @@ insert into post_history_types(id, name) values(1, 'Initial Title'), (2, 'Initia
 	(4, 'Edit title'), (5, 'Edit Body'), (6, 'Edit Tags'), (7, 'Rollback Title'), (8, 'Rollback Body'),
 	(9, 'Roolback Tags'), (10, 'Post Closed'), (11, 'Post Reopened'), (12, 'Post Deleted'), (13, 'Post Undeleted'),
 	(14, 'Post Locked'), (15, 'Post Unlocked'), (16, 'Community Owned'), (18, 'Question Merged'), (19, 'Question Protected'),
-	(20, 'Question Unprotected'), (21, 'Post Disassociated'), (22, 'Question Unmerged'), (23, 'Suggested Edit Applied'),
-	(24, 'Post Tweeted'), ('31', 'Comment discussion moved to chat'), (33, 'Post notice added'),
+	(20, 'Question Unprotected'), (21, 'Post Disassociated'), (22, 'Question Unmerged'), (24, 'Suggested Edit Applied'),
+	(25, 'Post Tweeted'), ('31', 'Comment discussion moved to chat'), (33, 'Post notice added'),
 	(34, 'Post notice removed'), (35, 'Post migrated away'), (36, 'Post migrated here'), (37, 'Post merge source'),
 	(38, 'Post merge destination'), (50, 'Bumped by community user');
 
@@ -156,6 +222,7 @@ insert into post_history_types(id, name) values(1, 'Initial Title'), (2, 'Initia
 -- text -> A raw version of the new value for a given revision
 -- - If PostHistoryTypeId in (10,11,12,13,14,15,19,20,35) text column will contain a JSON encoded string with all users who have voted for the PostHistoryTypeId
 -- If it is a duplicate close vote, the JSON string will contain an array of original questions as OriginalQuestionIds
+-- If PostHistoryTypeId in (18,22,37,38) text column will contain a JSON encoded string with the moved AnswerIds and VoteIds
 -- If PostHistoryTypeId = 17 text column will contain migration details of either from <url> or to <url>
 -- user_display_name is populated if a user has been removed and no longer referenced by user Id
 create table post_history(id bigserial primary key, post_history_type_id bigint references post_history_types(id),
@@ -186,5 +253,61 @@ insert into vote_types(id, name) values(1, 'AcceptedByOriginator'), (2, 'UpVote'
 
 -- user_id: (present only if VoteTypeId in (5,8); -1 if user is deleted)
 -- bounty_amount (present only if VoteTypeId in (8,9))
+-- an applied suggested edit records a VoteTypeId 16 vote whose user_id is the suggester and whose
+-- voter_reputation_change is the reputation awarded for it
+-- owner_reputation_change and voter_reputation_change hold the reputation applied to the post owner and
+-- the voter when an up/down vote was cast, so that retracting the vote reverses exactly that amount
 create table votes(id bigserial primary key, post_id bigint references posts(id), vote_type_id int references
-	vote_types(id), user_id bigint references users(id), creation_date date, bounty_amount int);
\ No newline at end of file
+	vote_types(id), user_id bigint references users(id), creation_date date, bounty_amount int,
+	owner_reputation_change bigint default 0, voter_reputation_change bigint default 0);
+
+-- a user has at most one up or down vote on a post
+create unique index votes_post_user_updown on votes(post_id, user_id) where vote_type_id in (2, 3);
+
+-- edits by users below the edit privilege wait here for review, title and tags are null for answers
+-- revision_guid points at the post_history rows written when the edit is applied
+create table suggested_edits(id bigserial primary key, post_id bigint references posts(id), creation_date timestamp default now(),
+	approval_date timestamp, rejection_date timestamp, owner_user_id bigint references users(id), text text, title varchar(256), tags varchar(256), revision_guid uuid);
+
+-- a post has at most one pending suggested edit
+create unique index suggested_edits_pending on suggested_edits(post_id) where approval_date is null and rejection_date is null;
+
+-- vote_type_id is 2 (approve) or 3 (reject), one per reviewer
+create table suggested_edit_votes(suggested_edit_id bigint references suggested_edits(id), user_id bigint references users(id),
+	vote_type_id int references vote_types(id), creation_date timestamp default now(), primary key(suggested_edit_id, user_id));
+
+-- flag_type_id
+-- 1 = spam
+-- 2 = rude or abusive
+-- 3 = needs moderator attention, text holds the reason
+-- 4 = not an answer
+-- comment_id is set for flags on comments, post_id is then the commented post
+-- spam and rude flags on posts are also kept as votes of type 12 and 4
+-- helpful is set by the moderator handling the flag, or true when the flags deleted the content
+create table flags(id bigserial primary key, post_id bigint references posts(id), comment_id bigint references comments(id),
+	flag_type_id smallint not null, user_id bigint references users(id), text varchar(512),
+	creation_date timestamp default now(), handled_date timestamp, handled_user_id bigint references users(id),
+	helpful boolean);
+
+-- a user has one pending flag on a post or comment
+create unique index flags_pending_post on flags(post_id, user_id) where comment_id is null and handled_date is null;
+create unique index flags_pending_comment on flags(comment_id, user_id) where comment_id is not null and handled_date is null;
+
+-- queue_id
+-- 1 = first posts
+-- 2 = late answers
+-- 3 = low quality
+-- 4 = close votes
+-- 5 = reopen votes
+-- 6 = suggested edits
+-- review_action_id
+-- 1 = looks ok, 2 = skip, 3 = edit, 4 = delete, 5 = close, 6 = leave open, 7 = reopen,
+-- 8 = leave closed, 9 = approve, 10 = reject
+-- a post leaves a queue after Config::review_completions reviews other than skips
+create table reviews(id bigserial primary key, queue_id smallint not null, post_id bigint references posts(id),
+	user_id bigint references users(id), review_action_id smallint not null, creation_date timestamp default now());
+
+-- one review per reviewer and post in a queue
+create unique index reviews_queue_post_user on reviews(queue_id, post_id, user_id);
+create index reviews_post_id on reviews(post_id, queue_id);
+create index reviews_user_id on reviews(user_id, creation_date);
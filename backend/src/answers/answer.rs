@@ -0,0 +1,140 @@
+use crate::notices::notice::PostNoticeResponse;
+use crate::revisions::revision::Snapshot;
+use chrono::NaiveDateTime;
+
+// post_type_id of an answer in the posts table
+pub const ANSWER_POST_TYPE: i16 = 2;
+// vote_type_id recorded when the question owner accepts an answer
+pub const ACCEPTED_VOTE_TYPE: i32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct PostAnswer {
+    #[validate(length(min = 30, max = 102400))]
+    pub body: String,
+}
+
+impl PostAnswer {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            body: self.body.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerSort {
+    #[default]
+    Votes,
+    Newest,
+    Oldest,
+    Active,
+}
+
+impl AnswerSort {
+    // the accepted answer is always pinned on top
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            AnswerSort::Votes => "is_accepted desc, score desc, id asc",
+            AnswerSort::Newest => "is_accepted desc, creation_date desc, id desc",
+            AnswerSort::Oldest => "is_accepted desc, creation_date asc, id asc",
+            AnswerSort::Active => "is_accepted desc, last_activity_date desc, id desc",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnswersReq {
+    #[serde(default)]
+    pub sort: AnswerSort,
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(FromRow, Debug)]
+pub struct AnswerRow {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub body: Option<String>,
+    pub score: Option<i64>,
+    pub comment_count: Option<i32>,
+    pub owner_user_id: Option<i64>,
+    pub owner_display_name: Option<String>,
+    pub last_editor_display_name: Option<String>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub last_edit_date: Option<NaiveDateTime>,
+    pub last_activity_date: Option<NaiveDateTime>,
+    pub deletion_date: Option<NaiveDateTime>,
+    pub locked_date: Option<NaiveDateTime>,
+    pub community_owned_date: Option<NaiveDateTime>,
+    pub is_accepted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnswerResponse {
+    pub id: String,
+    pub question_id: String,
+    pub body: String,
+    pub score: i64,
+    pub comment_count: i32,
+    pub is_accepted: bool,
+    pub owner_user_id: String,
+    pub owner_display_name: String,
+    pub last_editor_display_name: Option<String>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub last_edit_date: Option<NaiveDateTime>,
+    pub last_activity_date: Option<NaiveDateTime>,
+    pub deletion_date: Option<NaiveDateTime>,
+    pub locked_date: Option<NaiveDateTime>,
+    pub community_owned_date: Option<NaiveDateTime>,
+    pub notices: Vec<PostNoticeResponse>,
+}
+
+impl From<AnswerRow> for AnswerResponse {
+    fn from(a: AnswerRow) -> Self {
+        Self {
+            id: a.id.to_string(),
+            question_id: a.parent_id.unwrap_or_default().to_string(),
+            body: a.body.unwrap_or_default(),
+            score: a.score.unwrap_or_default(),
+            comment_count: a.comment_count.unwrap_or_default(),
+            is_accepted: a.is_accepted,
+            owner_user_id: a.owner_user_id.unwrap_or_default().to_string(),
+            owner_display_name: a.owner_display_name.unwrap_or_default(),
+            last_editor_display_name: a.last_editor_display_name,
+            creation_date: a.creation_date,
+            last_edit_date: a.last_edit_date,
+            last_activity_date: a.last_activity_date,
+            deletion_date: a.deletion_date,
+            locked_date: a.locked_date,
+            community_owned_date: a.community_owned_date,
+            notices: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnswersResponse {
+    pub answers: Vec<AnswerResponse>,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PostAnswerResponse {
+    pub id: String,
+}
+
+// outcome of an operation that depends on who owns the post
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome<T = ()> {
+    Done(T),
+    NotFound,
+    Forbidden,
+    // the question is closed to new answers
+    Closed,
+    // the post is locked by a moderator
+    Locked,
+    // the question takes answers only from users with the answer-protected privilege
+    Protected,
+}
@@ -0,0 +1,375 @@
+use super::answer::*;
+use crate::badges::dao::check_badges;
+use crate::notices::dao::active_notices;
+use crate::notices::notice::PostNoticeResponse;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Snapshot;
+use crate::state::AppStateRaw;
+
+#[async_trait]
+pub trait IAnswer: std::ops::Deref<Target = AppStateRaw> {
+    async fn insert_answer(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        established: bool,
+        form: &PostAnswer,
+    ) -> sqlx::Result<Outcome<i64>>;
+    async fn update_answer(
+        &self,
+        aid: i64,
+        uid: i64,
+        username: &str,
+        any_post: bool,
+        any_wiki: bool,
+        form: &PostAnswer,
+    ) -> sqlx::Result<Outcome>;
+    async fn get_answers(
+        &self,
+        qid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+        form: &AnswersReq,
+    ) -> sqlx::Result<AnswersResponse>;
+    async fn accept_answer(&self, aid: i64, uid: i64) -> sqlx::Result<Outcome>;
+    async fn unaccept_answer(&self, aid: i64, uid: i64) -> sqlx::Result<Outcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IAnswer for &AppStateRaw {
+    async fn insert_answer(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        established: bool,
+        form: &PostAnswer,
+    ) -> sqlx::Result<Outcome<i64>> {
+        let mut tx = self.sql.begin().await?;
+
+        let parent = sqlx::query!(
+            r#"
+            update posts set answer_count = coalesce(answer_count, 0) + 1, last_activity_date = now()
+            where id = $1 and post_type_id = $2 and deletion_date is null
+            returning closed_date, locked_date, protected_date
+            "#,
+            qid,
+            QUESTION_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        match parent {
+            Some(p) if p.closed_date.is_some() => return Ok(Outcome::Closed),
+            Some(p) if p.locked_date.is_some() => return Ok(Outcome::Locked),
+            Some(p) if p.protected_date.is_some() && !established => {
+                return Ok(Outcome::Protected)
+            }
+            Some(_) => {}
+            None => return Ok(Outcome::NotFound),
+        }
+
+        let r = sqlx::query!(
+            r#"
+            insert into posts (post_type_id, parent_id, body, owner_user_id, owner_display_name,
+                score, view_count)
+            values ($1, $2, $3, $4, $5, 0, 0) returning id
+            "#,
+            ANSWER_POST_TYPE,
+            qid,
+            form.body,
+            uid,
+            username
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        let snapshot = form.snapshot();
+        let changes = snapshot.initial_changes();
+        record_revision(&mut tx, r.id, uid, username, None, &changes).await?;
+
+        tx.commit().await?;
+
+        check_badges(self, Some(uid)).await;
+
+        Ok(Outcome::Done(r.id))
+    }
+
+    async fn update_answer(
+        &self,
+        aid: i64,
+        uid: i64,
+        username: &str,
+        any_post: bool,
+        any_wiki: bool,
+        form: &PostAnswer,
+    ) -> sqlx::Result<Outcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let a = sqlx::query!(
+            r#"
+            select owner_user_id, parent_id, body, locked_date,
+                community_owned_date is not null as "wiki!"
+            from posts
+            where id = $1 and post_type_id = $2 and deletion_date is null
+            for update
+            "#,
+            aid,
+            ANSWER_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let a = match a {
+            Some(a) => a,
+            None => return Ok(Outcome::NotFound),
+        };
+        if a.locked_date.is_some() {
+            return Ok(Outcome::Locked);
+        }
+        // only owners and users who may edit any post, or any community wiki
+        if a.owner_user_id != Some(uid) && !any_post && !(a.wiki && any_wiki) {
+            return Ok(Outcome::Forbidden);
+        }
+        let parent_id = a.parent_id;
+        let old = Snapshot {
+            body: a.body.unwrap_or_default(),
+            ..Default::default()
+        };
+        let snapshot = form.snapshot();
+        let changes = snapshot.edit_changes(&old);
+        if changes.is_empty() {
+            return Ok(Outcome::Done(()));
+        }
+
+        sqlx::query!(
+            r#"
+            update posts set body = $1, last_editor_user_id = $2, last_editor_display_name = $3,
+                last_edit_date = now(), last_activity_date = now()
+            where id = $4
+            "#,
+            form.body,
+            uid,
+            username,
+            aid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        record_revision(&mut tx, aid, uid, username, None, &changes).await?;
+
+        sqlx::query!(
+            r#"
+            update posts set last_activity_date = now() where id = $1
+            "#,
+            parent_id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Outcome::Done(()))
+    }
+
+    async fn get_answers(
+        &self,
+        qid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+        form: &AnswersReq,
+    ) -> sqlx::Result<AnswersResponse> {
+        let per_page = self.config.answers_per_page;
+        let offset = form.page.max(0) * per_page;
+
+        let count = sqlx::query!(
+            r#"
+            select count(1) as "count!" from posts
+            where parent_id = $1 and post_type_id = $2
+                and (deletion_date is null or $3 or owner_user_id = $4)
+            "#,
+            qid,
+            ANSWER_POST_TYPE,
+            moderator,
+            viewer
+        )
+        .fetch_one(&self.sql)
+        .await?
+        .count;
+
+        let sql = format!(
+            "select * from (
+                select a.id, a.parent_id, a.body, a.score, a.comment_count, a.owner_user_id,
+                    a.owner_display_name, a.last_editor_display_name, a.creation_date,
+                    a.last_edit_date, a.last_activity_date, a.deletion_date, a.locked_date,
+                    a.community_owned_date,
+                    coalesce(q.accepted_answer_id = a.id, false) as is_accepted
+                from posts a join posts q on q.id = a.parent_id
+                where a.parent_id = $1 and a.post_type_id = $2
+                    and (a.deletion_date is null or $5 or a.owner_user_id = $6)
+            ) answers
+            order by {} limit $3 offset $4;",
+            form.sort.order_by()
+        );
+
+        let rows: Vec<AnswerRow> = sqlx::query_as(&sql)
+            .bind(qid)
+            .bind(ANSWER_POST_TYPE)
+            .bind(per_page)
+            .bind(offset)
+            .bind(moderator)
+            .bind(viewer)
+            .fetch_all(&self.sql)
+            .await?;
+
+        let ids: Vec<i64> = rows.iter().map(|a| a.id).collect();
+        let mut notices = active_notices(&self.sql, &ids).await?;
+        let answers = rows
+            .into_iter()
+            .map(|a| {
+                let id = a.id;
+                let (own, rest): (Vec<_>, Vec<_>) =
+                    notices.drain(..).partition(|n| n.post_id == id);
+                notices = rest;
+                let mut answer = AnswerResponse::from(a);
+                answer.notices = own.into_iter().map(PostNoticeResponse::from).collect();
+                answer
+            })
+            .collect();
+
+        Ok(AnswersResponse { answers, count })
+    }
+
+    async fn accept_answer(&self, aid: i64, uid: i64) -> sqlx::Result<Outcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let q = sqlx::query!(
+            r#"
+            select q.id, q.owner_user_id, q.accepted_answer_id, a.owner_user_id as answer_owner_id,
+                q.deletion_date, q.locked_date is not null or a.locked_date is not null as "locked!"
+            from posts q join posts a on a.parent_id = q.id
+            where a.id = $1 and a.post_type_id = $2 and a.deletion_date is null
+            for update of q
+            "#,
+            aid,
+            ANSWER_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        // locks freeze acceptance along with the other votes
+        let q = match q {
+            Some(q) if q.deletion_date.is_none() => q,
+            _ => return Ok(Outcome::NotFound),
+        };
+        if q.locked {
+            return Ok(Outcome::Locked);
+        }
+        if q.owner_user_id != Some(uid) {
+            return Ok(Outcome::Forbidden);
+        }
+        if q.accepted_answer_id == Some(aid) {
+            return Ok(Outcome::Done(()));
+        }
+
+        // a question has at most one accepted answer
+        if let Some(previous) = q.accepted_answer_id {
+            sqlx::query!(
+                r#"
+                delete from votes where post_id = $1 and vote_type_id = $2
+                "#,
+                previous,
+                ACCEPTED_VOTE_TYPE
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            insert into votes (post_id, vote_type_id, user_id, creation_date)
+            values ($1, $2, $3, current_date)
+            "#,
+            aid,
+            ACCEPTED_VOTE_TYPE,
+            uid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            update posts set accepted_answer_id = $1, last_activity_date = now() where id = $2
+            "#,
+            aid,
+            q.id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        check_badges(self, q.answer_owner_id).await;
+
+        Ok(Outcome::Done(()))
+    }
+
+    async fn unaccept_answer(&self, aid: i64, uid: i64) -> sqlx::Result<Outcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let q = sqlx::query!(
+            r#"
+            select q.id, q.owner_user_id, q.accepted_answer_id, q.deletion_date,
+                q.locked_date is not null or a.locked_date is not null as "locked!"
+            from posts q join posts a on a.parent_id = q.id
+            where a.id = $1 and a.post_type_id = $2
+            for update of q
+            "#,
+            aid,
+            ANSWER_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        // locks freeze acceptance along with the other votes
+        let q = match q {
+            Some(q) if q.deletion_date.is_none() => q,
+            _ => return Ok(Outcome::NotFound),
+        };
+        if q.locked {
+            return Ok(Outcome::Locked);
+        }
+        if q.owner_user_id != Some(uid) {
+            return Ok(Outcome::Forbidden);
+        }
+        if q.accepted_answer_id != Some(aid) {
+            return Ok(Outcome::Done(()));
+        }
+
+        sqlx::query!(
+            r#"
+            delete from votes where post_id = $1 and vote_type_id = $2
+            "#,
+            aid,
+            ACCEPTED_VOTE_TYPE
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            update posts set accepted_answer_id = null, last_activity_date = now() where id = $1
+            "#,
+            q.id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Outcome::Done(()))
+    }
+}
@@ -0,0 +1,3 @@
+pub mod answer;
+pub mod dao;
+pub mod routes;
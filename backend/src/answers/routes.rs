@@ -0,0 +1,173 @@
+use super::answer::*;
+use super::dao::IAnswer;
+use crate::api::ApiResult;
+use crate::middlewares::auth::AuthorizationService;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+use crate::suggestions::routes::submit_suggested_edit;
+use crate::users::dao::IUser;
+
+use actix_web::{post, web, Responder};
+use validator::Validate;
+
+#[post("/question/{id}/answer")]
+async fn create_answer(
+    params: web::Path<i64>,
+    form: web::Json<PostAnswer>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("create answer {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    let privileges = &state.config.privileges;
+    let established = auth.has(Privilege::AnswerProtected, privileges);
+    match state
+        .get_ref()
+        .insert_answer(qid, auth.claims.id, &auth.claims.username, established, &form)
+        .await
+    {
+        Ok(Outcome::Done(id)) => {
+            let res = PostAnswerResponse { id: id.to_string() };
+            ApiResult::new().code(200).with_msg("").with_data(res)
+        }
+        Ok(Outcome::Closed) => ApiResult::new()
+            .code(403)
+            .with_msg("This question is closed to new answers"),
+        Ok(Outcome::Locked) => ApiResult::new().code(403).with_msg("This question is locked"),
+        Ok(Outcome::Protected) => Privilege::AnswerProtected.denied(privileges),
+        Ok(_) => ApiResult::new().code(404).with_msg("Question not found"),
+        Err(e) => {
+            error!("create answer {:?} error: {:?}", form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/edit-answer/{id}")]
+async fn edit_answer(
+    params: web::Path<i64>,
+    form: web::Json<PostAnswer>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let aid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("edit answer {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    let privileges = &state.config.privileges;
+    let any_post = auth.has(Privilege::EditWithoutReview, privileges);
+    let any_wiki = auth.has(Privilege::EditCommunityWiki, privileges);
+    match state
+        .get_ref()
+        .update_answer(
+            aid,
+            auth.claims.id,
+            &auth.claims.username,
+            any_post,
+            any_wiki,
+            &form,
+        )
+        .await
+    {
+        Ok(Outcome::Done(())) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Ok(Outcome::Locked) => ApiResult::new().code(403).with_msg("This answer is locked"),
+        Ok(Outcome::Forbidden) => {
+            submit_suggested_edit(&state, aid, auth.claims.id, &form.snapshot()).await
+        }
+        Ok(_) => ApiResult::new().code(404).with_msg("Answer not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/question/{id}/answers")]
+async fn get_answers(
+    params: web::Path<i64>,
+    form: web::Json<AnswersReq>,
+    auth: Option<AuthorizationService>,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.into_inner();
+    let form = form.into_inner();
+    let viewer = auth.map(|a| a.claims.id);
+    let moderator = match viewer {
+        Some(uid) => state.get_ref().is_moderator(uid).await.unwrap_or(false),
+        None => false,
+    };
+    match state
+        .get_ref()
+        .get_answers(qid, viewer, moderator, &form)
+        .await
+    {
+        Ok(ars) => ApiResult::new().code(200).with_msg("").with_data(ars),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+#[post("/accept-answer/{id}")]
+async fn accept_answer(
+    params: web::Path<i64>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let aid = params.into_inner();
+    match state.get_ref().accept_answer(aid, auth.claims.id).await {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("accept answer {} error: {:?}", aid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/unaccept-answer/{id}")]
+async fn unaccept_answer(
+    params: web::Path<i64>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let aid = params.into_inner();
+    match state.get_ref().unaccept_answer(aid, auth.claims.id).await {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("unaccept answer {} error: {:?}", aid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result(outcome: Outcome) -> ApiResult<bool> {
+    match outcome {
+        Outcome::Done(()) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Outcome::NotFound => ApiResult::new().code(404).with_msg("Answer not found"),
+        Outcome::Forbidden => ApiResult::new()
+            .code(403)
+            .with_msg("Only the question owner can accept an answer"),
+        Outcome::Closed => ApiResult::new().code(403).with_msg("This question is closed"),
+        Outcome::Locked => ApiResult::new().code(403).with_msg("This post is locked"),
+        Outcome::Protected => ApiResult::new()
+            .code(403)
+            .with_msg("This question is protected"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_answer);
+    cfg.service(edit_answer);
+    cfg.service(get_answers);
+    cfg.service(accept_answer);
+    cfg.service(unaccept_answer);
+}
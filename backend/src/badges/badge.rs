@@ -0,0 +1,95 @@
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::questions::question::QUESTION_POST_TYPE;
+use chrono::NaiveDateTime;
+
+// badges.class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadgeClass {
+    Gold = 1,
+    Silver = 2,
+    Bronze = 3,
+}
+
+#[derive(Debug)]
+pub enum RuleKind {
+    // the user has a live post of this type
+    FirstPost { post_type: i16 },
+    // the user has a live post of this type scoring at least min_score
+    PostScore { post_type: i16, min_score: i64 },
+    // the user has an accepted answer scoring at least min_score
+    Accepted { min_score: i64 },
+    // total answer score and answer count in a tag, awarded once per tag under the tag's name
+    TagScore { min_score: i64, min_answers: i64 },
+}
+
+#[derive(Debug)]
+pub struct Rule {
+    pub name: &'static str,
+    pub class: BadgeClass,
+    pub kind: RuleKind,
+}
+
+impl Rule {
+    pub fn tag_based(&self) -> bool {
+        matches!(self.kind, RuleKind::TagScore { .. })
+    }
+}
+
+const fn rule(name: &'static str, class: BadgeClass, kind: RuleKind) -> Rule {
+    Rule { name, class, kind }
+}
+
+use BadgeClass::*;
+use RuleKind::*;
+
+// Badges the engine awards. A user holds each badge at most once, tag badges once per tag and class.
+pub const RULES: &[Rule] = &[
+    rule("Student", Bronze, FirstPost { post_type: QUESTION_POST_TYPE }),
+    rule("Teacher", Bronze, FirstPost { post_type: ANSWER_POST_TYPE }),
+    rule("Nice Question", Bronze, PostScore { post_type: QUESTION_POST_TYPE, min_score: 10 }),
+    rule("Good Question", Silver, PostScore { post_type: QUESTION_POST_TYPE, min_score: 25 }),
+    rule("Great Question", Gold, PostScore { post_type: QUESTION_POST_TYPE, min_score: 100 }),
+    rule("Nice Answer", Bronze, PostScore { post_type: ANSWER_POST_TYPE, min_score: 10 }),
+    rule("Good Answer", Silver, PostScore { post_type: ANSWER_POST_TYPE, min_score: 25 }),
+    rule("Great Answer", Gold, PostScore { post_type: ANSWER_POST_TYPE, min_score: 100 }),
+    rule("Enlightened", Bronze, Accepted { min_score: 10 }),
+    rule("Guru", Silver, Accepted { min_score: 40 }),
+    rule("tag", Bronze, TagScore { min_score: 100, min_answers: 20 }),
+    rule("tag", Silver, TagScore { min_score: 400, min_answers: 80 }),
+    rule("tag", Gold, TagScore { min_score: 1000, min_answers: 200 }),
+];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BadgeResponse {
+    pub name: String,
+    pub class: i16,
+    pub tag_based: bool,
+    pub date: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserBadgesResponse {
+    pub badges: Vec<BadgeResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BadgeHoldersReq {
+    #[serde(default)]
+    pub tag_based: bool,
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BadgeHolder {
+    pub user_id: String,
+    pub display_name: String,
+    pub class: i16,
+    pub date: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BadgeHoldersResponse {
+    pub holders: Vec<BadgeHolder>,
+    pub count: i64,
+}
@@ -0,0 +1,188 @@
+use super::badge::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::state::AppStateRaw;
+
+#[async_trait]
+pub trait IBadge: std::ops::Deref<Target = AppStateRaw> {
+    async fn award_badges(&self, uid: Option<i64>) -> sqlx::Result<u64>;
+    async fn get_user_badges(&self, uid: i64) -> sqlx::Result<UserBadgesResponse>;
+    async fn get_badge_holders(
+        &self,
+        name: &str,
+        form: &BadgeHoldersReq,
+    ) -> sqlx::Result<BadgeHoldersResponse>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IBadge for &AppStateRaw {
+    // Evaluates every rule for `uid`, or for all users when None, returning the number of new badges
+    async fn award_badges(&self, uid: Option<i64>) -> sqlx::Result<u64> {
+        let mut awarded = 0;
+
+        for rule in RULES {
+            let class = rule.class as i16;
+            let r = match rule.kind {
+                RuleKind::FirstPost { post_type } => {
+                    sqlx::query!(
+                        r#"
+                        insert into badges (user_id, name, class, tag_based)
+                        select distinct owner_user_id, $2::text, $3::smallint, false from posts
+                        where post_type_id = $4 and deletion_date is null
+                            and owner_user_id is not null and ($1::bigint is null or owner_user_id = $1)
+                        on conflict do nothing
+                        "#,
+                        uid,
+                        rule.name,
+                        class,
+                        post_type
+                    )
+                    .execute(&self.sql)
+                    .await?
+                }
+                RuleKind::PostScore {
+                    post_type,
+                    min_score,
+                } => {
+                    sqlx::query!(
+                        r#"
+                        insert into badges (user_id, name, class, tag_based)
+                        select distinct owner_user_id, $2::text, $3::smallint, false from posts
+                        where post_type_id = $4 and deletion_date is null and score >= $5
+                            and owner_user_id is not null and ($1::bigint is null or owner_user_id = $1)
+                        on conflict do nothing
+                        "#,
+                        uid,
+                        rule.name,
+                        class,
+                        post_type,
+                        min_score
+                    )
+                    .execute(&self.sql)
+                    .await?
+                }
+                RuleKind::Accepted { min_score } => {
+                    sqlx::query!(
+                        r#"
+                        insert into badges (user_id, name, class, tag_based)
+                        select distinct a.owner_user_id, $2::text, $3::smallint, false from posts a
+                        join posts q on q.accepted_answer_id = a.id
+                        where a.deletion_date is null and q.deletion_date is null and a.score >= $4
+                            and a.owner_user_id is not null
+                            and ($1::bigint is null or a.owner_user_id = $1)
+                        on conflict do nothing
+                        "#,
+                        uid,
+                        rule.name,
+                        class,
+                        min_score
+                    )
+                    .execute(&self.sql)
+                    .await?
+                }
+                RuleKind::TagScore {
+                    min_score,
+                    min_answers,
+                } => {
+                    sqlx::query!(
+                        r#"
+                        insert into badges (user_id, name, class, tag_based)
+                        select a.owner_user_id, t.tag_name, $2::smallint, true from posts a
+                        join posts q on q.id = a.parent_id
+                        join post_tags pt on pt.post_id = q.id
+                        join tags t on t.id = pt.tag_id
+                        where a.post_type_id = $3 and a.deletion_date is null and q.deletion_date is null
+                            and a.owner_user_id is not null
+                            and ($1::bigint is null or a.owner_user_id = $1)
+                        group by a.owner_user_id, t.tag_name
+                        having sum(coalesce(a.score, 0))::bigint >= $4 and count(1) >= $5
+                        on conflict do nothing
+                        "#,
+                        uid,
+                        class,
+                        ANSWER_POST_TYPE,
+                        min_score,
+                        min_answers
+                    )
+                    .execute(&self.sql)
+                    .await?
+                }
+            };
+            awarded += r.rows_affected();
+        }
+
+        Ok(awarded)
+    }
+
+    async fn get_user_badges(&self, uid: i64) -> sqlx::Result<UserBadgesResponse> {
+        let badges = sqlx::query_as!(
+            BadgeResponse,
+            r#"
+            select name as "name!", class, tag_based as "tag_based!", date from badges
+            where user_id = $1
+            order by class asc, date desc
+            "#,
+            uid
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        Ok(UserBadgesResponse { badges })
+    }
+
+    async fn get_badge_holders(
+        &self,
+        name: &str,
+        form: &BadgeHoldersReq,
+    ) -> sqlx::Result<BadgeHoldersResponse> {
+        let per_page = self.config.users_per_page as i64;
+        let offset = form.page.max(0) * per_page;
+
+        let count = sqlx::query!(
+            r#"
+            select count(1) as "count!" from badges where name = $1 and tag_based = $2
+            "#,
+            name,
+            form.tag_based
+        )
+        .fetch_one(&self.sql)
+        .await?
+        .count;
+
+        let hr = sqlx::query!(
+            r#"
+            select b.user_id, u.display_name, b.class, b.date from badges b
+            join users u on u.id = b.user_id
+            where b.name = $1 and b.tag_based = $2
+            order by b.date desc, b.id desc limit $3 offset $4
+            "#,
+            name,
+            form.tag_based,
+            per_page,
+            offset
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let holders = hr
+            .into_iter()
+            .map(|h| BadgeHolder {
+                user_id: h.user_id.to_string(),
+                display_name: h.display_name.unwrap_or_default(),
+                class: h.class,
+                date: h.date,
+            })
+            .collect();
+
+        Ok(BadgeHoldersResponse { holders, count })
+    }
+}
+
+// Awards whatever badges `uid` has earned after an event, a failure only gets logged
+pub async fn check_badges(state: &AppStateRaw, uid: Option<i64>) {
+    if let Some(uid) = uid {
+        if let Err(e) = state.award_badges(Some(uid)).await {
+            error!("award badges for {} error: {:?}", uid, e);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use super::badge::*;
+use super::dao::IBadge;
+use crate::api::ApiResult;
+use crate::state::AppState;
+
+use actix_web::{get, post, web, Responder};
+
+#[get("/badges/user/{id}")]
+async fn get_user_badges(params: web::Path<i64>, state: AppState) -> impl Responder {
+    let uid = params.into_inner();
+    match state.get_ref().get_user_badges(uid).await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/badges/{name}")]
+async fn get_badge_holders(
+    params: web::Path<String>,
+    form: web::Json<BadgeHoldersReq>,
+    state: AppState,
+) -> impl Responder {
+    let name = params.into_inner();
+    let form = form.into_inner();
+    match state.get_ref().get_badge_holders(&name, &form).await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_user_badges);
+    cfg.service(get_badge_holders);
+}
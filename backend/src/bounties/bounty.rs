@@ -0,0 +1,72 @@
+use chrono::{Duration, NaiveDate};
+
+// vote_type_id recorded when a bounty is started and when it is paid out or expires
+pub const BOUNTY_START_VOTE_TYPE: i32 = 8;
+pub const BOUNTY_CLOSE_VOTE_TYPE: i32 = 9;
+// post_notice_duration_id 1, a bounty runs for 7 days
+pub const BOUNTY_PERIOD_DAYS: i32 = 7;
+// an unawarded bounty pays half to the best answer posted after it started with at least this score
+pub const AUTO_AWARD_MIN_SCORE: i64 = 2;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StartBountyReq {
+    pub amount: i32,
+    // post notice type shown while the bounty runs, one of the bounty class
+    pub reason: Option<i64>,
+}
+
+// The BountyStart vote of a question that has not been awarded or expired yet
+#[derive(Debug)]
+pub struct ActiveBounty {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub amount: i32,
+    pub creation_date: Option<NaiveDate>,
+}
+
+impl ActiveBounty {
+    // the bounty expires on this day
+    pub fn end_date(&self) -> Option<NaiveDate> {
+        self.creation_date
+            .map(|d| d + Duration::days(BOUNTY_PERIOD_DAYS as i64))
+    }
+    pub fn notice(&self) -> BountyNotice {
+        BountyNotice {
+            amount: self.amount,
+            owner_user_id: self.user_id.unwrap_or_default().to_string(),
+            start_date: self.creation_date,
+            end_date: self.end_date(),
+        }
+    }
+}
+
+// Shown on the question while its bounty is active
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BountyNotice {
+    pub amount: i32,
+    pub owner_user_id: String,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AwardResponse {
+    pub answer_id: String,
+    pub amount: i32,
+}
+
+#[derive(Debug)]
+pub enum BountyOutcome<T> {
+    Done(T),
+    NotFound,
+    Forbidden,
+    Closed,
+    // starting a bounty on a question that already has one
+    Active,
+    // awarding on a question without an active bounty
+    NoBounty,
+    BadAmount,
+    // the reason is not a bounty notice type
+    BadReason,
+    NotEnoughReputation,
+}
@@ -0,0 +1,318 @@
+use super::bounty::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::notices::dao::{insert_notice, remove_notices};
+use crate::notices::notice::{BOUNTY_NOTICE_CLASS, DEFAULT_BOUNTY_NOTICE_TYPE};
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::state::{AppStateRaw, SqlTx};
+
+use std::time::Duration;
+
+#[async_trait]
+pub trait IBounty: std::ops::Deref<Target = AppStateRaw> {
+    async fn start_bounty(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        form: &StartBountyReq,
+    ) -> sqlx::Result<BountyOutcome<BountyNotice>>;
+    async fn award_bounty(
+        &self,
+        aid: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<BountyOutcome<AwardResponse>>;
+    async fn expire_bounties(&self) -> sqlx::Result<usize>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IBounty for &AppStateRaw {
+    async fn start_bounty(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        form: &StartBountyReq,
+    ) -> sqlx::Result<BountyOutcome<BountyNotice>> {
+        let amount = form.amount;
+        if amount < self.config.bounty_min_amount || amount > self.config.bounty_max_amount {
+            return Ok(BountyOutcome::BadAmount);
+        }
+
+        let mut tx = self.sql.begin().await?;
+
+        let q = sqlx::query!(
+            r#"
+            select closed_date from posts
+            where id = $1 and post_type_id = $2 and deletion_date is null
+            for update
+            "#,
+            qid,
+            QUESTION_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let q = match q {
+            Some(q) => q,
+            None => return Ok(BountyOutcome::NotFound),
+        };
+        if q.closed_date.is_some() {
+            return Ok(BountyOutcome::Closed);
+        }
+        if active_bounty(&mut tx, qid).await?.is_some() {
+            return Ok(BountyOutcome::Active);
+        }
+        let reason = form.reason.unwrap_or(DEFAULT_BOUNTY_NOTICE_TYPE);
+        let bounty_reason = sqlx::query!(
+            r#"
+            select exists(select 1 from post_notice_types where id = $1 and class_id = $2) as "ok!"
+            "#,
+            reason,
+            BOUNTY_NOTICE_CLASS
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .ok;
+        if !bounty_reason {
+            return Ok(BountyOutcome::BadReason);
+        }
+
+        // the bounty is held back from the sponsor until it is awarded or expires
+        let escrowed = sqlx::query!(
+            r#"
+            update users set reputation = reputation - $1 where id = $2 and reputation >= $1
+            "#,
+            amount as i64,
+            uid
+        )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        if escrowed != 1 {
+            return Ok(BountyOutcome::NotEnoughReputation);
+        }
+
+        let v = sqlx::query!(
+            r#"
+            insert into votes (post_id, vote_type_id, user_id, creation_date, bounty_amount,
+                voter_reputation_change)
+            values ($1, $2, $3, current_date, $4, $5)
+            returning id, creation_date
+            "#,
+            qid,
+            BOUNTY_START_VOTE_TYPE,
+            uid,
+            amount,
+            -(amount as i64)
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        let bounty = ActiveBounty {
+            id: v.id,
+            user_id: Some(uid),
+            amount,
+            creation_date: v.creation_date,
+        };
+        // the notice expires with the bounty
+        let expiry_date = bounty.end_date().and_then(|d| d.and_hms_opt(0, 0, 0));
+        insert_notice(&mut tx, qid, reason, None, expiry_date, uid, username).await?;
+
+        tx.commit().await?;
+
+        Ok(BountyOutcome::Done(bounty.notice()))
+    }
+
+    async fn award_bounty(
+        &self,
+        aid: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<BountyOutcome<AwardResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let a = sqlx::query!(
+            r#"
+            select a.owner_user_id, a.parent_id as "parent_id!" from posts a
+            join posts q on q.id = a.parent_id
+            where a.id = $1 and a.post_type_id = $2 and a.deletion_date is null
+            for update of q
+            "#,
+            aid,
+            ANSWER_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let a = match a {
+            Some(a) => a,
+            None => return Ok(BountyOutcome::NotFound),
+        };
+        let bounty = match active_bounty(&mut tx, a.parent_id).await? {
+            Some(b) => b,
+            None => return Ok(BountyOutcome::NoBounty),
+        };
+        if bounty.user_id != Some(uid) || a.owner_user_id == Some(uid) {
+            return Ok(BountyOutcome::Forbidden);
+        }
+
+        close_bounty(&mut tx, aid, a.owner_user_id, bounty.amount).await?;
+        remove_notices(
+            &mut tx,
+            a.parent_id,
+            BOUNTY_NOTICE_CLASS,
+            Some(uid),
+            Some(username),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(BountyOutcome::Done(AwardResponse {
+            answer_id: aid.to_string(),
+            amount: bounty.amount,
+        }))
+    }
+
+    async fn expire_bounties(&self) -> sqlx::Result<usize> {
+        let expired = sqlx::query!(
+            r#"
+            select v.post_id as "post_id!" from votes v
+            where v.vote_type_id = $1 and v.creation_date + $2::int <= current_date
+                and not exists (
+                    select 1 from votes c left join posts a on a.id = c.post_id
+                    where c.vote_type_id = $3 and c.id > v.id
+                        and (c.post_id = v.post_id or a.parent_id = v.post_id)
+                )
+            "#,
+            BOUNTY_START_VOTE_TYPE,
+            BOUNTY_PERIOD_DAYS,
+            BOUNTY_CLOSE_VOTE_TYPE
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let mut closed = 0;
+        for e in expired {
+            let mut tx = self.sql.begin().await?;
+
+            sqlx::query!(
+                r#"
+                select id from posts where id = $1 for update
+                "#,
+                e.post_id
+            )
+            .fetch_one(&mut tx)
+            .await?;
+
+            // it may have been awarded since the scan
+            let bounty = match active_bounty(&mut tx, e.post_id).await? {
+                Some(b) => b,
+                None => continue,
+            };
+
+            let best = sqlx::query!(
+                r#"
+                select id, owner_user_id from posts
+                where parent_id = $1 and post_type_id = $2 and deletion_date is null
+                    and score >= $3 and creation_date::date >= $4
+                    and owner_user_id is distinct from $5
+                order by score desc, creation_date asc limit 1
+                "#,
+                e.post_id,
+                ANSWER_POST_TYPE,
+                AUTO_AWARD_MIN_SCORE,
+                bounty.creation_date,
+                bounty.user_id
+            )
+            .fetch_optional(&mut tx)
+            .await?;
+
+            match best {
+                Some(a) => close_bounty(&mut tx, a.id, a.owner_user_id, bounty.amount / 2).await?,
+                None => close_bounty(&mut tx, e.post_id, None, 0).await?,
+            }
+            remove_notices(&mut tx, e.post_id, BOUNTY_NOTICE_CLASS, None, None).await?;
+
+            tx.commit().await?;
+            closed += 1;
+        }
+
+        Ok(closed)
+    }
+}
+
+// The bounty started on `qid` that no BountyClose vote on the question or one of its answers has ended
+pub async fn active_bounty<'e, E>(e: E, qid: i64) -> sqlx::Result<Option<ActiveBounty>>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query_as!(
+        ActiveBounty,
+        r#"
+        select v.id, v.user_id, coalesce(v.bounty_amount, 0) as "amount!", v.creation_date
+        from votes v
+        where v.post_id = $1 and v.vote_type_id = $2
+            and not exists (
+                select 1 from votes c left join posts a on a.id = c.post_id
+                where c.vote_type_id = $3 and c.id > v.id and (c.post_id = $1 or a.parent_id = $1)
+            )
+        order by v.id desc limit 1
+        "#,
+        qid,
+        BOUNTY_START_VOTE_TYPE,
+        BOUNTY_CLOSE_VOTE_TYPE
+    )
+    .fetch_optional(e)
+    .await
+}
+
+// Ends the active bounty with a BountyClose vote on `pid`, paying `amount` to its owner
+async fn close_bounty(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    owner: Option<i64>,
+    amount: i32,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        insert into votes (post_id, vote_type_id, creation_date, bounty_amount, owner_reputation_change)
+        values ($1, $2, current_date, $3, $4)
+        "#,
+        pid,
+        BOUNTY_CLOSE_VOTE_TYPE,
+        amount,
+        amount as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        update users set reputation = coalesce(reputation, 0) + $1 where id = $2
+        "#,
+        amount as i64,
+        owner
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+// Closes bounties whose period has run out, every Config::bounty_check_interval seconds
+pub async fn expire_bounties_task(state: AppStateRaw) {
+    let mut interval =
+        actix_rt::time::interval(Duration::from_secs(state.config.bounty_check_interval));
+    loop {
+        interval.tick().await;
+        match (&state).expire_bounties().await {
+            Ok(0) => {}
+            Ok(n) => info!("closed {} expired bounties", n),
+            Err(e) => error!("expire bounties error: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod bounty;
+pub mod dao;
+pub mod routes;
@@ -0,0 +1,86 @@
+use super::bounty::*;
+use super::dao::IBounty;
+use crate::api::ApiResult;
+use crate::middlewares::auth::AuthorizationService;
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+use serde::Serialize;
+
+#[post("/question/{id}/bounty")]
+async fn start_bounty(
+    params: web::Path<i64>,
+    form: web::Json<StartBountyReq>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.into_inner();
+    let form = form.into_inner();
+
+    match state
+        .get_ref()
+        .start_bounty(qid, auth.claims.id, &auth.claims.username, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("start bounty {} {:?} error: {:?}", qid, form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/award-bounty/{id}")]
+async fn award_bounty(
+    params: web::Path<i64>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let aid = params.into_inner();
+
+    match state
+        .get_ref()
+        .award_bounty(aid, auth.claims.id, &auth.claims.username)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("award bounty {} error: {:?}", aid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result<T: Serialize>(outcome: BountyOutcome<T>, state: &AppState) -> ApiResult<T> {
+    match outcome {
+        BountyOutcome::Done(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        BountyOutcome::NotFound => ApiResult::new().code(404).with_msg("Post not found"),
+        BountyOutcome::Forbidden => ApiResult::new()
+            .code(403)
+            .with_msg("Only the bounty sponsor can award it, and not to their own answer"),
+        BountyOutcome::Closed => ApiResult::new()
+            .code(409)
+            .with_msg("Bounties cannot be started on closed questions"),
+        BountyOutcome::Active => ApiResult::new()
+            .code(409)
+            .with_msg("The question already has an active bounty"),
+        BountyOutcome::NoBounty => ApiResult::new()
+            .code(409)
+            .with_msg("The question has no active bounty"),
+        BountyOutcome::BadAmount => ApiResult::new().code(400).with_msg(format!(
+            "The bounty must be between {} and {} reputation",
+            state.config.bounty_min_amount, state.config.bounty_max_amount
+        )),
+        BountyOutcome::BadReason => ApiResult::new()
+            .code(400)
+            .with_msg("The reason must be a bounty notice type"),
+        BountyOutcome::NotEnoughReputation => ApiResult::new()
+            .code(403)
+            .with_msg("You do not have enough reputation for this bounty"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(start_bounty);
+    cfg.service(award_bounty);
+}
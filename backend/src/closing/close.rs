@@ -0,0 +1,47 @@
+// post_history_type_id written when a question is closed or reopened
+pub const POST_CLOSED: i64 = 10;
+pub const POST_REOPENED: i64 = 11;
+// post_links.link_type_id of a duplicate
+pub const DUPLICATE_LINK_TYPE: i32 = 3;
+// name of the configured close reason that requires the original question
+pub const DUPLICATE_CLOSE_REASON: &str = "duplicate";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CloseReq {
+    pub reason_id: i32,
+    #[serde(default)]
+    pub duplicate_of: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CloseVoteResponse {
+    pub votes: i64,
+    pub closed: bool,
+}
+
+// JSON kept in post_history.text listing the users who voted for an event
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct HistoryVoters {
+    pub voters: Vec<Voter>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub original_question_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct Voter {
+    pub id: i64,
+    pub display_name: String,
+}
+
+#[derive(Debug)]
+pub enum CloseOutcome {
+    Voted(CloseVoteResponse),
+    NotFound,
+    // closing a closed question or reopening an open one
+    Unchanged,
+    AlreadyVoted,
+    BadReason,
+    BadDuplicate,
+}
@@ -0,0 +1,343 @@
+use super::close::*;
+use crate::config::Config;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Change;
+use crate::state::{AppStateRaw, SqlTx};
+
+#[async_trait]
+pub trait ICloseVote: std::ops::Deref<Target = AppStateRaw> {
+    async fn close_vote(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        binding: bool,
+        form: &CloseReq,
+    ) -> sqlx::Result<CloseOutcome>;
+    async fn reopen_vote(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        binding: bool,
+    ) -> sqlx::Result<CloseOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl ICloseVote for &AppStateRaw {
+    async fn close_vote(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        binding: bool,
+        form: &CloseReq,
+    ) -> sqlx::Result<CloseOutcome> {
+        let mut tx = self.sql.begin().await?;
+        let outcome =
+            close_vote_tx(&mut tx, &self.config, qid, uid, username, binding, form).await?;
+        if let CloseOutcome::Voted(_) = outcome {
+            tx.commit().await?;
+        }
+
+        Ok(outcome)
+    }
+
+    async fn reopen_vote(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        binding: bool,
+    ) -> sqlx::Result<CloseOutcome> {
+        let mut tx = self.sql.begin().await?;
+        let outcome = reopen_vote_tx(&mut tx, &self.config, qid, uid, username, binding).await?;
+        if let CloseOutcome::Voted(_) = outcome {
+            tx.commit().await?;
+        }
+
+        Ok(outcome)
+    }
+}
+
+// Locks the question and tells whether it is closed, None if it does not exist
+async fn lock_question(tx: &mut SqlTx<'_>, qid: i64) -> sqlx::Result<Option<bool>> {
+    let q = sqlx::query!(
+        r#"
+        select closed_date from posts where id = $1 and post_type_id = $2 and deletion_date is null
+        for update
+        "#,
+        qid,
+        QUESTION_POST_TYPE
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    Ok(q.map(|q| q.closed_date.is_some()))
+}
+
+// Records the vote and returns the number of pending votes, None if the user already voted
+async fn cast_vote(
+    tx: &mut SqlTx<'_>,
+    qid: i64,
+    uid: i64,
+    reopen: bool,
+    reason_id: Option<i32>,
+    duplicate_of: Option<i64>,
+) -> sqlx::Result<Option<i64>> {
+    let r = sqlx::query!(
+        r#"
+        insert into close_votes (post_id, user_id, reopen, close_reason_id, duplicate_of)
+        values ($1, $2, $3, $4, $5)
+        on conflict do nothing
+        "#,
+        qid,
+        uid,
+        reopen,
+        reason_id,
+        duplicate_of
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if r.rows_affected() != 1 {
+        return Ok(None);
+    }
+
+    let votes = sqlx::query!(
+        r#"
+        select count(1) as "count!" from close_votes where post_id = $1 and reopen = $2
+        "#,
+        qid,
+        reopen
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .count;
+
+    Ok(Some(votes))
+}
+
+async fn voters(tx: &mut SqlTx<'_>, qid: i64, reopen: bool) -> sqlx::Result<Vec<Voter>> {
+    let rows = sqlx::query!(
+        r#"
+        select u.id, u.display_name from close_votes cv join users u on u.id = cv.user_id
+        where cv.post_id = $1 and cv.reopen = $2
+        order by cv.creation_date asc
+        "#,
+        qid,
+        reopen
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Voter {
+            id: r.id,
+            display_name: r.display_name.unwrap_or_default(),
+        })
+        .collect())
+}
+
+// Writes the close or reopen history row and clears the pending votes that led to it
+async fn record_event(
+    tx: &mut SqlTx<'_>,
+    qid: i64,
+    uid: i64,
+    username: &str,
+    post_history_type_id: i64,
+    reason: Option<i32>,
+    voters: &HistoryVoters,
+) -> sqlx::Result<()> {
+    let text = serde_json::to_string(voters).unwrap_or_default();
+    let comment = reason.map(|r| r.to_string());
+    let change = Change {
+        post_history_type_id,
+        text: &text,
+    };
+    record_revision(&mut *tx, qid, uid, username, comment.as_deref(), &[change]).await?;
+
+    sqlx::query!(
+        r#"
+        delete from close_votes where post_id = $1 and reopen = $2
+        "#,
+        qid,
+        post_history_type_id == POST_REOPENED
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+// Votes to close `qid` in the caller's transaction, which is only to be committed
+// when the outcome is Voted
+pub async fn close_vote_tx(
+    tx: &mut SqlTx<'_>,
+    config: &Config,
+    qid: i64,
+    uid: i64,
+    username: &str,
+    binding: bool,
+    form: &CloseReq,
+) -> sqlx::Result<CloseOutcome> {
+    if !config.close_reasons.iter().any(|r| r.id == form.reason_id) {
+        return Ok(CloseOutcome::BadReason);
+    }
+    let duplicate_reason = config.close_reason_id(DUPLICATE_CLOSE_REASON);
+    let duplicate_of = match (Some(form.reason_id) == duplicate_reason, form.duplicate_of) {
+        (true, Some(original)) if original != qid => Some(original),
+        (false, None) => None,
+        _ => return Ok(CloseOutcome::BadDuplicate),
+    };
+
+    let closed = match lock_question(tx, qid).await? {
+        Some(closed) => closed,
+        None => return Ok(CloseOutcome::NotFound),
+    };
+    if closed {
+        return Ok(CloseOutcome::Unchanged);
+    }
+
+    if let Some(original) = duplicate_of {
+        let exists = sqlx::query!(
+            r#"
+            select id from posts where id = $1 and post_type_id = $2 and deletion_date is null
+            "#,
+            original,
+            QUESTION_POST_TYPE
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if exists.is_none() {
+            return Ok(CloseOutcome::BadDuplicate);
+        }
+    }
+
+    let votes = match cast_vote(tx, qid, uid, false, Some(form.reason_id), duplicate_of).await? {
+        Some(votes) => votes,
+        None => return Ok(CloseOutcome::AlreadyVoted),
+    };
+
+    let close = binding || votes >= config.close_vote_threshold;
+    if close {
+        let reason = sqlx::query!(
+            r#"
+            select close_reason_id from close_votes where post_id = $1 and not reopen
+            group by close_reason_id order by count(1) desc, min(creation_date) asc limit 1
+            "#,
+            qid
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .close_reason_id
+        .unwrap_or(form.reason_id);
+
+        let mut text = HistoryVoters {
+            voters: voters(tx, qid, false).await?,
+            ..Default::default()
+        };
+        if Some(reason) == duplicate_reason {
+            text.original_question_ids = sqlx::query!(
+                r#"
+                select distinct duplicate_of as "duplicate_of!" from close_votes
+                where post_id = $1 and not reopen and duplicate_of is not null
+                "#,
+                qid
+            )
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|r| r.duplicate_of)
+            .collect();
+
+            sqlx::query!(
+                r#"
+                insert into post_links (creation_date, post_id, related_post_id, link_type_id)
+                select now(), $1, unnest($2::bigint[]), $3
+                "#,
+                qid,
+                &text.original_question_ids,
+                DUPLICATE_LINK_TYPE
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            update posts set closed_date = now() where id = $1
+            "#,
+            qid
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        record_event(tx, qid, uid, username, POST_CLOSED, Some(reason), &text).await?;
+    }
+
+    Ok(CloseOutcome::Voted(CloseVoteResponse {
+        votes,
+        closed: close,
+    }))
+}
+
+// Votes to reopen `qid` like close_vote_tx
+pub async fn reopen_vote_tx(
+    tx: &mut SqlTx<'_>,
+    config: &Config,
+    qid: i64,
+    uid: i64,
+    username: &str,
+    binding: bool,
+) -> sqlx::Result<CloseOutcome> {
+    let closed = match lock_question(tx, qid).await? {
+        Some(closed) => closed,
+        None => return Ok(CloseOutcome::NotFound),
+    };
+    if !closed {
+        return Ok(CloseOutcome::Unchanged);
+    }
+
+    let votes = match cast_vote(tx, qid, uid, true, None, None).await? {
+        Some(votes) => votes,
+        None => return Ok(CloseOutcome::AlreadyVoted),
+    };
+
+    let reopen = binding || votes >= config.close_vote_threshold;
+    if reopen {
+        let text = HistoryVoters {
+            voters: voters(tx, qid, true).await?,
+            ..Default::default()
+        };
+
+        sqlx::query!(
+            r#"
+            delete from post_links where post_id = $1 and link_type_id = $2
+            "#,
+            qid,
+            DUPLICATE_LINK_TYPE
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            update posts set closed_date = null where id = $1
+            "#,
+            qid
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        record_event(tx, qid, uid, username, POST_REOPENED, None, &text).await?;
+    }
+
+    Ok(CloseOutcome::Voted(CloseVoteResponse {
+        votes,
+        closed: !reopen,
+    }))
+}
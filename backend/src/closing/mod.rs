@@ -0,0 +1,3 @@
+pub mod close;
+pub mod dao;
+pub mod routes;
@@ -0,0 +1,88 @@
+use super::close::*;
+use super::dao::ICloseVote;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{get, post, web, Responder};
+
+#[get("/close-reasons")]
+async fn get_close_reasons(state: AppState) -> impl Responder {
+    let reasons = state.config.close_reasons.clone();
+    ApiResult::new().code(200).with_msg("").with_data(reasons)
+}
+
+#[post("/close/{id}")]
+async fn close_question(
+    params: web::Path<i64>,
+    form: web::Json<CloseReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = auth.require(Privilege::CloseVote, &state.config.privileges) {
+        return e;
+    }
+    let binding = auth.moderator;
+    match state
+        .get_ref()
+        .close_vote(qid, auth.claims.id, &auth.claims.username, binding, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("close {} {:?} error: {:?}", qid, form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/reopen/{id}")]
+async fn reopen_question(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.into_inner();
+
+    if let Err(e) = auth.require(Privilege::CloseVote, &state.config.privileges) {
+        return e;
+    }
+    let binding = auth.moderator;
+    match state
+        .get_ref()
+        .reopen_vote(qid, auth.claims.id, &auth.claims.username, binding)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("reopen {} error: {:?}", qid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result(outcome: CloseOutcome) -> ApiResult<CloseVoteResponse> {
+    match outcome {
+        CloseOutcome::Voted(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        CloseOutcome::NotFound => ApiResult::new().code(404).with_msg("Question not found"),
+        CloseOutcome::Unchanged => ApiResult::new()
+            .code(409)
+            .with_msg("The question is already in that state"),
+        CloseOutcome::AlreadyVoted => ApiResult::new()
+            .code(409)
+            .with_msg("You have already voted on this question"),
+        CloseOutcome::BadReason => ApiResult::new().code(400).with_msg("Unknown close reason"),
+        CloseOutcome::BadDuplicate => ApiResult::new()
+            .code(400)
+            .with_msg("A duplicate needs another existing question as the original"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_close_reasons);
+    cfg.service(close_question);
+    cfg.service(reopen_question);
+}
@@ -0,0 +1,57 @@
+use chrono::NaiveDateTime;
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct PostComment {
+    // the schema limits comments to 1024 characters
+    #[validate(length(min = 15, max = 1024))]
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentVoteReq {
+    pub upvote: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentResponse {
+    pub id: String,
+    pub post_id: String,
+    pub text: String,
+    pub score: i32,
+    pub user_id: String,
+    pub user_display_name: String,
+    pub creation_date: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentsResponse {
+    pub comments: Vec<CommentResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentScoreResponse {
+    pub score: i32,
+}
+
+#[derive(Debug)]
+pub enum CommentOutcome<T> {
+    Done(T),
+    NotFound,
+    Forbidden,
+    // the edit window of the comment has passed
+    Expired,
+    // comments of locked posts are frozen
+    Locked,
+}
+
+impl<T> CommentOutcome<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> CommentOutcome<U> {
+        match self {
+            CommentOutcome::Done(t) => CommentOutcome::Done(f(t)),
+            CommentOutcome::NotFound => CommentOutcome::NotFound,
+            CommentOutcome::Forbidden => CommentOutcome::Forbidden,
+            CommentOutcome::Expired => CommentOutcome::Expired,
+            CommentOutcome::Locked => CommentOutcome::Locked,
+        }
+    }
+}
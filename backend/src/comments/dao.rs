@@ -0,0 +1,295 @@
+use super::comment::*;
+use crate::state::AppStateRaw;
+
+#[async_trait]
+pub trait IComment: std::ops::Deref<Target = AppStateRaw> {
+    async fn insert_comment(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        everywhere: bool,
+        form: &PostComment,
+    ) -> sqlx::Result<CommentOutcome<i64>>;
+    async fn update_comment(
+        &self,
+        cid: i64,
+        uid: i64,
+        form: &PostComment,
+    ) -> sqlx::Result<CommentOutcome<bool>>;
+    async fn delete_comment(&self, cid: i64, uid: i64) -> sqlx::Result<CommentOutcome<bool>>;
+    async fn get_comments(
+        &self,
+        pid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<CommentsResponse>;
+    async fn vote_comment(
+        &self,
+        cid: i64,
+        uid: i64,
+        upvote: bool,
+    ) -> sqlx::Result<CommentOutcome<CommentScoreResponse>>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IComment for &AppStateRaw {
+    async fn insert_comment(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        everywhere: bool,
+        form: &PostComment,
+    ) -> sqlx::Result<CommentOutcome<i64>> {
+        let mut tx = self.sql.begin().await?;
+
+        let post = sqlx::query!(
+            r#"
+            update posts p set comment_count = coalesce(p.comment_count, 0) + 1
+            where p.id = $1 and p.deletion_date is null
+            returning p.owner_user_id, p.locked_date,
+                (select q.owner_user_id from posts q where q.id = p.parent_id) as parent_owner_id
+            "#,
+            pid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let post = match post {
+            Some(p) => p,
+            None => return Ok(CommentOutcome::NotFound),
+        };
+        if post.locked_date.is_some() {
+            return Ok(CommentOutcome::Locked);
+        }
+        // without the privilege users comment on their posts and answers to their questions
+        if !everywhere && post.owner_user_id != Some(uid) && post.parent_owner_id != Some(uid) {
+            return Ok(CommentOutcome::Forbidden);
+        }
+
+        let r = sqlx::query!(
+            r#"
+            insert into comments (post_id, text, user_id, user_display_name, score)
+            values ($1, $2, $3, $4, 0) returning id
+            "#,
+            pid,
+            form.text,
+            uid,
+            username
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(CommentOutcome::Done(r.id))
+    }
+
+    async fn update_comment(
+        &self,
+        cid: i64,
+        uid: i64,
+        form: &PostComment,
+    ) -> sqlx::Result<CommentOutcome<bool>> {
+        let c = sqlx::query!(
+            r#"
+            select c.user_id, c.creation_date + make_interval(secs => $2) > now() as "editable!",
+                p.locked_date
+            from comments c join posts p on p.id = c.post_id
+            where c.id = $1 and c.deletion_date is null
+            "#,
+            cid,
+            self.config.comment_edit_window as f64
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        let c = match c {
+            Some(c) => c,
+            None => return Ok(CommentOutcome::NotFound),
+        };
+        if c.user_id != Some(uid) {
+            return Ok(CommentOutcome::Forbidden);
+        }
+        if c.locked_date.is_some() {
+            return Ok(CommentOutcome::Locked);
+        }
+        if !c.editable {
+            return Ok(CommentOutcome::Expired);
+        }
+
+        sqlx::query!(
+            r#"
+            update comments set text = $1 where id = $2
+            "#,
+            form.text,
+            cid
+        )
+        .execute(&self.sql)
+        .await?;
+
+        Ok(CommentOutcome::Done(true))
+    }
+
+    async fn delete_comment(&self, cid: i64, uid: i64) -> sqlx::Result<CommentOutcome<bool>> {
+        let mut tx = self.sql.begin().await?;
+
+        let c = sqlx::query!(
+            r#"
+            select c.post_id, c.user_id, p.locked_date
+            from comments c join posts p on p.id = c.post_id
+            where c.id = $1 and c.deletion_date is null
+            for update of c
+            "#,
+            cid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let c = match c {
+            Some(c) => c,
+            None => return Ok(CommentOutcome::NotFound),
+        };
+        if c.user_id != Some(uid) {
+            return Ok(CommentOutcome::Forbidden);
+        }
+        if c.locked_date.is_some() {
+            return Ok(CommentOutcome::Locked);
+        }
+
+        sqlx::query!(
+            r#"
+            update comments set deletion_date = now() where id = $1
+            "#,
+            cid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            update posts set comment_count = coalesce(comment_count, 0) - 1 where id = $1
+            "#,
+            c.post_id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(CommentOutcome::Done(true))
+    }
+
+    // comments of deleted posts stay visible to the post author and moderators
+    async fn get_comments(
+        &self,
+        pid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<CommentsResponse> {
+        let cr = sqlx::query!(
+            r#"
+            select c.id, c.post_id, c.text, c.score, c.user_id, c.user_display_name,
+                c.creation_date
+            from comments c join posts p on p.id = c.post_id
+            where c.post_id = $1 and c.deletion_date is null
+                and (p.deletion_date is null or $2 or p.owner_user_id = $3)
+            order by c.creation_date asc, c.id asc
+            "#,
+            pid,
+            moderator,
+            viewer
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let comments = cr
+            .into_iter()
+            .map(|c| CommentResponse {
+                id: c.id.to_string(),
+                post_id: c.post_id.unwrap_or_default().to_string(),
+                text: c.text.unwrap_or_default(),
+                score: c.score.unwrap_or_default(),
+                user_id: c.user_id.unwrap_or_default().to_string(),
+                user_display_name: c.user_display_name.unwrap_or_default(),
+                creation_date: c.creation_date,
+            })
+            .collect();
+
+        Ok(CommentsResponse { comments })
+    }
+
+    async fn vote_comment(
+        &self,
+        cid: i64,
+        uid: i64,
+        upvote: bool,
+    ) -> sqlx::Result<CommentOutcome<CommentScoreResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let c = sqlx::query!(
+            r#"
+            select c.user_id, coalesce(c.score, 0) as "score!", p.locked_date
+            from comments c join posts p on p.id = c.post_id
+            where c.id = $1 and c.deletion_date is null
+            for update of c
+            "#,
+            cid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let c = match c {
+            Some(c) => c,
+            None => return Ok(CommentOutcome::NotFound),
+        };
+        if c.user_id == Some(uid) {
+            return Ok(CommentOutcome::Forbidden);
+        }
+        if c.locked_date.is_some() {
+            return Ok(CommentOutcome::Locked);
+        }
+
+        let changed = if upvote {
+            sqlx::query!(
+                r#"
+                insert into comment_votes (comment_id, user_id) values ($1, $2)
+                on conflict do nothing
+                "#,
+                cid,
+                uid
+            )
+            .execute(&mut tx)
+            .await?
+            .rows_affected() as i32
+        } else {
+            -(sqlx::query!(
+                r#"
+                delete from comment_votes where comment_id = $1 and user_id = $2
+                "#,
+                cid,
+                uid
+            )
+            .execute(&mut tx)
+            .await?
+            .rows_affected() as i32)
+        };
+
+        let score = c.score + changed;
+        sqlx::query!(
+            r#"
+            update comments set score = $1 where id = $2
+            "#,
+            score,
+            cid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(CommentOutcome::Done(CommentScoreResponse { score }))
+    }
+}
@@ -0,0 +1,146 @@
+use super::comment::*;
+use super::dao::IComment;
+use crate::api::ApiResult;
+use crate::middlewares::auth::AuthorizationService;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+use crate::users::dao::IUser;
+
+use actix_web::{get, post, web, Responder};
+use serde::Serialize;
+use validator::Validate;
+
+#[post("/post/{id}/comment")]
+async fn create_comment(
+    params: web::Path<i64>,
+    form: web::Json<PostComment>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("create comment {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    let privileges = &state.config.privileges;
+    let everywhere = auth.has(Privilege::CommentEverywhere, privileges);
+    match state
+        .get_ref()
+        .insert_comment(pid, auth.claims.id, &auth.claims.username, everywhere, &form)
+        .await
+    {
+        Ok(CommentOutcome::Forbidden) => Privilege::CommentEverywhere.denied(privileges),
+        Ok(outcome) => outcome_to_result(outcome.map(|id| id.to_string())),
+        Err(e) => {
+            error!("create comment {:?} error: {:?}", form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/edit-comment/{id}")]
+async fn edit_comment(
+    params: web::Path<i64>,
+    form: web::Json<PostComment>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let cid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("edit comment {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    match state
+        .get_ref()
+        .update_comment(cid, auth.claims.id, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/delete-comment/{id}")]
+async fn delete_comment(
+    params: web::Path<i64>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let cid = params.into_inner();
+    match state.get_ref().delete_comment(cid, auth.claims.id).await {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[get("/post/{id}/comments")]
+async fn get_comments(
+    params: web::Path<i64>,
+    auth: Option<AuthorizationService>,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+    let viewer = auth.map(|a| a.claims.id);
+    let moderator = match viewer {
+        Some(uid) => state.get_ref().is_moderator(uid).await.unwrap_or(false),
+        None => false,
+    };
+    match state.get_ref().get_comments(pid, viewer, moderator).await {
+        Ok(crs) => ApiResult::new().code(200).with_msg("").with_data(crs),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+#[post("/vote-comment/{id}")]
+async fn vote_comment(
+    params: web::Path<i64>,
+    form: web::Json<CommentVoteReq>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let cid = params.into_inner();
+    match state
+        .get_ref()
+        .vote_comment(cid, auth.claims.id, form.upvote)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("vote comment {} error: {:?}", cid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result<T: Serialize>(outcome: CommentOutcome<T>) -> ApiResult<T> {
+    match outcome {
+        CommentOutcome::Done(data) => ApiResult::new().code(200).with_msg("").with_data(data),
+        CommentOutcome::NotFound => ApiResult::new().code(404).with_msg("Not found"),
+        CommentOutcome::Forbidden => ApiResult::new().code(403).with_msg("Not allowed"),
+        CommentOutcome::Expired => ApiResult::new()
+            .code(403)
+            .with_msg("Comments can only be edited shortly after posting"),
+        CommentOutcome::Locked => ApiResult::new().code(403).with_msg("This post is locked"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_comment);
+    cfg.service(edit_comment);
+    cfg.service(delete_comment);
+    cfg.service(get_comments);
+    cfg.service(vote_comment);
+}
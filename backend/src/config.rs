@@ -1,3 +1,4 @@
+use crate::sessions::keys::KeySet;
 use crate::state::*;
 use crate::state::{redis::Client, KvPool, RedisConnectionManager};
 
@@ -9,7 +10,11 @@ pub struct Config {
     pub sql: String,
     pub redis: String,
     pub listen: String,
+    // HS256 secret of tokens without a kid, it signs when jwt_keys is empty
     pub jwt_priv: String,
+    // the first key that is not retired signs, retired keys verify until their tokens expire
+    #[serde(default)]
+    pub jwt_keys: Vec<JwtKey>,
     pub mail_host: String,
     pub mail_port: u16,
     pub mail_username: String,
@@ -19,21 +24,167 @@ pub struct Config {
     pub host: String,
     pub secret_key: String,
     pub email_verification_expiry_time: u64,
+    // seconds a password reset link stays valid
+    pub password_reset_expiry_time: u64,
+    // seconds an access token is valid, clients then renew it with their refresh token
+    pub access_token_lifetime: i64,
+    // seconds a session and its refresh token last without and with remember me,
+    // counted again from each refresh
+    pub session_lifetime: i64,
+    pub remember_me_lifetime: i64,
     pub questions_per_page: i32,
+    pub answers_per_page: i64,
     pub users_per_page: i32,
     pub tags_per_page: i64,
+    // seconds after creation during which a comment can be edited
+    pub comment_edit_window: i64,
+    // reputation given to the post owner on an upvote
     pub vote_karma_gain: i64,
+    // reputation given to the post owner on a downvote, negative
     pub vote_karma_loss: i64,
-    pub downvote_karma_loss: i64
+    // reputation given to the voter on a downvote, negative
+    pub downvote_karma_loss: i64,
+    // close votes needed to close or reopen a question, moderators close at once
+    pub close_vote_threshold: i64,
+    pub close_reasons: Vec<CloseReason>,
+    // deletion or undeletion votes needed on a post, moderators delete at once
+    pub delete_vote_threshold: i64,
+    // reputation a bounty may offer
+    pub bounty_min_amount: i32,
+    pub bounty_max_amount: i32,
+    // seconds between checks for expired bounties
+    pub bounty_check_interval: u64,
+    // seconds between checks for expired post notices
+    pub notice_check_interval: u64,
+    // reputation needed for each privilege, moderators hold them all
+    pub privileges: Privileges,
+    // answer score in the target tag needed to propose or vote on a tag synonym
+    pub tag_synonym_min_score: i64,
+    // reviews needed to apply or reject a suggested edit
+    pub suggested_edit_approvals: i64,
+    pub suggested_edit_rejections: i64,
+    // reputation awarded to the author of an approved suggested edit
+    pub suggested_edit_reputation: i64,
+    // pending spam and rude flags that delete a post or comment
+    pub flag_auto_delete: i64,
+    // reputation given to the author of a post deleted by flags, negative
+    pub flag_penalty: i64,
+    // reviews a user can complete per day, skips excluded
+    pub review_daily_limit: i64,
+    // reviews other than skips after which a post leaves a review queue
+    pub review_completions: i64,
+    // days after the question from which an answer goes to the late answers queue
+    pub late_answer_days: i32,
+    // argon2 parameters for new password hashes, older hashes are redone on login
+    pub password_hashing: PasswordHashing,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CloseReason {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Privileges {
+    // answer protected questions
+    pub answer_protected: i64,
+    // comment on posts other than your own and answers to your questions
+    pub comment_everywhere: i64,
+    // flag posts and comments for spam, rudeness or moderator attention
+    pub flag_posts: i64,
+    pub upvote: i64,
+    pub downvote: i64,
+    // edit community wiki posts of others directly
+    pub edit_community_wiki: i64,
+    // edit posts of others directly
+    pub edit_without_review: i64,
+    pub close_vote: i64,
+    // vote to delete and undelete posts of others
+    pub moderation_tools: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PasswordHashing {
+    // argon2i, argon2d or argon2id
+    pub variant: String,
+    // KiB
+    pub mem_cost: u32,
+    pub time_cost: u32,
+    pub lanes: u32,
+    // random bytes per user
+    pub salt_length: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct JwtKey {
+    pub kid: String,
+    // HS256, RS256 or EdDSA
+    pub algorithm: String,
+    // HS256 only
+    #[serde(default)]
+    pub secret: String,
+    // PEM files of RS256 and EdDSA keys, retired keys need no private key
+    pub private_key: Option<PathBuf>,
+    pub public_key: Option<PathBuf>,
+    #[serde(default)]
+    pub retired: bool,
+}
+
+impl PasswordHashing {
+    pub fn argon2(&self) -> argon2::Config<'static> {
+        argon2::Config {
+            variant: argon2::Variant::from_str(&self.variant).expect("password_hashing.variant"),
+            mem_cost: self.mem_cost,
+            time_cost: self.time_cost,
+            lanes: self.lanes,
+            ..argon2::Config::default()
+        }
+    }
+    // checked once at startup so that hashing in the handlers cannot fail
+    pub fn validate(&self) -> Result<(), String> {
+        argon2::Variant::from_str(&self.variant).map_err(|e| format!("variant: {}", e))?;
+        let salt = vec![1u8; self.salt_length];
+        argon2::hash_encoded(b"password", &salt, &self.argon2())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+    // whether an encoded hash uses these parameters and a salt of this length,
+    // the salts of old hashes were all zeros
+    pub fn is_current(&self, hash: &str) -> bool {
+        let config = self.argon2();
+        let params = format!(
+            "${}$v={}$m={},t={},p={}$",
+            config.variant.as_lowercase_str(),
+            config.version.as_u32(),
+            config.mem_cost,
+            config.time_cost,
+            config.lanes
+        );
+        let salt = match hash.strip_prefix(&params).and_then(|s| s.split('$').next()) {
+            Some(salt) => salt,
+            None => return false,
+        };
+        // unpadded base64
+        salt.len() == (self.salt_length * 4).div_ceil(3) && salt.chars().any(|c| c != 'A')
+    }
 }
 
 impl Config {
+    // id of the close reason with this name
+    pub fn close_reason_id(&self, name: &str) -> Option<i32> {
+        self.close_reasons.iter().find(|r| r.name == name).map(|r| r.id)
+    }
     pub fn parse_from_file(file: &PathBuf) -> Self {
         use std::fs::read_to_string;
 
         info!("confp: {}", file.display());
         let confstr = read_to_string(file).expect("confile read");
-        json5::from_str(&confstr).expect("confile deser")
+        let config: Self = json5::from_str(&confstr).expect("confile deser");
+        if let Err(e) = config.password_hashing.validate() {
+            panic!("password_hashing: {}", e);
+        }
+        config
     }
     pub async fn into_state(self) -> AppStateRaw {
         info!("config: {:?}", self);
@@ -43,11 +194,13 @@ impl Config {
         let kvm =
             RedisConnectionManager::new(Client::open(self.redis.clone()).expect("redis open"));
         let kv = KvPool::builder().build(kvm);
+        let keys = KeySet::load(&self).expect("jwt keys");
 
         Arc::new(State {
             config: self,
             sql,
             kv,
+            keys,
         })
     }
     // generate and show config string
@@ -84,6 +237,10 @@ pub struct Opts {
         default_value = "template.json"
     )]
     pub config: PathBuf,
+
+    /// Recompute badges from existing posts and exit
+    #[clap(long = "backfill-badges")]
+    pub backfill_badges: bool,
 }
 
 impl Opts {
@@ -152,3 +309,61 @@ pub fn format(base: &BaseFormater, record: &Record) -> String {
         record.args()
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashing() -> PasswordHashing {
+        PasswordHashing {
+            variant: "argon2id".to_owned(),
+            mem_cost: 64,
+            time_cost: 1,
+            lanes: 1,
+            salt_length: 16,
+        }
+    }
+
+    fn hash(hashing: &PasswordHashing, salt: &[u8]) -> String {
+        argon2::hash_encoded(b"password", salt, &hashing.argon2()).unwrap()
+    }
+
+    #[test]
+    fn current_hash_is_current() {
+        let hashing = hashing();
+        assert!(hashing.validate().is_ok());
+        assert!(hashing.is_current(&hash(&hashing, b"0123456789abcdef")));
+    }
+
+    #[test]
+    fn zero_salt_hash_is_outdated() {
+        let hashing = hashing();
+        assert!(!hashing.is_current(&hash(&hashing, &[0u8; 16])));
+    }
+
+    #[test]
+    fn other_parameters_or_salt_length_are_outdated() {
+        let hashing = hashing();
+        let old = PasswordHashing {
+            time_cost: 2,
+            ..hashing.clone()
+        };
+        assert!(!hashing.is_current(&hash(&old, b"0123456789abcdef")));
+        assert!(!hashing.is_current(&hash(&hashing, b"01234567")));
+        assert!(!hashing.is_current("not a hash"));
+    }
+
+    #[test]
+    fn validate_rejects_bad_settings() {
+        let bad_variant = PasswordHashing {
+            variant: "argon3".to_owned(),
+            ..hashing()
+        };
+        let short_salt = PasswordHashing {
+            salt_length: 4,
+            ..hashing()
+        };
+        assert!(bad_variant.validate().is_err());
+        assert!(short_salt.validate().is_err());
+    }
+}
@@ -0,0 +1,330 @@
+use super::delete::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::closing::close::{HistoryVoters, Voter};
+use crate::config::Config;
+use crate::flags::dao::reverse_flag_penalty;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Change;
+use crate::state::{AppStateRaw, SqlTx};
+
+#[async_trait]
+pub trait IDeletion: std::ops::Deref<Target = AppStateRaw> {
+    async fn delete_post(&self, pid: i64, who: Deleter<'_>) -> sqlx::Result<DeleteOutcome>;
+    async fn undelete_post(&self, pid: i64, who: Deleter<'_>) -> sqlx::Result<DeleteOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IDeletion for &AppStateRaw {
+    async fn delete_post(&self, pid: i64, who: Deleter<'_>) -> sqlx::Result<DeleteOutcome> {
+        let mut tx = self.sql.begin().await?;
+        let outcome = delete_post_tx(&mut tx, &self.config, pid, who).await?;
+        if let DeleteOutcome::Voted(_) = outcome {
+            tx.commit().await?;
+        }
+
+        Ok(outcome)
+    }
+
+    async fn undelete_post(&self, pid: i64, who: Deleter<'_>) -> sqlx::Result<DeleteOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let post = sqlx::query!(
+            r#"
+            select owner_user_id, deletion_date,
+                (select user_id from post_history h where h.post_id = p.id
+                    and h.post_history_type_id = $2
+                    order by h.creation_date desc, h.id desc limit 1) as deleted_by
+            from posts p where id = $1 and post_type_id in ($3, $4)
+            for update
+            "#,
+            pid,
+            POST_DELETED,
+            ANSWER_POST_TYPE,
+            QUESTION_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let post = match post {
+            Some(p) => p,
+            None => return Ok(DeleteOutcome::NotFound),
+        };
+        if post.deletion_date.is_none() {
+            return Ok(DeleteOutcome::Unchanged);
+        }
+
+        // owners can bring back what they deleted themselves
+        let immediate = who.moderator
+            || (post.owner_user_id == Some(who.uid) && post.deleted_by == Some(who.uid));
+        if !immediate && !who.trusted {
+            return Ok(DeleteOutcome::Unprivileged);
+        }
+
+        let votes = match vote(&mut tx, pid, who.uid, UNDELETION_VOTE_TYPE).await? {
+            Some(votes) => votes,
+            None => return Ok(DeleteOutcome::AlreadyVoted),
+        };
+        let undeleted = immediate || votes >= self.config.delete_vote_threshold;
+        if undeleted {
+            restore(&mut tx, pid).await?;
+            reverse_flag_penalty(&mut tx, pid, post.owner_user_id).await?;
+            record(&mut tx, pid, who, POST_UNDELETED, UNDELETION_VOTE_TYPE).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(DeleteOutcome::Voted(DeleteVoteResponse {
+            votes,
+            deleted: !undeleted,
+        }))
+    }
+}
+
+// Records the vote and returns the number of pending votes of that type, None if
+// the user already voted
+async fn vote(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    uid: i64,
+    vote_type_id: i32,
+) -> sqlx::Result<Option<i64>> {
+    let r = sqlx::query!(
+        r#"
+        insert into votes (post_id, vote_type_id, user_id, creation_date)
+        select $1, $2, $3, current_date
+        where not exists(select 1 from votes where post_id = $1 and vote_type_id = $2 and user_id = $3)
+        "#,
+        pid,
+        vote_type_id,
+        uid
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if r.rows_affected() != 1 {
+        return Ok(None);
+    }
+
+    let votes = sqlx::query!(
+        r#"
+        select count(1) as "count!" from votes where post_id = $1 and vote_type_id = $2
+        "#,
+        pid,
+        vote_type_id
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .count;
+
+    Ok(Some(votes))
+}
+
+// Writes the history row listing the voters and clears the pending votes
+async fn record(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    who: Deleter<'_>,
+    post_history_type_id: i64,
+    vote_type_id: i32,
+) -> sqlx::Result<()> {
+    let voters = sqlx::query!(
+        r#"
+        delete from votes v using users u
+        where v.post_id = $1 and v.vote_type_id = $2 and u.id = v.user_id
+        returning u.id, u.display_name
+        "#,
+        pid,
+        vote_type_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|r| Voter {
+        id: r.id,
+        display_name: r.display_name.unwrap_or_default(),
+    })
+    .collect();
+
+    let text = serde_json::to_string(&HistoryVoters {
+        voters,
+        ..Default::default()
+    })
+    .unwrap_or_default();
+    let change = Change {
+        post_history_type_id,
+        text: &text,
+    };
+    record_revision(&mut *tx, pid, who.uid, who.username, None, &[change]).await?;
+
+    Ok(())
+}
+
+// Marks the post deleted, archives a copy in posts_with_deleted and takes it out
+// of the answer and tag counts
+pub async fn archive(tx: &mut SqlTx<'_>, pid: i64) -> sqlx::Result<()> {
+    let p = sqlx::query!(
+        r#"
+        update posts set deletion_date = now() where id = $1 returning parent_id
+        "#,
+        pid
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        insert into posts_with_deleted (id, post_type_id, accepted_answer_id, parent_id,
+            creation_date, deletion_date, score, view_count, body, owner_user_id,
+            owner_display_name, last_editor_user_id, last_editor_display_name, last_edit_date,
+            last_activity_date, title, tags, answer_count, comment_count, favorite_count,
+            closed_date, community_owned_date, content_license, locked_date, protected_date)
+        select id, post_type_id, accepted_answer_id, parent_id, creation_date, deletion_date,
+            score, view_count, body, owner_user_id, owner_display_name, last_editor_user_id,
+            last_editor_display_name, last_edit_date, last_activity_date, title, tags,
+            answer_count, comment_count, favorite_count, closed_date, community_owned_date,
+            content_license, locked_date, protected_date
+        from posts where id = $1
+        on conflict (id) do nothing
+        "#,
+        pid
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    count_post(tx, pid, p.parent_id, -1).await
+}
+
+async fn restore(tx: &mut SqlTx<'_>, pid: i64) -> sqlx::Result<()> {
+    let p = sqlx::query!(
+        r#"
+        update posts set deletion_date = null where id = $1 returning parent_id
+        "#,
+        pid
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        delete from posts_with_deleted where id = $1
+        "#,
+        pid
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    count_post(tx, pid, p.parent_id, 1).await
+}
+
+async fn count_post(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    parent_id: Option<i64>,
+    delta: i32,
+) -> sqlx::Result<()> {
+    match parent_id {
+        Some(parent_id) => {
+            sqlx::query!(
+                r#"
+                update posts set answer_count = coalesce(answer_count, 0) + $1 where id = $2
+                "#,
+                delta,
+                parent_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                r#"
+                update tags set count = coalesce(count, 0) + $1
+                where id in (select tag_id from post_tags where post_id = $2)
+                "#,
+                delta as i64,
+                pid
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Votes to delete `pid` in the caller's transaction, which is only to be committed
+// when the outcome is Voted
+pub async fn delete_post_tx(
+    tx: &mut SqlTx<'_>,
+    config: &Config,
+    pid: i64,
+    who: Deleter<'_>,
+) -> sqlx::Result<DeleteOutcome> {
+    let post = sqlx::query!(
+        r#"
+        select p.post_type_id, p.owner_user_id, p.deletion_date, p.closed_date,
+            coalesce(p.score, 0) as "score!", p.accepted_answer_id,
+            q.accepted_answer_id as "parent_accepted_answer_id?",
+            exists(select 1 from posts a where a.parent_id = p.id and a.post_type_id = $2
+                and a.deletion_date is null and a.score > 0) as "upvoted_answers!"
+        from posts p left join posts q on q.id = p.parent_id
+        where p.id = $1 and p.post_type_id in ($2, $3)
+        for update of p
+        "#,
+        pid,
+        ANSWER_POST_TYPE,
+        QUESTION_POST_TYPE
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let post = match post {
+        Some(p) => p,
+        None => return Ok(DeleteOutcome::NotFound),
+    };
+    if post.deletion_date.is_some() {
+        return Ok(DeleteOutcome::Unchanged);
+    }
+
+    let question = post.post_type_id == Some(QUESTION_POST_TYPE);
+    let immediate = if who.moderator {
+        true
+    } else if post.owner_user_id == Some(who.uid) {
+        // owners cannot remove content others have been rewarded for
+        let removable = if question {
+            post.accepted_answer_id.is_none() && !post.upvoted_answers
+        } else {
+            post.parent_accepted_answer_id != Some(pid)
+        };
+        if !removable {
+            return Ok(DeleteOutcome::Forbidden);
+        }
+        true
+    } else if !who.trusted {
+        return Ok(DeleteOutcome::Unprivileged);
+    } else {
+        // the community only deletes closed questions and downvoted answers
+        let eligible = if question {
+            post.closed_date.is_some()
+        } else {
+            post.score < 0
+        };
+        if !eligible {
+            return Ok(DeleteOutcome::Forbidden);
+        }
+        false
+    };
+
+    let votes = match vote(tx, pid, who.uid, DELETION_VOTE_TYPE).await? {
+        Some(votes) => votes,
+        None => return Ok(DeleteOutcome::AlreadyVoted),
+    };
+    let deleted = immediate || votes >= config.delete_vote_threshold;
+    if deleted {
+        archive(tx, pid).await?;
+        record(tx, pid, who, POST_DELETED, DELETION_VOTE_TYPE).await?;
+    }
+
+    Ok(DeleteOutcome::Voted(DeleteVoteResponse { votes, deleted }))
+}
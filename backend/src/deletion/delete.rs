@@ -0,0 +1,34 @@
+// vote_type_id of deletion and undeletion votes
+pub const DELETION_VOTE_TYPE: i32 = 10;
+pub const UNDELETION_VOTE_TYPE: i32 = 11;
+// post_history_type_id written when a post is deleted or undeleted
+pub const POST_DELETED: i64 = 12;
+pub const POST_UNDELETED: i64 = 13;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteVoteResponse {
+    pub votes: i64,
+    pub deleted: bool,
+}
+
+// Who is asking for the deletion or undeletion
+#[derive(Debug, Clone, Copy)]
+pub struct Deleter<'a> {
+    pub uid: i64,
+    pub username: &'a str,
+    pub moderator: bool,
+    // may vote on posts of others
+    pub trusted: bool,
+}
+
+#[derive(Debug)]
+pub enum DeleteOutcome {
+    Voted(DeleteVoteResponse),
+    NotFound,
+    // deleting a deleted post or undeleting a live one
+    Unchanged,
+    AlreadyVoted,
+    Forbidden,
+    // a community vote from a user without the moderation tools privilege
+    Unprivileged,
+}
@@ -0,0 +1,3 @@
+pub mod dao;
+pub mod delete;
+pub mod routes;
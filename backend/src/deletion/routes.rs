@@ -0,0 +1,77 @@
+use super::dao::IDeletion;
+use super::delete::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+
+#[post("/delete/{id}")]
+async fn delete_post(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+
+    let who = Deleter {
+        uid: auth.claims.id,
+        username: &auth.claims.username,
+        moderator: auth.moderator,
+        trusted: auth.has(Privilege::ModerationTools, &state.config.privileges),
+    };
+    match state.get_ref().delete_post(pid, who).await {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("delete {} error: {:?}", pid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/undelete/{id}")]
+async fn undelete_post(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+
+    let who = Deleter {
+        uid: auth.claims.id,
+        username: &auth.claims.username,
+        moderator: auth.moderator,
+        trusted: auth.has(Privilege::ModerationTools, &state.config.privileges),
+    };
+    match state.get_ref().undelete_post(pid, who).await {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("undelete {} error: {:?}", pid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result(outcome: DeleteOutcome, state: &AppState) -> ApiResult<DeleteVoteResponse> {
+    match outcome {
+        DeleteOutcome::Voted(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        DeleteOutcome::NotFound => ApiResult::new().code(404).with_msg("Post not found"),
+        DeleteOutcome::Unchanged => ApiResult::new()
+            .code(409)
+            .with_msg("The post is already in that state"),
+        DeleteOutcome::AlreadyVoted => ApiResult::new()
+            .code(409)
+            .with_msg("You have already voted on this post"),
+        DeleteOutcome::Forbidden => ApiResult::new()
+            .code(403)
+            .with_msg("You cannot vote to delete this post"),
+        DeleteOutcome::Unprivileged => {
+            Privilege::ModerationTools.denied(&state.config.privileges)
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(delete_post);
+    cfg.service(undelete_post);
+}
@@ -0,0 +1,454 @@
+use super::flag::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::closing::close::{HistoryVoters, Voter};
+use crate::deletion::dao::archive;
+use crate::deletion::delete::POST_DELETED;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Change;
+use crate::state::{AppStateRaw, SqlTx};
+use crate::users::user::{COMMUNITY_USER_ID, COMMUNITY_USER_NAME};
+
+use std::collections::HashMap;
+
+#[async_trait]
+pub trait IFlag: std::ops::Deref<Target = AppStateRaw> {
+    async fn flag_post(
+        &self,
+        pid: i64,
+        uid: i64,
+        form: &FlagReq,
+    ) -> sqlx::Result<FlagOutcome<FlagResponse>>;
+    async fn flag_comment(
+        &self,
+        cid: i64,
+        uid: i64,
+        form: &FlagReq,
+    ) -> sqlx::Result<FlagOutcome<FlagResponse>>;
+    async fn get_flags(&self, form: &FlagsReq) -> sqlx::Result<FlagsResponse>;
+    async fn handle_flag(&self, fid: i64, uid: i64, helpful: bool)
+        -> sqlx::Result<FlagOutcome<()>>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IFlag for &AppStateRaw {
+    // enough spam and rude flags delete the post and cost its author reputation
+    async fn flag_post(
+        &self,
+        pid: i64,
+        uid: i64,
+        form: &FlagReq,
+    ) -> sqlx::Result<FlagOutcome<FlagResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let post = sqlx::query!(
+            r#"
+            select post_type_id, owner_user_id from posts
+            where id = $1 and post_type_id in ($2, $3) and deletion_date is null
+            for update
+            "#,
+            pid,
+            QUESTION_POST_TYPE,
+            ANSWER_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let post = match post {
+            Some(p) => p,
+            None => return Ok(FlagOutcome::NotFound),
+        };
+        if form.flag_type == FlagType::NotAnAnswer && post.post_type_id != Some(ANSWER_POST_TYPE) {
+            return Ok(FlagOutcome::InvalidType);
+        }
+        let vote_type_id = form.flag_type.vote_type();
+        if vote_type_id.is_some() && post.owner_user_id == Some(uid) {
+            return Ok(FlagOutcome::OwnContent);
+        }
+
+        let r = sqlx::query!(
+            r#"
+            insert into flags (post_id, flag_type_id, user_id, text)
+            select $1, $2, $3, $4
+            where not exists(select 1 from flags
+                where post_id = $1 and comment_id is null and user_id = $3 and handled_date is null)
+            "#,
+            pid,
+            form.flag_type.id(),
+            uid,
+            form.text
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if r.rows_affected() != 1 {
+            return Ok(FlagOutcome::AlreadyFlagged);
+        }
+        if let Some(vote_type_id) = vote_type_id {
+            sqlx::query!(
+                r#"
+                insert into votes (post_id, vote_type_id, user_id, creation_date)
+                values ($1, $2, $3, current_date)
+                "#,
+                pid,
+                vote_type_id,
+                uid
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        let flags = pending_flags(&mut tx, pid, None).await?;
+        let deleted = flags >= self.config.flag_auto_delete;
+        if deleted {
+            let voters = resolve_flags(&mut tx, pid, None).await?;
+            archive(&mut tx, pid).await?;
+
+            let text = serde_json::to_string(&HistoryVoters {
+                voters,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+            let change = Change {
+                post_history_type_id: POST_DELETED,
+                text: &text,
+            };
+            // the community deletes the post, not the last flagger
+            record_revision(
+                &mut tx,
+                pid,
+                COMMUNITY_USER_ID,
+                COMMUNITY_USER_NAME,
+                None,
+                &[change],
+            )
+            .await?;
+
+            sqlx::query!(
+                r#"
+                insert into votes (post_id, vote_type_id, user_id, creation_date,
+                    owner_reputation_change)
+                values ($1, $2, $3, current_date, $4)
+                "#,
+                pid,
+                MODERATOR_REVIEW_VOTE_TYPE,
+                COMMUNITY_USER_ID,
+                self.config.flag_penalty
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                update users set reputation = coalesce(reputation, 0) + $1 where id = $2
+                "#,
+                self.config.flag_penalty,
+                post.owner_user_id
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(FlagOutcome::Done(FlagResponse { flags, deleted }))
+    }
+
+    async fn flag_comment(
+        &self,
+        cid: i64,
+        uid: i64,
+        form: &FlagReq,
+    ) -> sqlx::Result<FlagOutcome<FlagResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let c = sqlx::query!(
+            r#"
+            select c.post_id as "post_id!", c.user_id from comments c
+            join posts p on p.id = c.post_id
+            where c.id = $1 and c.deletion_date is null and p.deletion_date is null
+            for update of c
+            "#,
+            cid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let c = match c {
+            Some(c) => c,
+            None => return Ok(FlagOutcome::NotFound),
+        };
+        if form.flag_type == FlagType::NotAnAnswer {
+            return Ok(FlagOutcome::InvalidType);
+        }
+        if form.flag_type.vote_type().is_some() && c.user_id == Some(uid) {
+            return Ok(FlagOutcome::OwnContent);
+        }
+
+        let r = sqlx::query!(
+            r#"
+            insert into flags (post_id, comment_id, flag_type_id, user_id, text)
+            select $1, $2, $3, $4, $5
+            where not exists(select 1 from flags
+                where comment_id = $2 and user_id = $4 and handled_date is null)
+            "#,
+            c.post_id,
+            cid,
+            form.flag_type.id(),
+            uid,
+            form.text
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if r.rows_affected() != 1 {
+            return Ok(FlagOutcome::AlreadyFlagged);
+        }
+
+        // comments go away without a reputation penalty
+        let flags = pending_flags(&mut tx, c.post_id, Some(cid)).await?;
+        let deleted = flags >= self.config.flag_auto_delete;
+        if deleted {
+            resolve_flags(&mut tx, c.post_id, Some(cid)).await?;
+
+            sqlx::query!(
+                r#"
+                update comments set deletion_date = now() where id = $1
+                "#,
+                cid
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                update posts set comment_count = coalesce(comment_count, 0) - 1 where id = $1
+                "#,
+                c.post_id
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(FlagOutcome::Done(FlagResponse { flags, deleted }))
+    }
+
+    // flagged content with the most flags first
+    async fn get_flags(&self, form: &FlagsReq) -> sqlx::Result<FlagsResponse> {
+        let per_page = self.config.questions_per_page as i64;
+        let offset = form.page.max(0) * per_page;
+
+        let count = sqlx::query!(
+            r#"
+            select count(distinct (post_id, comment_id)) as "count!" from flags
+            where (handled_date is not null) = $1
+            "#,
+            form.handled
+        )
+        .fetch_one(&self.sql)
+        .await?
+        .count;
+
+        let targets = sqlx::query!(
+            r#"
+            select f.post_id as "post_id!", f.comment_id, count(1) as "flags!",
+                coalesce(p.title, q.title) as title, coalesce(c.text, p.body) as body,
+                coalesce(c.user_id, p.owner_user_id) as owner_user_id,
+                coalesce(c.deletion_date, p.deletion_date) as deletion_date
+            from flags f join posts p on p.id = f.post_id
+            left join posts q on q.id = p.parent_id
+            left join comments c on c.id = f.comment_id
+            where (f.handled_date is not null) = $1
+            group by f.post_id, f.comment_id, p.id, q.id, c.id
+            order by count(1) desc, max(f.creation_date) desc
+            limit $2 offset $3
+            "#,
+            form.handled,
+            per_page,
+            offset
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let pids = targets.iter().map(|t| t.post_id).collect::<Vec<_>>();
+        let fr = sqlx::query!(
+            r#"
+            select f.id, f.post_id as "post_id!", f.comment_id, f.flag_type_id, f.text, f.user_id,
+                u.display_name as "user_display_name?", f.creation_date, f.handled_date, f.helpful
+            from flags f left join users u on u.id = f.user_id
+            where f.post_id = any($1) and (f.handled_date is not null) = $2
+            order by f.creation_date, f.id
+            "#,
+            &pids,
+            form.handled
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let mut entries = HashMap::<(i64, Option<i64>), Vec<FlagEntry>>::new();
+        for f in fr {
+            entries
+                .entry((f.post_id, f.comment_id))
+                .or_default()
+                .push(FlagEntry {
+                    id: f.id.to_string(),
+                    flag_type: FlagType::from_id(f.flag_type_id),
+                    text: f.text,
+                    user_id: f.user_id.map(|id| id.to_string()),
+                    user_display_name: f.user_display_name,
+                    creation_date: f.creation_date,
+                    handled_date: f.handled_date,
+                    helpful: f.helpful,
+                });
+        }
+
+        let flagged = targets
+            .into_iter()
+            .map(|t| FlaggedResponse {
+                post_id: t.post_id.to_string(),
+                comment_id: t.comment_id.map(|id| id.to_string()),
+                title: t.title.unwrap_or_default(),
+                body: t.body.unwrap_or_default(),
+                owner_user_id: t.owner_user_id.map(|id| id.to_string()),
+                deletion_date: t.deletion_date,
+                flags: entries
+                    .remove(&(t.post_id, t.comment_id))
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(FlagsResponse { flagged, count })
+    }
+
+    async fn handle_flag(
+        &self,
+        fid: i64,
+        uid: i64,
+        helpful: bool,
+    ) -> sqlx::Result<FlagOutcome<()>> {
+        let mut tx = self.sql.begin().await?;
+
+        let f = sqlx::query!(
+            r#"
+            update flags set handled_date = now(), handled_user_id = $2, helpful = $3
+            where id = $1 and handled_date is null
+            returning post_id as "post_id!", comment_id
+            "#,
+            fid,
+            uid,
+            helpful
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let f = match f {
+            Some(f) => f,
+            None => return Ok(FlagOutcome::NotFound),
+        };
+        if f.comment_id.is_none() {
+            sqlx::query!(
+                r#"
+                insert into votes (post_id, vote_type_id, user_id, creation_date)
+                select $1, $2, $3, current_date
+                where not exists(select 1 from votes
+                    where post_id = $1 and vote_type_id = $2 and user_id = $3)
+                "#,
+                f.post_id,
+                MODERATOR_REVIEW_VOTE_TYPE,
+                uid
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(FlagOutcome::Done(()))
+    }
+}
+
+// Pending spam and rude flags on a post, or on one of its comments
+async fn pending_flags(tx: &mut SqlTx<'_>, pid: i64, cid: Option<i64>) -> sqlx::Result<i64> {
+    let flags = sqlx::query!(
+        r#"
+        select count(1) as "count!" from flags
+        where post_id = $1 and comment_id is not distinct from $2 and handled_date is null
+            and flag_type_id in ($3, $4)
+        "#,
+        pid,
+        cid,
+        FlagType::Spam.id(),
+        FlagType::Rude.id()
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .count;
+
+    Ok(flags)
+}
+
+// Marks the pending spam and rude flags helpful once they deleted the content,
+// returns the flaggers
+async fn resolve_flags(tx: &mut SqlTx<'_>, pid: i64, cid: Option<i64>) -> sqlx::Result<Vec<Voter>> {
+    let voters = sqlx::query!(
+        r#"
+        update flags f set handled_date = now(), helpful = true from users u
+        where f.post_id = $1 and f.comment_id is not distinct from $2 and f.handled_date is null
+            and f.flag_type_id in ($3, $4) and u.id = f.user_id
+        returning u.id, u.display_name
+        "#,
+        pid,
+        cid,
+        FlagType::Spam.id(),
+        FlagType::Rude.id()
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|r| Voter {
+        id: r.id,
+        display_name: r.display_name.unwrap_or_default(),
+    })
+    .collect();
+
+    Ok(voters)
+}
+
+// Gives back the reputation penalty of a post deleted by flags once it is undeleted
+pub async fn reverse_flag_penalty(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    owner: Option<i64>,
+) -> sqlx::Result<()> {
+    let penalty = sqlx::query!(
+        r#"
+        delete from votes where post_id = $1 and vote_type_id = $2 and user_id = $3
+        returning owner_reputation_change
+        "#,
+        pid,
+        MODERATOR_REVIEW_VOTE_TYPE,
+        COMMUNITY_USER_ID
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|v| v.owner_reputation_change.unwrap_or_default())
+    .sum::<i64>();
+
+    if penalty != 0 {
+        sqlx::query!(
+            r#"
+            update users set reputation = coalesce(reputation, 0) - $1 where id = $2
+            "#,
+            penalty,
+            owner
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
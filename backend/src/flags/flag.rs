@@ -0,0 +1,129 @@
+use chrono::NaiveDateTime;
+
+// vote_type_id kept for spam and rude flags on posts
+pub const OFFENSIVE_VOTE_TYPE: i32 = 4;
+pub const SPAM_VOTE_TYPE: i32 = 12;
+// vote_type_id of a moderator handling a flag on a post, also cast by Community with
+// the reputation penalty of a post deleted by flags
+pub const MODERATOR_REVIEW_VOTE_TYPE: i32 = 15;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FlagType {
+    Spam,
+    Rude,
+    // needs moderator attention, with a reason
+    Moderator,
+    NotAnAnswer,
+}
+
+impl FlagType {
+    pub fn id(&self) -> i16 {
+        match self {
+            FlagType::Spam => 1,
+            FlagType::Rude => 2,
+            FlagType::Moderator => 3,
+            FlagType::NotAnAnswer => 4,
+        }
+    }
+    pub fn from_id(flag_type_id: i16) -> Option<Self> {
+        match flag_type_id {
+            1 => Some(FlagType::Spam),
+            2 => Some(FlagType::Rude),
+            3 => Some(FlagType::Moderator),
+            4 => Some(FlagType::NotAnAnswer),
+            _ => None,
+        }
+    }
+    // the flags counting towards deleting the content without a moderator
+    pub fn vote_type(&self) -> Option<i32> {
+        match self {
+            FlagType::Spam => Some(SPAM_VOTE_TYPE),
+            FlagType::Rude => Some(OFFENSIVE_VOTE_TYPE),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct FlagReq {
+    pub flag_type: FlagType,
+    // required for moderator flags
+    #[validate(length(min = 10, max = 500))]
+    pub text: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlagResponse {
+    // pending spam and rude flags on the content
+    pub flags: i64,
+    pub deleted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlagsReq {
+    #[serde(default)]
+    pub page: i64,
+    // list handled flags instead of pending ones
+    #[serde(default)]
+    pub handled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlagEntry {
+    pub id: String,
+    pub flag_type: Option<FlagType>,
+    pub text: Option<String>,
+    pub user_id: Option<String>,
+    pub user_display_name: Option<String>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub handled_date: Option<NaiveDateTime>,
+    pub helpful: Option<bool>,
+}
+
+// A flagged post or comment with its flags
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlaggedResponse {
+    pub post_id: String,
+    pub comment_id: Option<String>,
+    // title of the question the content belongs to
+    pub title: String,
+    pub body: String,
+    pub owner_user_id: Option<String>,
+    pub deletion_date: Option<NaiveDateTime>,
+    pub flags: Vec<FlagEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlagsResponse {
+    pub flagged: Vec<FlaggedResponse>,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HandleFlagReq {
+    pub helpful: bool,
+}
+
+#[derive(Debug)]
+pub enum FlagOutcome<T> {
+    Done(T),
+    NotFound,
+    // not-an-answer on a question or a comment
+    InvalidType,
+    // spam and rude flags on your own content
+    OwnContent,
+    AlreadyFlagged,
+}
+
+impl<T> FlagOutcome<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> FlagOutcome<U> {
+        match self {
+            FlagOutcome::Done(t) => FlagOutcome::Done(f(t)),
+            FlagOutcome::NotFound => FlagOutcome::NotFound,
+            FlagOutcome::InvalidType => FlagOutcome::InvalidType,
+            FlagOutcome::OwnContent => FlagOutcome::OwnContent,
+            FlagOutcome::AlreadyFlagged => FlagOutcome::AlreadyFlagged,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::dao::IFlag;
+use super::flag::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+use serde::Serialize;
+use validator::Validate;
+
+#[post("/flag/post/{id}")]
+async fn flag_post(
+    params: web::Path<i64>,
+    form: web::Json<FlagReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(denied) = auth.require(Privilege::FlagPosts, &state.config.privileges) {
+        return denied;
+    }
+    if let Err(res) = validate(&form) {
+        return res;
+    }
+    match state.get_ref().flag_post(pid, auth.claims.id, &form).await {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("flag post {} error: {:?}", pid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/flag/comment/{id}")]
+async fn flag_comment(
+    params: web::Path<i64>,
+    form: web::Json<FlagReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let cid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(denied) = auth.require(Privilege::FlagPosts, &state.config.privileges) {
+        return denied;
+    }
+    if let Err(res) = validate(&form) {
+        return res;
+    }
+    match state
+        .get_ref()
+        .flag_comment(cid, auth.claims.id, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("flag comment {} error: {:?}", cid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/flags")]
+async fn get_flags(form: web::Json<FlagsReq>, auth: Privileged, state: AppState) -> impl Responder {
+    let form = form.into_inner();
+
+    if let Err(denied) = auth.require(Privilege::ModerationTools, &state.config.privileges) {
+        return denied;
+    }
+    match state.get_ref().get_flags(&form).await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/handle-flag/{id}")]
+async fn handle_flag(
+    params: web::Path<i64>,
+    form: web::Json<HandleFlagReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let fid = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can handle flags");
+    }
+    match state
+        .get_ref()
+        .handle_flag(fid, auth.claims.id, form.helpful)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome.map(|()| true)),
+        Err(e) => {
+            error!("handle flag {} error: {:?}", fid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+// moderator flags must say what needs attention
+fn validate(form: &FlagReq) -> Result<(), ApiResult<FlagResponse>> {
+    if let Err(e) = form.validate() {
+        debug!("flag {:?} error: {:?}", form, e);
+        return Err(ApiResult::new().code(400).with_msg(e.to_string()));
+    }
+    if form.flag_type == FlagType::Moderator && form.text.is_none() {
+        return Err(ApiResult::new()
+            .code(400)
+            .with_msg("Tell the moderators what needs their attention"));
+    }
+    Ok(())
+}
+
+fn outcome_to_result<T: Serialize>(outcome: FlagOutcome<T>) -> ApiResult<T> {
+    match outcome {
+        FlagOutcome::Done(data) => ApiResult::new().code(200).with_msg("").with_data(data),
+        FlagOutcome::NotFound => ApiResult::new().code(404).with_msg("Not found"),
+        FlagOutcome::InvalidType => ApiResult::new()
+            .code(400)
+            .with_msg("This flag does not apply here"),
+        FlagOutcome::OwnContent => ApiResult::new()
+            .code(403)
+            .with_msg("You cannot flag your own content as spam or rude"),
+        FlagOutcome::AlreadyFlagged => ApiResult::new()
+            .code(409)
+            .with_msg("You have already flagged this"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(flag_post);
+    cfg.service(flag_comment);
+    cfg.service(get_flags);
+    cfg.service(handle_flag);
+}
@@ -0,0 +1,209 @@
+use super::lock::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::closing::close::{HistoryVoters, Voter};
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Change;
+use crate::state::{AppStateRaw, SqlTx};
+
+use chrono::NaiveDateTime;
+
+#[async_trait]
+pub trait ILock: std::ops::Deref<Target = AppStateRaw> {
+    async fn set_locked(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        locked: bool,
+    ) -> sqlx::Result<LockOutcome>;
+    async fn set_protected(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        protected: bool,
+    ) -> sqlx::Result<LockOutcome>;
+    async fn make_community_wiki(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        moderator: bool,
+    ) -> sqlx::Result<LockOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl ILock for &AppStateRaw {
+    async fn set_locked(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        locked: bool,
+    ) -> sqlx::Result<LockOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let p = match post_state(&mut tx, pid).await? {
+            Some(p) => p,
+            None => return Ok(LockOutcome::NotFound),
+        };
+        if p.locked_date.is_some() == locked {
+            return Ok(LockOutcome::Unchanged);
+        }
+
+        let state = sqlx::query_as!(
+            PostStateResponse,
+            r#"
+            update posts set locked_date = case when $1 then now() end where id = $2
+            returning locked_date, protected_date, community_owned_date
+            "#,
+            locked,
+            pid
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        let post_history_type_id = if locked { POST_LOCKED } else { POST_UNLOCKED };
+        record(&mut tx, pid, uid, username, post_history_type_id).await?;
+
+        tx.commit().await?;
+
+        Ok(LockOutcome::Done(state))
+    }
+
+    async fn set_protected(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        protected: bool,
+    ) -> sqlx::Result<LockOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let p = match post_state(&mut tx, qid).await? {
+            Some(p) if p.post_type_id == Some(QUESTION_POST_TYPE) => p,
+            _ => return Ok(LockOutcome::NotFound),
+        };
+        if p.protected_date.is_some() == protected {
+            return Ok(LockOutcome::Unchanged);
+        }
+
+        let state = sqlx::query_as!(
+            PostStateResponse,
+            r#"
+            update posts set protected_date = case when $1 then now() end where id = $2
+            returning locked_date, protected_date, community_owned_date
+            "#,
+            protected,
+            qid
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        let post_history_type_id = if protected {
+            QUESTION_PROTECTED
+        } else {
+            QUESTION_UNPROTECTED
+        };
+        record(&mut tx, qid, uid, username, post_history_type_id).await?;
+
+        tx.commit().await?;
+
+        Ok(LockOutcome::Done(state))
+    }
+
+    // there is no way back, the post stays community owned
+    async fn make_community_wiki(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        moderator: bool,
+    ) -> sqlx::Result<LockOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let p = match post_state(&mut tx, pid).await? {
+            Some(p) => p,
+            None => return Ok(LockOutcome::NotFound),
+        };
+        if p.owner_user_id != Some(uid) && !moderator {
+            return Ok(LockOutcome::Forbidden);
+        }
+        if p.community_owned_date.is_some() {
+            return Ok(LockOutcome::Unchanged);
+        }
+
+        let state = sqlx::query_as!(
+            PostStateResponse,
+            r#"
+            update posts set community_owned_date = now() where id = $1
+            returning locked_date, protected_date, community_owned_date
+            "#,
+            pid
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        let change = Change {
+            post_history_type_id: COMMUNITY_OWNED,
+            text: "",
+        };
+        record_revision(&mut tx, pid, uid, username, None, &[change]).await?;
+
+        tx.commit().await?;
+
+        Ok(LockOutcome::Done(state))
+    }
+}
+
+struct PostState {
+    post_type_id: Option<i16>,
+    owner_user_id: Option<i64>,
+    locked_date: Option<NaiveDateTime>,
+    protected_date: Option<NaiveDateTime>,
+    community_owned_date: Option<NaiveDateTime>,
+}
+
+async fn post_state(tx: &mut SqlTx<'_>, pid: i64) -> sqlx::Result<Option<PostState>> {
+    sqlx::query_as!(
+        PostState,
+        r#"
+        select post_type_id, owner_user_id, locked_date, protected_date, community_owned_date
+        from posts
+        where id = $1 and post_type_id in ($2, $3) and deletion_date is null
+        for update
+        "#,
+        pid,
+        QUESTION_POST_TYPE,
+        ANSWER_POST_TYPE
+    )
+    .fetch_optional(&mut *tx)
+    .await
+}
+
+// the history text lists the moderator who acted, like the votes of a close
+async fn record(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    uid: i64,
+    username: &str,
+    post_history_type_id: i64,
+) -> sqlx::Result<()> {
+    let text = serde_json::to_string(&HistoryVoters {
+        voters: vec![Voter {
+            id: uid,
+            display_name: username.to_owned(),
+        }],
+        ..Default::default()
+    })
+    .unwrap_or_default();
+    let change = Change {
+        post_history_type_id,
+        text: &text,
+    };
+    record_revision(&mut *tx, pid, uid, username, None, &[change]).await?;
+
+    Ok(())
+}
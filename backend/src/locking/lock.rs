@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+
+// post_history_type_id written when moderators lock, protect or hand over a post
+pub const POST_LOCKED: i64 = 14;
+pub const POST_UNLOCKED: i64 = 15;
+pub const COMMUNITY_OWNED: i64 = 16;
+pub const QUESTION_PROTECTED: i64 = 19;
+pub const QUESTION_UNPROTECTED: i64 = 20;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PostStateResponse {
+    pub locked_date: Option<NaiveDateTime>,
+    pub protected_date: Option<NaiveDateTime>,
+    pub community_owned_date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub enum LockOutcome {
+    Done(PostStateResponse),
+    NotFound,
+    // the post is already in that state
+    Unchanged,
+    // only the owner or a moderator can make a post community wiki
+    Forbidden,
+}
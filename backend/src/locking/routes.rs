@@ -0,0 +1,125 @@
+use super::dao::ILock;
+use super::lock::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::Privileged;
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+
+#[post("/lock/{id}")]
+async fn lock_post(params: web::Path<i64>, auth: Privileged, state: AppState) -> impl Responder {
+    set_locked(params.into_inner(), auth, state, true).await
+}
+
+#[post("/unlock/{id}")]
+async fn unlock_post(params: web::Path<i64>, auth: Privileged, state: AppState) -> impl Responder {
+    set_locked(params.into_inner(), auth, state, false).await
+}
+
+async fn set_locked(
+    pid: i64,
+    auth: Privileged,
+    state: AppState,
+    locked: bool,
+) -> ApiResult<PostStateResponse> {
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can lock posts");
+    }
+    match state
+        .get_ref()
+        .set_locked(pid, auth.claims.id, &auth.claims.username, locked)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("lock {} {} error: {:?}", pid, locked, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/protect/{id}")]
+async fn protect_question(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    set_protected(params.into_inner(), auth, state, true).await
+}
+
+#[post("/unprotect/{id}")]
+async fn unprotect_question(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    set_protected(params.into_inner(), auth, state, false).await
+}
+
+async fn set_protected(
+    qid: i64,
+    auth: Privileged,
+    state: AppState,
+    protected: bool,
+) -> ApiResult<PostStateResponse> {
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can protect questions");
+    }
+    match state
+        .get_ref()
+        .set_protected(qid, auth.claims.id, &auth.claims.username, protected)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("protect {} {} error: {:?}", qid, protected, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/community-wiki/{id}")]
+async fn make_community_wiki(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+
+    match state
+        .get_ref()
+        .make_community_wiki(pid, auth.claims.id, &auth.claims.username, auth.moderator)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("community wiki {} error: {:?}", pid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result(outcome: LockOutcome) -> ApiResult<PostStateResponse> {
+    match outcome {
+        LockOutcome::Done(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        LockOutcome::NotFound => ApiResult::new().code(404).with_msg("Post not found"),
+        LockOutcome::Unchanged => ApiResult::new()
+            .code(409)
+            .with_msg("The post is already in that state"),
+        LockOutcome::Forbidden => ApiResult::new()
+            .code(403)
+            .with_msg("Only the owner or a moderator can make a post community wiki"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(lock_post);
+    cfg.service(unlock_post);
+    cfg.service(protect_question);
+    cfg.service(unprotect_question);
+    cfg.service(make_community_wiki);
+}
@@ -12,16 +12,32 @@ extern crate serde;
 use actix_web::{middleware, web, App, HttpServer};
 use num_cpus;
 
+pub mod answers;
 pub mod api;
+pub mod badges;
+pub mod bounties;
+pub mod closing;
+pub mod comments;
 pub mod config;
+pub mod deletion;
+pub mod flags;
 pub mod how;
+pub mod locking;
+pub mod merging;
 pub mod middlewares;
+pub mod notices;
 // pub mod models;
+pub mod privileges;
 pub mod state;
+pub mod suggestions;
+pub mod synonyms;
 pub mod users;
-// pub mod tags;
-// pub mod questions;
-// pub mod votes;
+pub mod tags;
+pub mod questions;
+pub mod revisions;
+pub mod reviews;
+pub mod sessions;
+pub mod votes;
 pub mod utils;
 
 use config::{Config, Opts};
@@ -31,9 +47,27 @@ async fn main() -> std::io::Result<()> {
     // Config::show();
     let (_handle, opt) = Opts::parse_from_args();
     let state = Config::parse_from_file(&opt.config).into_state().await;
+
+    if opt.backfill_badges {
+        use badges::dao::IBadge;
+        return match (&state).award_badges(None).await {
+            Ok(n) => {
+                info!("backfill awarded {} badges", n);
+                Ok(())
+            }
+            Err(e) => {
+                error!("backfill badges error: {:?}", e);
+                Err(std::io::Error::other(e))
+            }
+        };
+    }
+
     let state2 = state.clone();
     let apiv1 = "/api/v1";
 
+    actix_rt::spawn(bounties::dao::expire_bounties_task(state.clone()));
+    actix_rt::spawn(notices::dao::expire_notices_task(state.clone()));
+
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(state.clone()))
@@ -41,10 +75,30 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::PathConfig::default().error_handler(api::json_error_handler))
             .wrap(middleware::Compress::default())
             .wrap(middleware::Logger::default())
-            .service(web::scope(&apiv1).configure(users::routes::init))
-            // .service(web::scope(&apiv1)).configure(tags::routes::init)
-            // .service(web::scope(&apiv1)).configure(votes::routes::init)
-            // .service(web::scope(&apiv1)).configure(questions::routes::init))
+            .service(sessions::routes::jwks)
+            .service(
+                web::scope(apiv1)
+                    .configure(users::routes::init)
+                    .configure(sessions::routes::init)
+                    .configure(questions::routes::init)
+                    .configure(answers::routes::init)
+                    .configure(votes::routes::init)
+                    .configure(tags::routes::init)
+                    .configure(comments::routes::init)
+                    .configure(revisions::routes::init)
+                    .configure(closing::routes::init)
+                    .configure(deletion::routes::init)
+                    .configure(bounties::routes::init)
+                    .configure(badges::routes::init)
+                    .configure(privileges::routes::init)
+                    .configure(synonyms::routes::init)
+                    .configure(suggestions::routes::init)
+                    .configure(notices::routes::init)
+                    .configure(locking::routes::init)
+                    .configure(merging::routes::init)
+                    .configure(flags::routes::init)
+                    .configure(reviews::routes::init),
+            )
     }).workers(num_cpus::get())
     .keep_alive(std::time::Duration::from_secs(300))
     .bind(&state2.config.listen)?
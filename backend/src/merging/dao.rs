@@ -0,0 +1,402 @@
+use super::merge::*;
+use crate::answers::answer::{ACCEPTED_VOTE_TYPE, ANSWER_POST_TYPE};
+use crate::badges::dao::check_badges;
+use crate::closing::close::DUPLICATE_LINK_TYPE;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::revisions::dao::record_changes;
+use crate::revisions::revision::Change;
+use crate::state::{AppStateRaw, SqlTx};
+use crate::votes::vote::{VoteKind, DOWN_VOTE_TYPE, UP_VOTE_TYPE};
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait IMerge: std::ops::Deref<Target = AppStateRaw> {
+    async fn merge_question(
+        &self,
+        source: i64,
+        target: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<MergeOutcome>;
+    async fn unmerge_question(
+        &self,
+        source: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<MergeOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IMerge for &AppStateRaw {
+    // the source keeps its comments and stays closed, redirecting to the target
+    async fn merge_question(
+        &self,
+        source: i64,
+        target: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<MergeOutcome> {
+        if source == target {
+            return Ok(MergeOutcome::NotDuplicate);
+        }
+        let mut tx = self.sql.begin().await?;
+
+        let questions = lock_questions(&mut tx, &[source, target]).await?;
+        let (s, t) = match (
+            questions.iter().find(|q| q.id == source),
+            questions.iter().find(|q| q.id == target),
+        ) {
+            (Some(s), Some(t)) if s.deletion_date.is_none() && t.deletion_date.is_none() => (s, t),
+            _ => return Ok(MergeOutcome::NotFound),
+        };
+
+        let links = sqlx::query!(
+            r#"
+            select post_id, related_post_id, link_type_id from post_links
+            where post_id in ($1, $2) and link_type_id in ($3, $4)
+            "#,
+            source,
+            target,
+            DUPLICATE_LINK_TYPE,
+            MERGED_LINK_TYPE
+        )
+        .fetch_all(&mut tx)
+        .await?;
+
+        if links
+            .iter()
+            .any(|l| l.link_type_id == Some(MERGED_LINK_TYPE))
+        {
+            return Ok(MergeOutcome::Merged);
+        }
+        let duplicate = links.iter().any(|l| {
+            l.post_id == Some(source)
+                && l.related_post_id == Some(target)
+                && l.link_type_id == Some(DUPLICATE_LINK_TYPE)
+        });
+        if s.closed_date.is_none() || !duplicate {
+            return Ok(MergeOutcome::NotDuplicate);
+        }
+
+        // an accepted answer is accepted by the question it was posted to only
+        if let Some(aid) = s.accepted_answer_id {
+            set_accepted(&mut tx, source, None).await?;
+            sqlx::query!(
+                r#"
+                delete from votes where post_id = $1 and vote_type_id = $2
+                "#,
+                aid,
+                ACCEPTED_VOTE_TYPE
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        let answer_ids = move_answers(&mut tx, source, target, None).await?;
+        let vote_ids = move_votes(&mut tx, s, t, None).await?;
+
+        sqlx::query!(
+            r#"
+            insert into post_links (creation_date, post_id, related_post_id, link_type_id)
+            values (now(), $1, $2, $3)
+            "#,
+            source,
+            target,
+            MERGED_LINK_TYPE
+        )
+        .execute(&mut tx)
+        .await?;
+
+        let merge = MergeRecord {
+            source_id: source,
+            target_id: target,
+            answer_ids,
+            vote_ids,
+            accepted_answer_id: s.accepted_answer_id,
+        };
+        let text = serde_json::to_string(&merge).unwrap_or_default();
+        let guid = Uuid::new_v4();
+        let source_changes = [
+            Change {
+                post_history_type_id: QUESTION_MERGED,
+                text: &text,
+            },
+            Change {
+                post_history_type_id: POST_MERGE_SOURCE,
+                text: &text,
+            },
+        ];
+        record_changes(&mut tx, guid, source, uid, username, None, &source_changes).await?;
+        let target_changes = [Change {
+            post_history_type_id: POST_MERGE_DESTINATION,
+            text: &text,
+        }];
+        record_changes(&mut tx, guid, target, uid, username, None, &target_changes).await?;
+
+        tx.commit().await?;
+
+        check_badges(self, t.owner_user_id).await;
+
+        Ok(MergeOutcome::Done(MergeResponse::from(&merge)))
+    }
+
+    // moves back what the last merge moved and is still on the target
+    async fn unmerge_question(
+        &self,
+        source: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<MergeOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let link = sqlx::query!(
+            r#"
+            delete from post_links where post_id = $1 and link_type_id = $2
+            returning related_post_id as "target!"
+            "#,
+            source,
+            MERGED_LINK_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let target = match link {
+            Some(l) => l.target,
+            None => return Ok(MergeOutcome::NotMerged),
+        };
+        let merged = sqlx::query!(
+            r#"
+            select text from post_history where post_id = $1 and post_history_type_id = $2
+            order by creation_date desc, id desc limit 1
+            "#,
+            source,
+            QUESTION_MERGED
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .and_then(|h| h.text)
+        .and_then(|text| serde_json::from_str::<MergeRecord>(&text).ok());
+
+        let merged = match merged {
+            Some(m) if m.target_id == target => m,
+            _ => return Ok(MergeOutcome::NotMerged),
+        };
+        let questions = lock_questions(&mut tx, &[source, target]).await?;
+        let (s, t) = match (
+            questions.iter().find(|q| q.id == source),
+            questions.iter().find(|q| q.id == target),
+        ) {
+            (Some(s), Some(t)) => (s, t),
+            _ => return Ok(MergeOutcome::NotFound),
+        };
+
+        // the target may have accepted one of the answers it got from the source
+        if let Some(aid) = t.accepted_answer_id {
+            if merged.answer_ids.contains(&aid) {
+                set_accepted(&mut tx, target, None).await?;
+                sqlx::query!(
+                    r#"
+                    delete from votes where post_id = $1 and vote_type_id = $2
+                    "#,
+                    aid,
+                    ACCEPTED_VOTE_TYPE
+                )
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+
+        let answer_ids = move_answers(&mut tx, target, source, Some(&merged.answer_ids)).await?;
+        let vote_ids = move_votes(&mut tx, t, s, Some(&merged.vote_ids)).await?;
+
+        let accepted_answer_id = merged
+            .accepted_answer_id
+            .filter(|aid| s.accepted_answer_id.is_none() && answer_ids.contains(aid));
+        if let Some(aid) = accepted_answer_id {
+            set_accepted(&mut tx, source, Some(aid)).await?;
+            sqlx::query!(
+                r#"
+                insert into votes (post_id, vote_type_id, user_id, creation_date)
+                values ($1, $2, $3, current_date)
+                "#,
+                aid,
+                ACCEPTED_VOTE_TYPE,
+                s.owner_user_id
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        let unmerge = MergeRecord {
+            source_id: source,
+            target_id: target,
+            answer_ids,
+            vote_ids,
+            accepted_answer_id,
+        };
+        let text = serde_json::to_string(&unmerge).unwrap_or_default();
+        let guid = Uuid::new_v4();
+        let changes = [Change {
+            post_history_type_id: QUESTION_UNMERGED,
+            text: &text,
+        }];
+        record_changes(&mut tx, guid, source, uid, username, None, &changes).await?;
+        record_changes(&mut tx, guid, target, uid, username, None, &changes).await?;
+
+        tx.commit().await?;
+
+        check_badges(self, s.owner_user_id).await;
+
+        Ok(MergeOutcome::Done(MergeResponse::from(&unmerge)))
+    }
+}
+
+struct MergedQuestion {
+    id: i64,
+    owner_user_id: Option<i64>,
+    accepted_answer_id: Option<i64>,
+    closed_date: Option<NaiveDateTime>,
+    deletion_date: Option<NaiveDateTime>,
+}
+
+// locks in id order so concurrent merges of the same pair cannot deadlock
+async fn lock_questions(tx: &mut SqlTx<'_>, qids: &[i64]) -> sqlx::Result<Vec<MergedQuestion>> {
+    sqlx::query_as!(
+        MergedQuestion,
+        r#"
+        select id, owner_user_id, accepted_answer_id, closed_date, deletion_date from posts
+        where id = any($1) and post_type_id = $2
+        order by id
+        for update
+        "#,
+        qids,
+        QUESTION_POST_TYPE
+    )
+    .fetch_all(&mut *tx)
+    .await
+}
+
+async fn set_accepted(tx: &mut SqlTx<'_>, qid: i64, aid: Option<i64>) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        update posts set accepted_answer_id = $1 where id = $2
+        "#,
+        aid,
+        qid
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+// Moves the answers of `from`, or only `ids` of them, under `to`
+async fn move_answers(
+    tx: &mut SqlTx<'_>,
+    from: i64,
+    to: i64,
+    ids: Option<&[i64]>,
+) -> sqlx::Result<Vec<i64>> {
+    let moved = sqlx::query!(
+        r#"
+        update posts set parent_id = $2
+        where parent_id = $1 and post_type_id = $3 and ($4::bigint[] is null or id = any($4))
+        returning id, deletion_date is null as "live!"
+        "#,
+        from,
+        to,
+        ANSWER_POST_TYPE,
+        ids
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // deleted answers are not counted
+    let live = moved.iter().filter(|a| a.live).count() as i32;
+    sqlx::query!(
+        r#"
+        update posts set answer_count = coalesce(answer_count, 0)
+            + case when id = $2 then $3::int else -$3::int end,
+            last_activity_date = case when id = $2 then now() else last_activity_date end
+        where id in ($1, $2)
+        "#,
+        from,
+        to,
+        live
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(moved.into_iter().map(|a| a.id).collect())
+}
+
+// Moves the up and down votes of `from`, or only `ids` of them, to `to` along with their score
+// and the reputation they earned, skipping voters who own `to` or already voted on it
+async fn move_votes(
+    tx: &mut SqlTx<'_>,
+    from: &MergedQuestion,
+    to: &MergedQuestion,
+    ids: Option<&[i64]>,
+) -> sqlx::Result<Vec<i64>> {
+    let moved = sqlx::query!(
+        r#"
+        update votes v set post_id = $2
+        where v.post_id = $1 and v.vote_type_id in ($3, $4)
+            and ($5::bigint[] is null or v.id = any($5))
+            and v.user_id is distinct from $6
+            and not exists (select 1 from votes o
+                where o.post_id = $2 and o.user_id = v.user_id and o.vote_type_id in ($3, $4))
+        returning v.id, v.vote_type_id, v.owner_reputation_change
+        "#,
+        from.id,
+        to.id,
+        UP_VOTE_TYPE,
+        DOWN_VOTE_TYPE,
+        ids,
+        to.owner_user_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let score: i64 = moved
+        .iter()
+        .map(|v| VoteKind::from_vote_type(v.vote_type_id).score())
+        .sum();
+    let reputation: i64 = moved
+        .iter()
+        .map(|v| v.owner_reputation_change.unwrap_or_default())
+        .sum();
+
+    sqlx::query!(
+        r#"
+        update posts set score = coalesce(score, 0)
+            + case when id = $2 then $3::bigint else -$3::bigint end
+        where id in ($1, $2)
+        "#,
+        from.id,
+        to.id,
+        score
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if from.owner_user_id != to.owner_user_id {
+        sqlx::query!(
+            r#"
+            update users set reputation = coalesce(reputation, 0)
+                + case when id = $2 then $3::bigint else -$3::bigint end
+            where id in ($1, $2)
+            "#,
+            from.owner_user_id,
+            to.owner_user_id,
+            reputation
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(moved.into_iter().map(|v| v.id).collect())
+}
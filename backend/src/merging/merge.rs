@@ -0,0 +1,53 @@
+// post_history_type_id written when a question is merged into another one or split back,
+// all rows of one merge or unmerge share a revision GUID
+pub const QUESTION_MERGED: i64 = 18;
+pub const QUESTION_UNMERGED: i64 = 22;
+pub const POST_MERGE_SOURCE: i64 = 37;
+pub const POST_MERGE_DESTINATION: i64 = 38;
+
+// post_links.link_type_id of a merged question pointing at the question it redirects to
+pub const MERGED_LINK_TYPE: i32 = 4;
+
+// JSON kept in post_history.text of a merge, listing what was moved so unmerge can put it back
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct MergeRecord {
+    pub source_id: i64,
+    pub target_id: i64,
+    pub answer_ids: Vec<i64>,
+    pub vote_ids: Vec<i64>,
+    // the accepted answer of the source, unaccepted while merged
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub accepted_answer_id: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MergeResponse {
+    pub source_id: String,
+    pub target_id: String,
+    pub answer_ids: Vec<String>,
+    pub vote_count: usize,
+}
+
+impl From<&MergeRecord> for MergeResponse {
+    fn from(r: &MergeRecord) -> Self {
+        Self {
+            source_id: r.source_id.to_string(),
+            target_id: r.target_id.to_string(),
+            answer_ids: r.answer_ids.iter().map(|id| id.to_string()).collect(),
+            vote_count: r.vote_ids.len(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MergeOutcome {
+    Done(MergeResponse),
+    NotFound,
+    // the source is not closed as a duplicate of the target
+    NotDuplicate,
+    // the source or the target is already merged into another question
+    Merged,
+    // unmerging a question that is not merged
+    NotMerged,
+}
@@ -0,0 +1,80 @@
+use super::dao::IMerge;
+use super::merge::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::Privileged;
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+
+#[post("/merge/{id}/{target}")]
+async fn merge_question(
+    params: web::Path<(i64, i64)>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let (source, target) = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can merge questions");
+    }
+    match state
+        .get_ref()
+        .merge_question(source, target, auth.claims.id, &auth.claims.username)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("merge {} into {} error: {:?}", source, target, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/unmerge/{id}")]
+async fn unmerge_question(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let source = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can unmerge questions");
+    }
+    match state
+        .get_ref()
+        .unmerge_question(source, auth.claims.id, &auth.claims.username)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("unmerge {} error: {:?}", source, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result(outcome: MergeOutcome) -> ApiResult<MergeResponse> {
+    match outcome {
+        MergeOutcome::Done(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        MergeOutcome::NotFound => ApiResult::new().code(404).with_msg("Question not found"),
+        MergeOutcome::NotDuplicate => ApiResult::new()
+            .code(409)
+            .with_msg("Only a question closed as a duplicate of the target can be merged"),
+        MergeOutcome::Merged => ApiResult::new()
+            .code(409)
+            .with_msg("One of the questions is already merged"),
+        MergeOutcome::NotMerged => ApiResult::new()
+            .code(409)
+            .with_msg("This question is not merged"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(merge_question);
+    cfg.service(unmerge_question);
+}
@@ -1,11 +1,12 @@
 use actix_web::{dev, FromRequest, HttpRequest};
-use futures::future::{err, ok, Ready};
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
-use std::borrow::Cow;
+use futures::future::LocalBoxFuture;
+use jsonwebtoken::errors::ErrorKind;
 
 use crate::api::ApiError;
+use crate::sessions::dao::ISession;
 use crate::state::AppStateRaw;
-use crate::users::user::Claims;
+use crate::users::dao::IUser;
+use crate::users::user::{Account, AccountStatus, Claims};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct QueryParams {
@@ -20,11 +21,12 @@ pub struct AuthorizationService {
 
 impl FromRequest for AuthorizationService {
     type Error = ApiError;
-    type Future = Ready<Result<AuthorizationService, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<AuthorizationService, Self::Error>>;
 
     // 1. header: Authorization: Bearer xxx
     // 2. URL's query: ?access_token=xxx
     // 3x. Body's query: ?access_token=xxx
+    // then turns away ended sessions and suspended and deleted accounts
     fn from_request(req: &HttpRequest, _payload: &mut dev::Payload) -> Self::Future {
         let xsrf_token_header = req
             .headers()
@@ -35,40 +37,66 @@ impl FromRequest for AuthorizationService {
             None => "".to_owned(),
         };
         let token = req.cookie("jwt");
+        let state = req.app_data::<AppStateRaw>().expect("get AppStateRaw").clone();
+        let req = req.clone();
 
-        match token
+        let decoded = token
             .as_ref()
-            .ok_or_else(|| Cow::Borrowed("Unauthorized"))
+            .ok_or_else(|| ApiError::new().code(400).with_msg("Unauthorized"))
             .and_then(|token| {
-                let state = req.app_data::<AppStateRaw>().expect("get AppStateRaw");
-                let key = state.config.jwt_priv.as_bytes();
-                match decode::<Claims>(
-                    token.value(),
-                    &DecodingKey::from_secret(key),
-                    &Validation::new(Algorithm::HS256),
-                ) {
+                match state.keys.verify(token.value()) {
                     Ok(claims) => {
-                        if claims.claims.xsrf_token != xsrf_token {
-                            Err(format!("Invalid credentials").into())
+                        if claims.xsrf_token != xsrf_token {
+                            Err(ApiError::new().code(400).with_msg("Invalid credentials"))
                         } else {
                             Ok(AuthorizationService {
-                                claims: claims.claims,
+                                claims,
                                 xsrf_token: xsrf_token,
                             })
                         }
                     }
+                    // clients renew expired tokens with their refresh token
+                    Err(e) if *e.kind() == ErrorKind::ExpiredSignature => {
+                        Err(ApiError::new().code(401).with_msg("Token expired"))
+                    }
                     Err(e) => {
                         error!("jwt.decode {} failed: {:?}", token, e);
-                        Err(format!("invalid token: {}", e).into())
+                        Err(ApiError::new()
+                            .code(400)
+                            .with_msg(format!("invalid token: {}", e)))
+                    }
+                }
+            });
+
+        Box::pin(async move {
+            let service = match decoded {
+                Ok(service) => service,
+                Err(api) => {
+                    api.log(&req);
+                    return Err(api);
+                }
+            };
+            let api = match (&state).get_account(service.claims.id).await {
+                Ok(Some(account)) if account.session_ended(&service.claims) => ApiError::new()
+                    .code(401)
+                    .with_msg("Your session has ended, please log in again"),
+                Ok(Some(account)) if account.status == AccountStatus::Active => {
+                    match (&state).touch_session(service.claims.id, &service.claims.sid).await {
+                        Ok(true) => return Ok(service),
+                        Ok(false) => ApiError::new()
+                            .code(401)
+                            .with_msg("Your session has ended, please log in again"),
+                        Err(e) => ApiError::new().code(500).with_msg(e.to_string()),
                     }
                 }
-            }) {
-            Ok(service) => ok(service),
-            Err(e) => {
-                let api = ApiError::new().code(400).with_msg(e);
-                api.log(req);
-                err(api)
-            }
-        }
+                Ok(Some(Account { status, .. })) => {
+                    ApiError::new().code(403).with_msg(status.denial().unwrap_or_default())
+                }
+                Ok(None) => ApiError::new().code(401).with_msg("Unauthorized"),
+                Err(e) => ApiError::new().code(500).with_msg(e.to_string()),
+            };
+            api.log(&req);
+            Err(api)
+        })
     }
 }
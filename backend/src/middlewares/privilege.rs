@@ -0,0 +1,155 @@
+use actix_web::{dev, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+
+use super::auth::AuthorizationService;
+use crate::api::{ApiError, ApiResult};
+use crate::config::Privileges;
+use crate::state::AppStateRaw;
+use crate::users::dao::IUser;
+use crate::users::user::Claims;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Privilege {
+    AnswerProtected,
+    CommentEverywhere,
+    FlagPosts,
+    Upvote,
+    Downvote,
+    EditCommunityWiki,
+    EditWithoutReview,
+    CloseVote,
+    ModerationTools,
+}
+
+impl Privilege {
+    pub const ALL: [Privilege; 9] = [
+        Privilege::AnswerProtected,
+        Privilege::CommentEverywhere,
+        Privilege::FlagPosts,
+        Privilege::Upvote,
+        Privilege::Downvote,
+        Privilege::EditCommunityWiki,
+        Privilege::EditWithoutReview,
+        Privilege::CloseVote,
+        Privilege::ModerationTools,
+    ];
+
+    // also the title of the privilege wiki post describing it
+    pub fn key(&self) -> &'static str {
+        match self {
+            Privilege::AnswerProtected => "answer-protected",
+            Privilege::CommentEverywhere => "comment-everywhere",
+            Privilege::FlagPosts => "flag-posts",
+            Privilege::Upvote => "upvote",
+            Privilege::Downvote => "downvote",
+            Privilege::EditCommunityWiki => "edit-community-wiki",
+            Privilege::EditWithoutReview => "edit-without-review",
+            Privilege::CloseVote => "close-vote",
+            Privilege::ModerationTools => "moderation-tools",
+        }
+    }
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.key() == key)
+    }
+    pub fn reputation(&self, privileges: &Privileges) -> i64 {
+        match self {
+            Privilege::AnswerProtected => privileges.answer_protected,
+            Privilege::CommentEverywhere => privileges.comment_everywhere,
+            Privilege::FlagPosts => privileges.flag_posts,
+            Privilege::Upvote => privileges.upvote,
+            Privilege::Downvote => privileges.downvote,
+            Privilege::EditCommunityWiki => privileges.edit_community_wiki,
+            Privilege::EditWithoutReview => privileges.edit_without_review,
+            Privilege::CloseVote => privileges.close_vote,
+            Privilege::ModerationTools => privileges.moderation_tools,
+        }
+    }
+    pub fn denied<T: Serialize>(&self, privileges: &Privileges) -> ApiResult<T> {
+        ApiResult::new().code(403).with_msg(format!(
+            "The {} privilege requires {} reputation",
+            self.key(),
+            self.reputation(privileges)
+        ))
+    }
+}
+
+// An authenticated user with the reputation their privileges are checked against.
+// Moderators hold every privilege.
+#[derive(Debug)]
+pub struct Privileged {
+    pub claims: Claims,
+    pub reputation: i64,
+    pub moderator: bool,
+}
+
+impl Privileged {
+    pub fn has(&self, privilege: Privilege, privileges: &Privileges) -> bool {
+        self.moderator || self.reputation >= privilege.reputation(privileges)
+    }
+    pub fn require<T: Serialize>(
+        &self,
+        privilege: Privilege,
+        privileges: &Privileges,
+    ) -> Result<(), ApiResult<T>> {
+        if self.has(privilege, privileges) {
+            Ok(())
+        } else {
+            Err(privilege.denied(privileges))
+        }
+    }
+}
+
+impl FromRequest for Privileged {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Privileged, Self::Error>>;
+
+    // authenticates like AuthorizationService, then loads the user's standing
+    fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
+        let auth = AuthorizationService::from_request(req, payload);
+        let state = req.app_data::<AppStateRaw>().expect("get AppStateRaw").clone();
+        let req = req.clone();
+
+        Box::pin(async move {
+            let auth = auth.await?;
+            match (&state).get_standing(auth.claims.id).await {
+                Ok(Some(standing)) => Ok(Privileged {
+                    claims: auth.claims,
+                    reputation: standing.reputation,
+                    moderator: standing.moderator,
+                }),
+                Ok(None) => {
+                    let api = ApiError::new().code(401).with_msg("Unauthorized");
+                    api.log(&req);
+                    Err(api)
+                }
+                Err(e) => {
+                    let api = ApiError::new().code(500).with_msg(e.to_string());
+                    api.log(&req);
+                    Err(api)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_key_round_trips_every_privilege() {
+        for privilege in Privilege::ALL {
+            assert_eq!(Privilege::from_key(privilege.key()), Some(privilege));
+        }
+    }
+
+    #[test]
+    fn from_key_rejects_unknown_keys() {
+        assert_eq!(Privilege::from_key("edit-everything"), None);
+        assert_eq!(Privilege::from_key(""), None);
+        // keys are kebab case
+        assert_eq!(Privilege::from_key("CloseVote"), None);
+    }
+}
@@ -0,0 +1,372 @@
+use super::notice::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::state::{AppStateRaw, SqlTx};
+
+use chrono::NaiveDateTime;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait INotice: std::ops::Deref<Target = AppStateRaw> {
+    async fn get_notice_types(&self) -> sqlx::Result<NoticeTypesResponse>;
+    async fn add_notice(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        form: &AddNoticeReq,
+    ) -> sqlx::Result<NoticeOutcome<PostNoticeResponse>>;
+    async fn remove_notice(
+        &self,
+        nid: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<NoticeOutcome<()>>;
+    async fn expire_notices(&self) -> sqlx::Result<u64>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl INotice for &AppStateRaw {
+    async fn get_notice_types(&self) -> sqlx::Result<NoticeTypesResponse> {
+        let tr = sqlx::query!(
+            r#"
+            select id, class_id, name, body, post_notice_duration_id from post_notice_types
+            where not coalesce(is_hidden, false)
+            order by id
+            "#
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let notice_types = tr
+            .into_iter()
+            .map(|t| NoticeTypeResponse {
+                id: t.id.to_string(),
+                class_id: t.class_id.unwrap_or_default(),
+                name: t.name.unwrap_or_default(),
+                body: t.body.unwrap_or_default(),
+                duration_days: duration_days(t.post_notice_duration_id),
+            })
+            .collect();
+
+        Ok(NoticeTypesResponse { notice_types })
+    }
+
+    // bounty notices come and go with their bounty
+    async fn add_notice(
+        &self,
+        pid: i64,
+        uid: i64,
+        username: &str,
+        form: &AddNoticeReq,
+    ) -> sqlx::Result<NoticeOutcome<PostNoticeResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let post = sqlx::query!(
+            r#"
+            select id from posts
+            where id = $1 and post_type_id in ($2, $3) and deletion_date is null
+            for update
+            "#,
+            pid,
+            QUESTION_POST_TYPE,
+            ANSWER_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        if post.is_none() {
+            return Ok(NoticeOutcome::NotFound);
+        }
+        let class_id = sqlx::query!(
+            r#"
+            select class_id from post_notice_types where id = $1
+            "#,
+            form.post_notice_type_id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .and_then(|t| t.class_id);
+        if class_id.is_none() || class_id == Some(BOUNTY_NOTICE_CLASS) {
+            return Ok(NoticeOutcome::InvalidType);
+        }
+
+        let outcome = insert_notice(
+            &mut tx,
+            pid,
+            form.post_notice_type_id,
+            form.body.as_deref(),
+            None,
+            uid,
+            username,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(outcome)
+    }
+
+    async fn remove_notice(
+        &self,
+        nid: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<NoticeOutcome<()>> {
+        let mut tx = self.sql.begin().await?;
+
+        let n = sqlx::query!(
+            r#"
+            update post_notices n set deletion_date = now(), deletion_user_id = $2
+            from post_notice_types t
+            where n.id = $1 and t.id = n.post_notice_type_id and t.class_id <> $3
+                and n.deletion_date is null and (n.expiry_date is null or n.expiry_date > now())
+            returning n.post_id as "post_id!", n.body
+            "#,
+            nid,
+            uid,
+            BOUNTY_NOTICE_CLASS
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let n = match n {
+            Some(n) => n,
+            None => return Ok(NoticeOutcome::NotFound),
+        };
+        record_notice_history(
+            &mut tx,
+            n.post_id,
+            nid,
+            POST_NOTICE_REMOVED,
+            n.body.as_deref(),
+            Some(uid),
+            Some(username),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(NoticeOutcome::Done(()))
+    }
+
+    async fn expire_notices(&self) -> sqlx::Result<u64> {
+        let mut tx = self.sql.begin().await?;
+
+        let expired = sqlx::query!(
+            r#"
+            update post_notices set deletion_date = expiry_date
+            where deletion_date is null and expiry_date <= now()
+            returning id, post_id as "post_id!", body
+            "#
+        )
+        .fetch_all(&mut tx)
+        .await?;
+
+        for n in expired.iter() {
+            record_notice_history(
+                &mut tx,
+                n.post_id,
+                n.id,
+                POST_NOTICE_REMOVED,
+                n.body.as_deref(),
+                None,
+                None,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(expired.len() as u64)
+    }
+}
+
+// Adds a notice to `pid`, expiring at `expiry_date` or else after the duration of its type
+pub async fn insert_notice(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    post_notice_type_id: i64,
+    body: Option<&str>,
+    expiry_date: Option<NaiveDateTime>,
+    uid: i64,
+    username: &str,
+) -> sqlx::Result<NoticeOutcome<PostNoticeResponse>> {
+    let t = sqlx::query!(
+        r#"
+        select class_id, name, body, post_notice_duration_id from post_notice_types where id = $1
+        "#,
+        post_notice_type_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let t = match t {
+        Some(t) => t,
+        None => return Ok(NoticeOutcome::InvalidType),
+    };
+    let exists = sqlx::query!(
+        r#"
+        select exists(select 1 from post_notices
+            where post_id = $1 and post_notice_type_id = $2 and deletion_date is null
+                and (expiry_date is null or expiry_date > now())) as "exists!"
+        "#,
+        pid,
+        post_notice_type_id
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .exists;
+    if exists {
+        return Ok(NoticeOutcome::Exists);
+    }
+
+    let body = body.map(|b| b.to_owned()).or(t.body).unwrap_or_default();
+    let n = sqlx::query!(
+        r#"
+        insert into post_notices (post_id, post_notice_type_id, body, owner_user_id, expiry_date)
+        values ($1, $2, $3, $4, coalesce($6::timestamp, now() + make_interval(days => $5)))
+        returning id, creation_date, expiry_date
+        "#,
+        pid,
+        post_notice_type_id,
+        body,
+        uid,
+        duration_days(t.post_notice_duration_id),
+        expiry_date
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_notice_history(
+        tx,
+        pid,
+        n.id,
+        POST_NOTICE_ADDED,
+        Some(&body),
+        Some(uid),
+        Some(username),
+    )
+    .await?;
+
+    let notice = PostNoticeRow {
+        id: n.id,
+        post_id: pid,
+        post_notice_type_id,
+        class_id: t.class_id.unwrap_or_default(),
+        name: t.name.unwrap_or_default(),
+        body,
+        owner_user_id: Some(uid),
+        creation_date: n.creation_date,
+        expiry_date: n.expiry_date,
+    };
+    Ok(NoticeOutcome::Done(PostNoticeResponse::from(notice)))
+}
+
+// Removes the active notices of a class from `pid`, `uid` is None when the system removes them
+pub async fn remove_notices(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    class_id: i32,
+    uid: Option<i64>,
+    username: Option<&str>,
+) -> sqlx::Result<()> {
+    let removed = sqlx::query!(
+        r#"
+        update post_notices n set deletion_date = now(), deletion_user_id = $3
+        from post_notice_types t
+        where n.post_id = $1 and t.id = n.post_notice_type_id and t.class_id = $2
+            and n.deletion_date is null and (n.expiry_date is null or n.expiry_date > now())
+        returning n.id, n.body
+        "#,
+        pid,
+        class_id,
+        uid
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for n in removed {
+        record_notice_history(
+            tx,
+            pid,
+            n.id,
+            POST_NOTICE_REMOVED,
+            n.body.as_deref(),
+            uid,
+            username,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Notices currently shown on the given posts, oldest first
+pub async fn active_notices<'e, E>(e: E, pids: &[i64]) -> sqlx::Result<Vec<PostNoticeRow>>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query_as!(
+        PostNoticeRow,
+        r#"
+        select n.id, n.post_id as "post_id!", n.post_notice_type_id as "post_notice_type_id!",
+            coalesce(t.class_id, 0) as "class_id!", coalesce(t.name, '') as "name!",
+            coalesce(n.body, t.body, '') as "body!", n.owner_user_id, n.creation_date,
+            n.expiry_date
+        from post_notices n join post_notice_types t on t.id = n.post_notice_type_id
+        where n.post_id = any($1) and n.deletion_date is null
+            and (n.expiry_date is null or n.expiry_date > now())
+        order by n.creation_date, n.id
+        "#,
+        pids
+    )
+    .fetch_all(e)
+    .await
+}
+
+// the history comment holds the notice id and the text its body
+async fn record_notice_history(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    nid: i64,
+    post_history_type_id: i64,
+    body: Option<&str>,
+    uid: Option<i64>,
+    username: Option<&str>,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        insert into post_history (post_history_type_id, post_id, revision_guid, user_id,
+            user_display_name, comment, text)
+        values ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        post_history_type_id,
+        pid,
+        Uuid::new_v4(),
+        uid,
+        username,
+        nid.to_string(),
+        body
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+// Ends notices whose duration has run out, every Config::notice_check_interval seconds
+pub async fn expire_notices_task(state: AppStateRaw) {
+    let mut interval =
+        actix_rt::time::interval(Duration::from_secs(state.config.notice_check_interval));
+    loop {
+        interval.tick().await;
+        match (&state).expire_notices().await {
+            Ok(0) => {}
+            Ok(n) => info!("expired {} post notices", n),
+            Err(e) => error!("expire post notices error: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::bounties::bounty::BOUNTY_PERIOD_DAYS;
+use chrono::NaiveDateTime;
+
+// post_history_type_id written when a notice is added to or removed from a post,
+// its comment holds the post_notices id
+pub const POST_NOTICE_ADDED: i64 = 33;
+pub const POST_NOTICE_REMOVED: i64 = 34;
+
+// post_notice_types.class_id of the notices started with a bounty
+pub const BOUNTY_NOTICE_CLASS: i32 = 2;
+
+// notice type of a bounty started without a reason, "draw attention"
+pub const DEFAULT_BOUNTY_NOTICE_TYPE: i64 = 11;
+
+// Days a notice stays on a post for a post_notice_duration_id, None when it does not expire
+pub fn duration_days(post_notice_duration_id: Option<i32>) -> Option<i32> {
+    match post_notice_duration_id {
+        Some(1) => Some(BOUNTY_PERIOD_DAYS),
+        _ => None,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NoticeTypeResponse {
+    pub id: String,
+    pub class_id: i32,
+    pub name: String,
+    pub body: String,
+    pub duration_days: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NoticeTypesResponse {
+    pub notice_types: Vec<NoticeTypeResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct AddNoticeReq {
+    pub post_notice_type_id: i64,
+    // replaces the text of the notice type
+    #[validate(length(min = 1, max = 1024))]
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostNoticeResponse {
+    pub id: String,
+    pub post_notice_type_id: String,
+    pub class_id: i32,
+    pub name: String,
+    pub body: String,
+    pub owner_user_id: Option<String>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub expiry_date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct PostNoticeRow {
+    pub id: i64,
+    pub post_id: i64,
+    pub post_notice_type_id: i64,
+    pub class_id: i32,
+    pub name: String,
+    pub body: String,
+    pub owner_user_id: Option<i64>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub expiry_date: Option<NaiveDateTime>,
+}
+
+impl From<PostNoticeRow> for PostNoticeResponse {
+    fn from(n: PostNoticeRow) -> Self {
+        Self {
+            id: n.id.to_string(),
+            post_notice_type_id: n.post_notice_type_id.to_string(),
+            class_id: n.class_id,
+            name: n.name,
+            body: n.body,
+            owner_user_id: n.owner_user_id.map(|id| id.to_string()),
+            creation_date: n.creation_date,
+            expiry_date: n.expiry_date,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NoticeOutcome<T> {
+    Done(T),
+    NotFound,
+    // unknown notice type, or a bounty notice outside of a bounty
+    InvalidType,
+    // the post already shows a notice of that type
+    Exists,
+}
+
+impl<T> NoticeOutcome<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> NoticeOutcome<U> {
+        match self {
+            NoticeOutcome::Done(t) => NoticeOutcome::Done(f(t)),
+            NoticeOutcome::NotFound => NoticeOutcome::NotFound,
+            NoticeOutcome::InvalidType => NoticeOutcome::InvalidType,
+            NoticeOutcome::Exists => NoticeOutcome::Exists,
+        }
+    }
+}
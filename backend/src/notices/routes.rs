@@ -0,0 +1,97 @@
+use super::dao::INotice;
+use super::notice::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::Privileged;
+use crate::state::AppState;
+
+use actix_web::{get, post, web, Responder};
+use serde::Serialize;
+use validator::Validate;
+
+#[get("/post-notice-types")]
+async fn get_notice_types(state: AppState) -> impl Responder {
+    match state.get_ref().get_notice_types().await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/post/{id}/notice")]
+async fn add_notice(
+    params: web::Path<i64>,
+    form: web::Json<AddNoticeReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("add notice {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can add post notices");
+    }
+    match state
+        .get_ref()
+        .add_notice(pid, auth.claims.id, &auth.claims.username, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome),
+        Err(e) => {
+            error!("add notice {} {:?} error: {:?}", pid, form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/remove-notice/{id}")]
+async fn remove_notice(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let nid = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can remove post notices");
+    }
+    match state
+        .get_ref()
+        .remove_notice(nid, auth.claims.id, &auth.claims.username)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome.map(|()| true)),
+        Err(e) => {
+            error!("remove notice {} error: {:?}", nid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result<T: Serialize>(outcome: NoticeOutcome<T>) -> ApiResult<T> {
+    match outcome {
+        NoticeOutcome::Done(data) => ApiResult::new().code(200).with_msg("").with_data(data),
+        NoticeOutcome::NotFound => ApiResult::new().code(404).with_msg("Not found"),
+        NoticeOutcome::InvalidType => ApiResult::new()
+            .code(400)
+            .with_msg("Unknown notice type, bounty notices come with a bounty"),
+        NoticeOutcome::Exists => ApiResult::new()
+            .code(409)
+            .with_msg("The post already has this notice"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_notice_types);
+    cfg.service(add_notice);
+    cfg.service(remove_notice);
+}
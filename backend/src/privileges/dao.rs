@@ -0,0 +1,108 @@
+use super::wiki::*;
+use crate::middlewares::privilege::Privilege;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::{Change, EDIT_BODY, INITIAL_BODY, INITIAL_TITLE};
+use crate::state::AppStateRaw;
+
+use std::collections::HashMap;
+
+#[async_trait]
+pub trait IPrivilegeWiki: std::ops::Deref<Target = AppStateRaw> {
+    async fn get_privilege_wikis(&self) -> sqlx::Result<HashMap<String, String>>;
+    async fn set_privilege_wiki(
+        &self,
+        privilege: Privilege,
+        uid: i64,
+        username: &str,
+        form: &PrivilegeWikiReq,
+    ) -> sqlx::Result<()>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IPrivilegeWiki for &AppStateRaw {
+    // privilege key => description
+    async fn get_privilege_wikis(&self) -> sqlx::Result<HashMap<String, String>> {
+        let wr = sqlx::query!(
+            r#"
+            select title, body from posts where post_type_id = $1 and deletion_date is null
+            "#,
+            PRIVILEGE_WIKI_POST_TYPE
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        Ok(wr
+            .into_iter()
+            .filter_map(|w| Some((w.title?, w.body.unwrap_or_default())))
+            .collect())
+    }
+
+    async fn set_privilege_wiki(
+        &self,
+        privilege: Privilege,
+        uid: i64,
+        username: &str,
+        form: &PrivilegeWikiReq,
+    ) -> sqlx::Result<()> {
+        let mut tx = self.sql.begin().await?;
+
+        let existing = sqlx::query!(
+            r#"
+            update posts set body = $1, last_editor_user_id = $2, last_editor_display_name = $3,
+                last_edit_date = now(), last_activity_date = now()
+            where post_type_id = $4 and title = $5 and deletion_date is null
+            returning id
+            "#,
+            form.body,
+            uid,
+            username,
+            PRIVILEGE_WIKI_POST_TYPE,
+            privilege.key()
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        match existing {
+            Some(p) => {
+                let changes = [Change {
+                    post_history_type_id: EDIT_BODY,
+                    text: &form.body,
+                }];
+                record_revision(&mut tx, p.id, uid, username, None, &changes).await?;
+            }
+            None => {
+                let p = sqlx::query!(
+                    r#"
+                    insert into posts (post_type_id, title, body, owner_user_id, owner_display_name,
+                        score, view_count)
+                    values ($1, $2, $3, $4, $5, 0, 0) returning id
+                    "#,
+                    PRIVILEGE_WIKI_POST_TYPE,
+                    privilege.key(),
+                    form.body,
+                    uid,
+                    username
+                )
+                .fetch_one(&mut tx)
+                .await?;
+
+                let changes = [
+                    Change {
+                        post_history_type_id: INITIAL_TITLE,
+                        text: privilege.key(),
+                    },
+                    Change {
+                        post_history_type_id: INITIAL_BODY,
+                        text: &form.body,
+                    },
+                ];
+                record_revision(&mut tx, p.id, uid, username, None, &changes).await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use super::dao::IPrivilegeWiki;
+use super::wiki::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{get, post, web, Responder};
+use validator::Validate;
+
+#[get("/privileges")]
+async fn get_privileges(auth: Option<Privileged>, state: AppState) -> impl Responder {
+    let mut wikis = match state.get_ref().get_privilege_wikis().await {
+        Ok(w) => w,
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            return ApiResult::new().code(500).with_msg(e.to_string());
+        }
+    };
+
+    let config = &state.config.privileges;
+    let privileges = Privilege::ALL
+        .iter()
+        .map(|p| PrivilegeResponse {
+            privilege: *p,
+            reputation: p.reputation(config),
+            description: wikis.remove(p.key()),
+            granted: auth.as_ref().map(|a| a.has(*p, config)),
+        })
+        .collect();
+
+    ApiResult::new()
+        .code(200)
+        .with_msg("")
+        .with_data(PrivilegesResponse { privileges })
+}
+
+#[post("/privilege/{key}/wiki")]
+async fn edit_privilege_wiki(
+    params: web::Path<String>,
+    form: web::Json<PrivilegeWikiReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let key = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("edit privilege wiki {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can edit privilege wikis");
+    }
+    let privilege = match Privilege::from_key(&key) {
+        Some(p) => p,
+        None => return ApiResult::new().code(404).with_msg("Privilege not found"),
+    };
+    match state
+        .get_ref()
+        .set_privilege_wiki(privilege, auth.claims.id, &auth.claims.username, &form)
+        .await
+    {
+        Ok(()) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Err(e) => {
+            error!("edit privilege wiki {} error: {:?}", key, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_privileges);
+    cfg.service(edit_privilege_wiki);
+}
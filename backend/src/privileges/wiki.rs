@@ -0,0 +1,24 @@
+use crate::middlewares::privilege::Privilege;
+
+// post_type_id of a privilege wiki, its title is the privilege key
+pub const PRIVILEGE_WIKI_POST_TYPE: i16 = 8;
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct PrivilegeWikiReq {
+    #[validate(length(min = 1, max = 102400))]
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PrivilegeResponse {
+    pub privilege: Privilege,
+    pub reputation: i64,
+    pub description: Option<String>,
+    // whether the signed in user holds it
+    pub granted: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PrivilegesResponse {
+    pub privileges: Vec<PrivilegeResponse>,
+}
@@ -0,0 +1,317 @@
+use super::question::*;
+use crate::answers::answer::Outcome;
+use crate::badges::dao::check_badges;
+use crate::bounties::dao::active_bounty;
+use crate::closing::close::{DUPLICATE_LINK_TYPE, POST_CLOSED};
+use crate::merging::merge::MERGED_LINK_TYPE;
+use crate::notices::dao::active_notices;
+use crate::notices::notice::PostNoticeResponse;
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Snapshot;
+use crate::state::AppStateRaw;
+use crate::synonyms::dao::resolve_synonyms;
+use crate::tags::dao::set_post_tags;
+use crate::utils::slug::create_slug;
+
+#[async_trait]
+pub trait IQuestion: std::ops::Deref<Target = AppStateRaw> {
+    async fn insert_question(&self, uid: i64, username: &str, form: &AskQuestion)
+        -> sqlx::Result<i64>;
+    async fn get_question(
+        &self,
+        qid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<QuestionResponse>;
+    async fn update_question(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        any_post: bool,
+        any_wiki: bool,
+        form: &AskQuestion,
+    ) -> sqlx::Result<Outcome>;
+    async fn get_questions(&self, form: &QuestionsReq) -> sqlx::Result<QuestionsResponse>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IQuestion for &AppStateRaw {
+    async fn insert_question(
+        &self,
+        uid: i64,
+        username: &str,
+        form: &AskQuestion,
+    ) -> sqlx::Result<i64> {
+        let mut tx = self.sql.begin().await?;
+
+        let tags = resolve_synonyms(&mut tx, &form.tags).await?;
+        let mut snapshot = form.snapshot();
+        snapshot.tags = tags.join(" ");
+
+        let r = sqlx::query!(
+            r#"
+            insert into posts (post_type_id, title, body, tags, owner_user_id, owner_display_name,
+                score, view_count)
+            values ($1, $2, $3, $4, $5, $6, 0, 0) returning id
+            "#,
+            QUESTION_POST_TYPE,
+            snapshot.title,
+            snapshot.body,
+            snapshot.tags,
+            uid,
+            username
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        set_post_tags(&mut tx, r.id, &tags).await?;
+
+        let changes = snapshot.initial_changes();
+        record_revision(&mut tx, r.id, uid, username, None, &changes).await?;
+
+        tx.commit().await?;
+
+        check_badges(self, Some(uid)).await;
+
+        Ok(r.id)
+    }
+
+    async fn get_question(
+        &self,
+        qid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<QuestionResponse> {
+        // deleted questions stay visible to their author and moderators
+        let q = sqlx::query!(
+            r#"
+            update posts set view_count = coalesce(view_count, 0) + 1
+            where id = $1 and post_type_id = $2
+                and (deletion_date is null or $3 or owner_user_id = $4)
+            returning id, title, body, tags, score, view_count, answer_count, comment_count,
+                accepted_answer_id, owner_user_id, owner_display_name, last_editor_display_name,
+                creation_date, last_edit_date, last_activity_date, closed_date, deletion_date,
+                locked_date, protected_date, community_owned_date
+            "#,
+            qid,
+            QUESTION_POST_TYPE,
+            moderator,
+            viewer
+        )
+        .fetch_one(&self.sql)
+        .await?;
+
+        // the reason of the latest close is kept in the comment of its history row
+        let close_reason = match q.closed_date {
+            Some(_) => sqlx::query!(
+                r#"
+                select comment from post_history where post_id = $1 and post_history_type_id = $2
+                order by creation_date desc, id desc limit 1
+                "#,
+                qid,
+                POST_CLOSED
+            )
+            .fetch_optional(&self.sql)
+            .await?
+            .and_then(|r| r.comment)
+            .and_then(|c| c.parse::<i32>().ok())
+            .and_then(|id| self.config.close_reasons.iter().find(|r| r.id == id))
+            .map(|r| r.name.clone()),
+            None => None,
+        };
+
+        let links = sqlx::query!(
+            r#"
+            select p.id, p.title, l.link_type_id from post_links l
+            join posts p on p.id = l.related_post_id
+            where l.post_id = $1 and l.link_type_id in ($2, $3) and p.deletion_date is null
+            "#,
+            qid,
+            DUPLICATE_LINK_TYPE,
+            MERGED_LINK_TYPE
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let mut duplicate_of = Vec::new();
+        let mut merged_into = None;
+        for l in links {
+            let title = l.title.unwrap_or_default();
+            let slug = create_slug(&title).await;
+            let link = QuestionLink {
+                id: l.id.to_string(),
+                title,
+                slug,
+            };
+            match l.link_type_id {
+                Some(MERGED_LINK_TYPE) => merged_into = Some(link),
+                _ => duplicate_of.push(link),
+            }
+        }
+
+        let bounty = active_bounty(&self.sql, qid).await?.map(|b| b.notice());
+        let notices = active_notices(&self.sql, &[qid])
+            .await?
+            .into_iter()
+            .map(PostNoticeResponse::from)
+            .collect();
+
+        let title = q.title.unwrap_or_default();
+        let slug = create_slug(&title).await;
+        Ok(QuestionResponse {
+            id: q.id.to_string(),
+            title,
+            slug,
+            body: q.body.unwrap_or_default(),
+            tags: split_tags(q.tags),
+            score: q.score.unwrap_or_default(),
+            view_count: q.view_count.unwrap_or_default(),
+            answer_count: q.answer_count.unwrap_or_default(),
+            comment_count: q.comment_count.unwrap_or_default(),
+            accepted_answer_id: q.accepted_answer_id.map(|id| id.to_string()),
+            owner_user_id: q.owner_user_id.unwrap_or_default().to_string(),
+            owner_display_name: q.owner_display_name.unwrap_or_default(),
+            last_editor_display_name: q.last_editor_display_name,
+            creation_date: q.creation_date,
+            last_edit_date: q.last_edit_date,
+            last_activity_date: q.last_activity_date,
+            closed_date: q.closed_date,
+            deletion_date: q.deletion_date,
+            locked_date: q.locked_date,
+            protected_date: q.protected_date,
+            community_owned_date: q.community_owned_date,
+            close_reason,
+            duplicate_of,
+            merged_into,
+            bounty,
+            notices,
+        })
+    }
+
+    async fn update_question(
+        &self,
+        qid: i64,
+        uid: i64,
+        username: &str,
+        any_post: bool,
+        any_wiki: bool,
+        form: &AskQuestion,
+    ) -> sqlx::Result<Outcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let q = sqlx::query!(
+            r#"
+            select owner_user_id, title, body, tags, locked_date,
+                community_owned_date is not null as "wiki!"
+            from posts
+            where id = $1 and post_type_id = $2 and deletion_date is null
+            for update
+            "#,
+            qid,
+            QUESTION_POST_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let q = match q {
+            Some(q) => q,
+            None => return Ok(Outcome::NotFound),
+        };
+        if q.locked_date.is_some() {
+            return Ok(Outcome::Locked);
+        }
+        // only owners and users who may edit any post, or any community wiki
+        if q.owner_user_id != Some(uid) && !any_post && !(q.wiki && any_wiki) {
+            return Ok(Outcome::Forbidden);
+        }
+        let old = Snapshot {
+            title: q.title.unwrap_or_default(),
+            body: q.body.unwrap_or_default(),
+            tags: q.tags.unwrap_or_default(),
+        };
+        let tags = resolve_synonyms(&mut tx, &form.tags).await?;
+        let mut snapshot = form.snapshot();
+        snapshot.tags = tags.join(" ");
+        let changes = snapshot.edit_changes(&old);
+        if changes.is_empty() {
+            return Ok(Outcome::Done(()));
+        }
+
+        sqlx::query!(
+            r#"
+            update posts set title = $1, body = $2, tags = $3, last_editor_user_id = $4,
+                last_editor_display_name = $5, last_edit_date = now(), last_activity_date = now()
+            where id = $6
+            "#,
+            snapshot.title,
+            snapshot.body,
+            snapshot.tags,
+            uid,
+            username,
+            qid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        set_post_tags(&mut tx, qid, &tags).await?;
+        record_revision(&mut tx, qid, uid, username, None, &changes).await?;
+
+        tx.commit().await?;
+
+        Ok(Outcome::Done(()))
+    }
+
+    async fn get_questions(&self, form: &QuestionsReq) -> sqlx::Result<QuestionsResponse> {
+        let per_page = self.config.questions_per_page as i64;
+        let offset = form.page.max(0) * per_page;
+
+        let count = sqlx::query!(
+            r#"
+            select count(1) as "count!" from posts where post_type_id = $1 and deletion_date is null
+            "#,
+            QUESTION_POST_TYPE
+        )
+        .fetch_one(&self.sql)
+        .await?
+        .count;
+
+        let qr = sqlx::query!(
+            r#"
+            select id, title, tags, score, view_count, answer_count, owner_user_id,
+                owner_display_name, last_activity_date
+            from posts where post_type_id = $1 and deletion_date is null
+            order by last_activity_date desc, id desc limit $2 offset $3
+            "#,
+            QUESTION_POST_TYPE,
+            per_page,
+            offset
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let mut qrs = QuestionsResponse {
+            questions: Vec::new(),
+            count,
+        };
+        for q in qr {
+            let title = q.title.unwrap_or_default();
+            let slug = create_slug(&title).await;
+            qrs.questions.push(QR {
+                id: q.id.to_string(),
+                title,
+                slug,
+                tags: split_tags(q.tags),
+                score: q.score.unwrap_or_default(),
+                view_count: q.view_count.unwrap_or_default(),
+                answer_count: q.answer_count.unwrap_or_default(),
+                owner_user_id: q.owner_user_id.unwrap_or_default().to_string(),
+                owner_display_name: q.owner_display_name.unwrap_or_default(),
+                last_activity_date: q.last_activity_date,
+            });
+        }
+
+        Ok(qrs)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod dao;
+pub mod question;
+pub mod routes;
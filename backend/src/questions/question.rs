@@ -0,0 +1,134 @@
+use crate::bounties::bounty::BountyNotice;
+use crate::notices::notice::PostNoticeResponse;
+use crate::revisions::revision::Snapshot;
+use chrono::NaiveDateTime;
+use validator::ValidationError;
+
+// post_type_id of a question in the posts table
+pub const QUESTION_POST_TYPE: i16 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct AskQuestion {
+    #[validate(length(min = 15, max = 150))]
+    pub title: String,
+    #[validate(length(min = 30, max = 102400))]
+    pub body: String,
+    #[validate(length(min = 1, max = 5), custom = "validate_tags")]
+    pub tags: Vec<String>,
+}
+
+impl AskQuestion {
+    // posts.tags keeps a space separated copy of the tags
+    pub fn joined_tags(&self) -> String {
+        self.tags.join(" ")
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            title: self.title.clone(),
+            body: self.body.clone(),
+            tags: self.joined_tags(),
+        }
+    }
+}
+
+fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    for (i, tag) in tags.iter().enumerate() {
+        if tags[..i].contains(tag) {
+            return Err(ValidationError::new("bad_tag: duplicate tag"));
+        }
+        validate_tag(tag)?;
+    }
+
+    Ok(())
+}
+
+pub fn validate_tag(tag: &str) -> Result<(), ValidationError> {
+    if tag.is_empty() || tag.len() > 64 {
+        return Err(ValidationError::new("bad_tag: length must be 1 to 64"));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-+#.".contains(c))
+    {
+        return Err(ValidationError::new("bad_tag: invalid character"));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AskResponse {
+    pub id: String,
+    pub slug: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuestionResponse {
+    pub id: String,
+    pub title: String,
+    pub slug: String,
+    pub body: String,
+    pub tags: Vec<String>,
+    pub score: i64,
+    pub view_count: i64,
+    pub answer_count: i32,
+    pub comment_count: i32,
+    pub accepted_answer_id: Option<String>,
+    pub owner_user_id: String,
+    pub owner_display_name: String,
+    pub last_editor_display_name: Option<String>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub last_edit_date: Option<NaiveDateTime>,
+    pub last_activity_date: Option<NaiveDateTime>,
+    pub closed_date: Option<NaiveDateTime>,
+    pub deletion_date: Option<NaiveDateTime>,
+    pub locked_date: Option<NaiveDateTime>,
+    pub protected_date: Option<NaiveDateTime>,
+    pub community_owned_date: Option<NaiveDateTime>,
+    pub close_reason: Option<String>,
+    pub duplicate_of: Vec<QuestionLink>,
+    // the question this one was merged into
+    pub merged_into: Option<QuestionLink>,
+    pub bounty: Option<BountyNotice>,
+    pub notices: Vec<PostNoticeResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuestionLink {
+    pub id: String,
+    pub title: String,
+    pub slug: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuestionsReq {
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QR {
+    pub id: String,
+    pub title: String,
+    pub slug: String,
+    pub tags: Vec<String>,
+    pub score: i64,
+    pub view_count: i64,
+    pub answer_count: i32,
+    pub owner_user_id: String,
+    pub owner_display_name: String,
+    pub last_activity_date: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuestionsResponse {
+    pub questions: Vec<QR>,
+    pub count: i64,
+}
+
+pub fn split_tags(tags: Option<String>) -> Vec<String> {
+    tags.unwrap_or_default()
+        .split_whitespace()
+        .map(|t| t.to_owned())
+        .collect()
+}
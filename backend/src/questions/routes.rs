@@ -0,0 +1,128 @@
+use super::dao::IQuestion;
+use super::question::*;
+use crate::answers::answer::Outcome;
+use crate::api::ApiResult;
+use crate::middlewares::auth::AuthorizationService;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+use crate::suggestions::routes::submit_suggested_edit;
+use crate::users::dao::IUser;
+use crate::utils::slug::create_slug;
+
+use actix_web::{get, post, web, Responder};
+use validator::Validate;
+
+#[post("/create-question")]
+async fn create_question(
+    form: web::Json<AskQuestion>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("create question {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    match state
+        .get_ref()
+        .insert_question(auth.claims.id, &auth.claims.username, &form)
+        .await
+    {
+        Ok(id) => {
+            let res = AskResponse {
+                id: id.to_string(),
+                slug: create_slug(&form.title).await,
+            };
+            ApiResult::new().code(200).with_msg("").with_data(res)
+        }
+        Err(e) => {
+            error!("create question {:?} error: {:?}", form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+// the slug is only decorative, the question is looked up by id
+#[get("/question/{id}/{slug}")]
+async fn get_question(
+    params: web::Path<(i64, String)>,
+    auth: Option<AuthorizationService>,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.0;
+    let viewer = auth.map(|a| a.claims.id);
+    let moderator = match viewer {
+        Some(uid) => state.get_ref().is_moderator(uid).await.unwrap_or(false),
+        None => false,
+    };
+    match state.get_ref().get_question(qid, viewer, moderator).await {
+        Ok(q) => ApiResult::new().code(200).with_msg("").with_data(q),
+        Err(sqlx::Error::RowNotFound) => ApiResult::new().code(404).with_msg("Question not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/edit-question/{id}")]
+async fn edit_question(
+    params: web::Path<i64>,
+    form: web::Json<AskQuestion>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let qid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("edit question {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    let privileges = &state.config.privileges;
+    let any_post = auth.has(Privilege::EditWithoutReview, privileges);
+    let any_wiki = auth.has(Privilege::EditCommunityWiki, privileges);
+    match state
+        .get_ref()
+        .update_question(
+            qid,
+            auth.claims.id,
+            &auth.claims.username,
+            any_post,
+            any_wiki,
+            &form,
+        )
+        .await
+    {
+        Ok(Outcome::Done(())) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Ok(Outcome::Locked) => ApiResult::new().code(403).with_msg("This question is locked"),
+        Ok(Outcome::Forbidden) => {
+            submit_suggested_edit(&state, qid, auth.claims.id, &form.snapshot()).await
+        }
+        Ok(_) => ApiResult::new().code(404).with_msg("Question not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/questions")]
+async fn get_questions(form: web::Json<QuestionsReq>, state: AppState) -> impl Responder {
+    let form = form.into_inner();
+    match state.get_ref().get_questions(&form).await {
+        Ok(qrs) => ApiResult::new().code(200).with_msg("").with_data(qrs),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_question);
+    cfg.service(get_question);
+    cfg.service(edit_question);
+    cfg.service(get_questions);
+}
@@ -0,0 +1,178 @@
+use super::queues::{ReviewQueue, QUEUES};
+use super::review::*;
+use crate::questions::question::split_tags;
+use crate::state::{AppStateRaw, SqlConnection};
+
+#[async_trait]
+pub trait IReview: std::ops::Deref<Target = AppStateRaw> {
+    async fn get_queues(&self, uid: i64) -> sqlx::Result<QueuesResponse>;
+    async fn get_review_items(
+        &self,
+        queue: &dyn ReviewQueue,
+        uid: i64,
+        form: &ReviewItemsReq,
+    ) -> sqlx::Result<ReviewItemsResponse>;
+    async fn review(
+        &self,
+        queue: &dyn ReviewQueue,
+        pid: i64,
+        who: Reviewer<'_>,
+        form: &ReviewTaskReq,
+    ) -> sqlx::Result<ReviewOutcome<ReviewTaskResponse>>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IReview for &AppStateRaw {
+    async fn get_queues(&self, uid: i64) -> sqlx::Result<QueuesResponse> {
+        let queues = QUEUES
+            .iter()
+            .map(|q| QueueResponse {
+                key: q.key().to_owned(),
+                privilege: q.privilege(),
+                actions: q.actions().to_vec(),
+            })
+            .collect();
+
+        Ok(QueuesResponse {
+            queues,
+            reviews_today: reviews_today(&mut *self.sql.acquire().await?, uid).await?,
+            daily_limit: self.config.review_daily_limit,
+        })
+    }
+
+    async fn get_review_items(
+        &self,
+        queue: &dyn ReviewQueue,
+        uid: i64,
+        form: &ReviewItemsReq,
+    ) -> sqlx::Result<ReviewItemsResponse> {
+        let per_page = self.config.questions_per_page as i64;
+        let filter = QueueFilter {
+            uid,
+            pid: None,
+            limit: per_page,
+            offset: form.page.max(0) * per_page,
+        };
+        let mut conn = self.sql.acquire().await?;
+        let pids = queue.pending(&self.config, &mut conn, filter).await?;
+
+        let ir = sqlx::query!(
+            r#"
+            select p.id, p.post_type_id, coalesce(p.parent_id, p.id) as "question_id!",
+                coalesce(p.title, q.title) as title, p.body, coalesce(p.tags, q.tags) as tags,
+                p.owner_user_id, p.owner_display_name, p.score, p.creation_date
+            from posts p left join posts q on q.id = p.parent_id
+            where p.id = any($1)
+            "#,
+            &pids
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        // in the order of the queue
+        let items = pids
+            .iter()
+            .filter_map(|pid| ir.iter().find(|i| i.id == *pid))
+            .map(|i| ReviewItemResponse {
+                post_id: i.id.to_string(),
+                post_type_id: i.post_type_id.unwrap_or_default(),
+                question_id: i.question_id.to_string(),
+                title: i.title.clone().unwrap_or_default(),
+                body: i.body.clone().unwrap_or_default(),
+                tags: split_tags(i.tags.clone()),
+                owner_user_id: i.owner_user_id.map(|id| id.to_string()),
+                owner_display_name: i.owner_display_name.clone().unwrap_or_default(),
+                score: i.score.unwrap_or_default(),
+                creation_date: i.creation_date,
+            })
+            .collect();
+
+        Ok(ReviewItemsResponse {
+            queue: queue.key().to_owned(),
+            actions: queue.actions().to_vec(),
+            items,
+        })
+    }
+
+    // skips are always allowed, other reviews count towards the daily limit
+    // the checks, the action and the record share a transaction holding the reviewer and the post
+    async fn review(
+        &self,
+        queue: &dyn ReviewQueue,
+        pid: i64,
+        who: Reviewer<'_>,
+        form: &ReviewTaskReq,
+    ) -> sqlx::Result<ReviewOutcome<ReviewTaskResponse>> {
+        if !queue.actions().contains(&form.action) {
+            return Ok(ReviewOutcome::InvalidAction);
+        }
+        let counted = form.action != ReviewAction::Skip;
+        let mut tx = self.sql.begin().await?;
+        sqlx::query!("select id from users where id = $1 for update", who.uid)
+            .fetch_optional(&mut tx)
+            .await?;
+        sqlx::query!("select id from posts where id = $1 for update", pid)
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let reviews = reviews_today(&mut tx, who.uid).await?;
+        if counted && reviews >= self.config.review_daily_limit {
+            return Ok(ReviewOutcome::LimitReached);
+        }
+
+        let filter = QueueFilter {
+            uid: who.uid,
+            pid: Some(pid),
+            limit: 1,
+            offset: 0,
+        };
+        if queue
+            .pending(&self.config, &mut tx, filter)
+            .await?
+            .is_empty()
+        {
+            return Ok(ReviewOutcome::NotFound);
+        }
+        let outcome = queue.apply(&self.config, &mut tx, pid, who, form).await?;
+        if let ReviewOutcome::Done(()) = outcome {
+            // a later suggested edit of the post is reviewed again
+            sqlx::query!(
+                r#"
+                insert into reviews (queue_id, post_id, user_id, review_action_id)
+                values ($1, $2, $3, $4)
+                on conflict (queue_id, post_id, user_id) do update
+                set review_action_id = excluded.review_action_id, creation_date = now()
+                "#,
+                queue.id(),
+                pid,
+                who.uid,
+                form.action.id()
+            )
+            .execute(&mut tx)
+            .await?;
+            tx.commit().await?;
+        }
+
+        Ok(outcome.map(|()| ReviewTaskResponse {
+            reviews_today: reviews + counted as i64,
+            daily_limit: self.config.review_daily_limit,
+        }))
+    }
+}
+
+async fn reviews_today(conn: &mut SqlConnection, uid: i64) -> sqlx::Result<i64> {
+    let reviews = sqlx::query!(
+        r#"
+        select count(1) as "count!" from reviews
+        where user_id = $1 and review_action_id <> $2 and creation_date >= current_date
+        "#,
+        uid,
+        ReviewAction::Skip.id()
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .count;
+
+    Ok(reviews)
+}
@@ -0,0 +1,4 @@
+pub mod dao;
+pub mod queues;
+pub mod review;
+pub mod routes;
@@ -0,0 +1,542 @@
+use super::review::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::closing::close::CloseOutcome;
+use crate::closing::dao::{close_vote_tx, reopen_vote_tx};
+use crate::config::Config;
+use crate::deletion::dao::delete_post_tx;
+use crate::deletion::delete::{DeleteOutcome, Deleter, DELETION_VOTE_TYPE};
+use crate::flags::flag::FlagType;
+use crate::middlewares::privilege::Privilege;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::state::{SqlConnection, SqlTx};
+use crate::suggestions::dao::{apply_edit, review_suggested_edit_tx};
+use crate::suggestions::suggestion::{ReviewVote, SuggestionOutcome};
+
+// A review queue selects its posts from the posts and votes tables, a new queue
+// implements this trait and is listed in QUEUES
+#[async_trait]
+pub trait ReviewQueue: Sync {
+    // stored in reviews.queue_id
+    fn id(&self) -> i16;
+    // names the queue in routes
+    fn key(&self) -> &'static str;
+    fn privilege(&self) -> Privilege;
+    fn actions(&self) -> &'static [ReviewAction];
+    // posts waiting for the reviewer, oldest first
+    async fn pending(
+        &self,
+        config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>>;
+    // carries the review over to the post in the transaction recording the review
+    async fn apply(
+        &self,
+        config: &Config,
+        tx: &mut SqlTx<'_>,
+        pid: i64,
+        who: Reviewer<'_>,
+        form: &ReviewTaskReq,
+    ) -> sqlx::Result<ReviewOutcome<()>> {
+        apply_review(config, tx, pid, who, form).await
+    }
+}
+
+pub static QUEUES: [&dyn ReviewQueue; 6] = [
+    &FirstPosts,
+    &LateAnswers,
+    &LowQuality,
+    &CloseVotes,
+    &ReopenVotes,
+    &SuggestedEdits,
+];
+
+pub fn find_queue(key: &str) -> Option<&'static dyn ReviewQueue> {
+    QUEUES.iter().copied().find(|q| q.key() == key)
+}
+
+// The first question or answer of a user
+pub struct FirstPosts;
+
+#[async_trait]
+impl ReviewQueue for FirstPosts {
+    fn id(&self) -> i16 {
+        1
+    }
+    fn key(&self) -> &'static str {
+        "first-posts"
+    }
+    fn privilege(&self) -> Privilege {
+        Privilege::EditWithoutReview
+    }
+    fn actions(&self) -> &'static [ReviewAction] {
+        &[
+            ReviewAction::LooksOk,
+            ReviewAction::Edit,
+            ReviewAction::Delete,
+            ReviewAction::Skip,
+        ]
+    }
+    async fn pending(
+        &self,
+        config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            r#"
+            select p.id from posts p
+            where p.post_type_id in ($1, $2) and p.deletion_date is null
+                and not exists(select 1 from posts o where o.owner_user_id = p.owner_user_id
+                    and o.post_type_id in ($1, $2) and o.id < p.id)
+                and p.owner_user_id is distinct from $3 and ($4::bigint is null or p.id = $4)
+                and not exists(select 1 from reviews r
+                    where r.post_id = p.id and r.queue_id = $5 and r.user_id = $3)
+                and (select count(1) from reviews r where r.post_id = p.id and r.queue_id = $5
+                    and r.review_action_id <> $6) < $7
+            order by p.creation_date, p.id limit $8 offset $9
+            "#,
+            QUESTION_POST_TYPE,
+            ANSWER_POST_TYPE,
+            filter.uid,
+            filter.pid,
+            self.id(),
+            ReviewAction::Skip.id(),
+            config.review_completions,
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids.into_iter().map(|r| r.id).collect())
+    }
+}
+
+// Answers posted long after their question
+pub struct LateAnswers;
+
+#[async_trait]
+impl ReviewQueue for LateAnswers {
+    fn id(&self) -> i16 {
+        2
+    }
+    fn key(&self) -> &'static str {
+        "late-answers"
+    }
+    fn privilege(&self) -> Privilege {
+        Privilege::EditWithoutReview
+    }
+    fn actions(&self) -> &'static [ReviewAction] {
+        &[
+            ReviewAction::LooksOk,
+            ReviewAction::Edit,
+            ReviewAction::Delete,
+            ReviewAction::Skip,
+        ]
+    }
+    async fn pending(
+        &self,
+        config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            r#"
+            select p.id from posts p join posts q on q.id = p.parent_id
+            where p.post_type_id = $1 and p.deletion_date is null
+                and p.creation_date > q.creation_date + make_interval(days => $2)
+                and p.owner_user_id is distinct from $3 and ($4::bigint is null or p.id = $4)
+                and not exists(select 1 from reviews r
+                    where r.post_id = p.id and r.queue_id = $5 and r.user_id = $3)
+                and (select count(1) from reviews r where r.post_id = p.id and r.queue_id = $5
+                    and r.review_action_id <> $6) < $7
+            order by p.creation_date, p.id limit $8 offset $9
+            "#,
+            ANSWER_POST_TYPE,
+            config.late_answer_days,
+            filter.uid,
+            filter.pid,
+            self.id(),
+            ReviewAction::Skip.id(),
+            config.review_completions,
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids.into_iter().map(|r| r.id).collect())
+    }
+}
+
+// Downvoted posts, posts with deletion votes and answers flagged as not an answer
+pub struct LowQuality;
+
+#[async_trait]
+impl ReviewQueue for LowQuality {
+    fn id(&self) -> i16 {
+        3
+    }
+    fn key(&self) -> &'static str {
+        "low-quality"
+    }
+    fn privilege(&self) -> Privilege {
+        Privilege::EditWithoutReview
+    }
+    fn actions(&self) -> &'static [ReviewAction] {
+        &[
+            ReviewAction::LooksOk,
+            ReviewAction::Edit,
+            ReviewAction::Delete,
+            ReviewAction::Skip,
+        ]
+    }
+    async fn pending(
+        &self,
+        config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            r#"
+            select p.id from posts p
+            where p.post_type_id in ($1, $2) and p.deletion_date is null
+                and (p.score < 0
+                    or exists(select 1 from votes v where v.post_id = p.id and v.vote_type_id = $3)
+                    or exists(select 1 from flags f where f.post_id = p.id and f.comment_id is null
+                        and f.flag_type_id = $4 and f.handled_date is null))
+                and p.owner_user_id is distinct from $5 and ($6::bigint is null or p.id = $6)
+                and not exists(select 1 from reviews r
+                    where r.post_id = p.id and r.queue_id = $7 and r.user_id = $5)
+                and (select count(1) from reviews r where r.post_id = p.id and r.queue_id = $7
+                    and r.review_action_id <> $8) < $9
+            order by p.creation_date, p.id limit $10 offset $11
+            "#,
+            QUESTION_POST_TYPE,
+            ANSWER_POST_TYPE,
+            DELETION_VOTE_TYPE,
+            FlagType::NotAnAnswer.id(),
+            filter.uid,
+            filter.pid,
+            self.id(),
+            ReviewAction::Skip.id(),
+            config.review_completions,
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids.into_iter().map(|r| r.id).collect())
+    }
+}
+
+// Open questions with pending close votes
+pub struct CloseVotes;
+
+#[async_trait]
+impl ReviewQueue for CloseVotes {
+    fn id(&self) -> i16 {
+        4
+    }
+    fn key(&self) -> &'static str {
+        "close-votes"
+    }
+    fn privilege(&self) -> Privilege {
+        Privilege::CloseVote
+    }
+    fn actions(&self) -> &'static [ReviewAction] {
+        &[
+            ReviewAction::Close,
+            ReviewAction::LeaveOpen,
+            ReviewAction::Edit,
+            ReviewAction::Skip,
+        ]
+    }
+    async fn pending(
+        &self,
+        config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            r#"
+            select p.id from posts p
+            where p.post_type_id = $1 and p.deletion_date is null and p.closed_date is null
+                and exists(select 1 from close_votes c where c.post_id = p.id and not c.reopen)
+                and not exists(select 1 from close_votes c where c.post_id = p.id
+                    and c.user_id = $2 and not c.reopen)
+                and p.owner_user_id is distinct from $2 and ($3::bigint is null or p.id = $3)
+                and not exists(select 1 from reviews r
+                    where r.post_id = p.id and r.queue_id = $4 and r.user_id = $2)
+                and (select count(1) from reviews r where r.post_id = p.id and r.queue_id = $4
+                    and r.review_action_id <> $5) < $6
+            order by p.creation_date, p.id limit $7 offset $8
+            "#,
+            QUESTION_POST_TYPE,
+            filter.uid,
+            filter.pid,
+            self.id(),
+            ReviewAction::Skip.id(),
+            config.review_completions,
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids.into_iter().map(|r| r.id).collect())
+    }
+}
+
+// Closed questions with pending reopen votes
+pub struct ReopenVotes;
+
+#[async_trait]
+impl ReviewQueue for ReopenVotes {
+    fn id(&self) -> i16 {
+        5
+    }
+    fn key(&self) -> &'static str {
+        "reopen-votes"
+    }
+    fn privilege(&self) -> Privilege {
+        Privilege::CloseVote
+    }
+    fn actions(&self) -> &'static [ReviewAction] {
+        &[
+            ReviewAction::Reopen,
+            ReviewAction::LeaveClosed,
+            ReviewAction::Edit,
+            ReviewAction::Skip,
+        ]
+    }
+    async fn pending(
+        &self,
+        config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            r#"
+            select p.id from posts p
+            where p.post_type_id = $1 and p.deletion_date is null and p.closed_date is not null
+                and exists(select 1 from close_votes c where c.post_id = p.id and c.reopen)
+                and not exists(select 1 from close_votes c where c.post_id = p.id
+                    and c.user_id = $2 and c.reopen)
+                and p.owner_user_id is distinct from $2 and ($3::bigint is null or p.id = $3)
+                and not exists(select 1 from reviews r
+                    where r.post_id = p.id and r.queue_id = $4 and r.user_id = $2)
+                and (select count(1) from reviews r where r.post_id = p.id and r.queue_id = $4
+                    and r.review_action_id <> $5) < $6
+            order by p.creation_date, p.id limit $7 offset $8
+            "#,
+            QUESTION_POST_TYPE,
+            filter.uid,
+            filter.pid,
+            self.id(),
+            ReviewAction::Skip.id(),
+            config.review_completions,
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids.into_iter().map(|r| r.id).collect())
+    }
+}
+
+// Posts with a suggested edit from someone else, the suggestion decides when it leaves
+pub struct SuggestedEdits;
+
+#[async_trait]
+impl ReviewQueue for SuggestedEdits {
+    fn id(&self) -> i16 {
+        6
+    }
+    fn key(&self) -> &'static str {
+        "suggested-edits"
+    }
+    fn privilege(&self) -> Privilege {
+        Privilege::EditWithoutReview
+    }
+    fn actions(&self) -> &'static [ReviewAction] {
+        &[
+            ReviewAction::Approve,
+            ReviewAction::Reject,
+            ReviewAction::Skip,
+        ]
+    }
+    async fn pending(
+        &self,
+        _config: &Config,
+        conn: &mut SqlConnection,
+        filter: QueueFilter,
+    ) -> sqlx::Result<Vec<i64>> {
+        let ids = sqlx::query!(
+            r#"
+            select p.id from posts p join suggested_edits s on s.post_id = p.id
+            where p.deletion_date is null and s.approval_date is null and s.rejection_date is null
+                and s.owner_user_id <> $1 and ($2::bigint is null or p.id = $2)
+                and not exists(select 1 from suggested_edit_votes v
+                    where v.suggested_edit_id = s.id and v.user_id = $1)
+                and not exists(select 1 from reviews r where r.post_id = p.id and r.queue_id = $3
+                    and r.user_id = $1 and r.creation_date >= s.creation_date)
+            order by s.creation_date, s.id limit $4 offset $5
+            "#,
+            filter.uid,
+            filter.pid,
+            self.id(),
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids.into_iter().map(|r| r.id).collect())
+    }
+    async fn apply(
+        &self,
+        config: &Config,
+        tx: &mut SqlTx<'_>,
+        pid: i64,
+        who: Reviewer<'_>,
+        form: &ReviewTaskReq,
+    ) -> sqlx::Result<ReviewOutcome<()>> {
+        let vote = match form.action {
+            ReviewAction::Approve => ReviewVote::Approve,
+            ReviewAction::Reject => ReviewVote::Reject,
+            _ => return apply_review(config, tx, pid, who, form).await,
+        };
+        let sid = sqlx::query!(
+            r#"
+            select id from suggested_edits
+            where post_id = $1 and approval_date is null and rejection_date is null
+            "#,
+            pid
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let sid = match sid {
+            Some(s) => s.id,
+            None => return Ok(ReviewOutcome::NotFound),
+        };
+        let outcome =
+            review_suggested_edit_tx(tx, config, sid, who.uid, who.editor, who.moderator, vote)
+                .await?;
+
+        Ok(match outcome {
+            SuggestionOutcome::Done(_) => ReviewOutcome::Done(()),
+            SuggestionOutcome::NotFound => ReviewOutcome::NotFound,
+            SuggestionOutcome::Unprivileged => {
+                ReviewOutcome::Unprivileged(Privilege::EditWithoutReview)
+            }
+            SuggestionOutcome::Invalid(msg) => ReviewOutcome::Invalid(msg),
+            _ => ReviewOutcome::Refused("You cannot review this suggested edit".to_owned()),
+        })
+    }
+}
+
+// Edits, deletes, closes or reopens the post through the same paths as outside of
+// the queues, the other actions leave the post as it is
+pub async fn apply_review(
+    config: &Config,
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    who: Reviewer<'_>,
+    form: &ReviewTaskReq,
+) -> sqlx::Result<ReviewOutcome<()>> {
+    match form.action {
+        ReviewAction::Edit => {
+            let edit = match &form.edit {
+                Some(edit) => edit,
+                None => return Ok(ReviewOutcome::Invalid("The edit is missing".to_owned())),
+            };
+            if !who.editor {
+                return Ok(ReviewOutcome::Unprivileged(Privilege::EditWithoutReview));
+            }
+            let post_type_id = sqlx::query!(
+                r#"
+                select post_type_id from posts where id = $1
+                "#,
+                pid
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .and_then(|p| p.post_type_id);
+
+            let post_type_id = match post_type_id {
+                Some(t) => t,
+                None => return Ok(ReviewOutcome::NotFound),
+            };
+            if let Err(e) = edit.validate_for(post_type_id) {
+                return Ok(ReviewOutcome::Invalid(e.to_string()));
+            }
+            let edited = apply_edit(tx, pid, who.uid, who.username, &edit.snapshot(), &[]).await?;
+            if edited.is_none() {
+                return Ok(ReviewOutcome::NotFound);
+            }
+
+            Ok(ReviewOutcome::Done(()))
+        }
+        ReviewAction::Delete => {
+            let deleter = Deleter {
+                uid: who.uid,
+                username: who.username,
+                moderator: who.moderator,
+                trusted: who.trusted,
+            };
+            Ok(match delete_post_tx(tx, config, pid, deleter).await? {
+                DeleteOutcome::Voted(_) => ReviewOutcome::Done(()),
+                DeleteOutcome::NotFound => ReviewOutcome::NotFound,
+                DeleteOutcome::Unprivileged => {
+                    ReviewOutcome::Unprivileged(Privilege::ModerationTools)
+                }
+                DeleteOutcome::AlreadyVoted => {
+                    ReviewOutcome::Refused("You have already voted to delete this post".to_owned())
+                }
+                _ => ReviewOutcome::Refused("This post cannot be deleted by vote".to_owned()),
+            })
+        }
+        ReviewAction::Close => {
+            let close = match &form.close {
+                Some(close) => close,
+                None => {
+                    return Ok(ReviewOutcome::Invalid(
+                        "The close reason is missing".to_owned(),
+                    ))
+                }
+            };
+            let outcome =
+                close_vote_tx(tx, config, pid, who.uid, who.username, who.moderator, close).await?;
+            Ok(close_outcome(outcome))
+        }
+        ReviewAction::Reopen => {
+            let outcome =
+                reopen_vote_tx(tx, config, pid, who.uid, who.username, who.moderator).await?;
+            Ok(close_outcome(outcome))
+        }
+        ReviewAction::Approve | ReviewAction::Reject => Ok(ReviewOutcome::InvalidAction),
+        _ => Ok(ReviewOutcome::Done(())),
+    }
+}
+
+fn close_outcome(outcome: CloseOutcome) -> ReviewOutcome<()> {
+    match outcome {
+        CloseOutcome::Voted(_) => ReviewOutcome::Done(()),
+        CloseOutcome::NotFound => ReviewOutcome::NotFound,
+        CloseOutcome::BadReason => ReviewOutcome::Invalid("Unknown close reason".to_owned()),
+        CloseOutcome::BadDuplicate => ReviewOutcome::Invalid(
+            "A duplicate needs another existing question as the original".to_owned(),
+        ),
+        CloseOutcome::Unchanged => {
+            ReviewOutcome::Refused("The question is already in that state".to_owned())
+        }
+        CloseOutcome::AlreadyVoted => {
+            ReviewOutcome::Refused("You have already voted on this question".to_owned())
+        }
+    }
+}
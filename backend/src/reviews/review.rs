@@ -0,0 +1,148 @@
+use crate::closing::close::CloseReq;
+use crate::middlewares::privilege::Privilege;
+use crate::suggestions::suggestion::ImproveEditReq;
+use chrono::NaiveDateTime;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReviewAction {
+    LooksOk,
+    // passes the item without counting towards the daily limit
+    Skip,
+    Edit,
+    Delete,
+    Close,
+    LeaveOpen,
+    Reopen,
+    LeaveClosed,
+    Approve,
+    Reject,
+}
+
+impl ReviewAction {
+    // stored in reviews.review_action_id
+    pub fn id(&self) -> i16 {
+        match self {
+            ReviewAction::LooksOk => 1,
+            ReviewAction::Skip => 2,
+            ReviewAction::Edit => 3,
+            ReviewAction::Delete => 4,
+            ReviewAction::Close => 5,
+            ReviewAction::LeaveOpen => 6,
+            ReviewAction::Reopen => 7,
+            ReviewAction::LeaveClosed => 8,
+            ReviewAction::Approve => 9,
+            ReviewAction::Reject => 10,
+        }
+    }
+}
+
+// Who is reviewing, with the privileges review actions are checked against
+#[derive(Debug, Clone, Copy)]
+pub struct Reviewer<'a> {
+    pub uid: i64,
+    pub username: &'a str,
+    pub moderator: bool,
+    // may edit posts of others
+    pub editor: bool,
+    // may vote to delete posts of others
+    pub trusted: bool,
+}
+
+// Selects the posts of a queue a reviewer has not reviewed yet
+#[derive(Debug, Clone, Copy)]
+pub struct QueueFilter {
+    pub uid: i64,
+    // only this post, to check it is in the queue
+    pub pid: Option<i64>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewItemsReq {
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewTaskReq {
+    pub action: ReviewAction,
+    // the new version of the post for the edit action
+    #[serde(default)]
+    pub edit: Option<ImproveEditReq>,
+    // the reason for the close action
+    #[serde(default)]
+    pub close: Option<CloseReq>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QueueResponse {
+    pub key: String,
+    pub privilege: Privilege,
+    pub actions: Vec<ReviewAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QueuesResponse {
+    pub queues: Vec<QueueResponse>,
+    pub reviews_today: i64,
+    pub daily_limit: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewItemResponse {
+    pub post_id: String,
+    pub post_type_id: i16,
+    pub question_id: String,
+    // title and tags of the question for answers
+    pub title: String,
+    pub body: String,
+    pub tags: Vec<String>,
+    pub owner_user_id: Option<String>,
+    pub owner_display_name: String,
+    pub score: i64,
+    pub creation_date: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewItemsResponse {
+    pub queue: String,
+    pub actions: Vec<ReviewAction>,
+    pub items: Vec<ReviewItemResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewTaskResponse {
+    pub reviews_today: i64,
+    pub daily_limit: i64,
+}
+
+#[derive(Debug)]
+pub enum ReviewOutcome<T> {
+    Done(T),
+    // the post is not in the queue, or already reviewed by the user
+    NotFound,
+    // the queue does not offer the action
+    InvalidAction,
+    Unprivileged(Privilege),
+    LimitReached,
+    // the action is missing its edit or close reason
+    Invalid(String),
+    // the post refused the action, e.g. a second delete vote
+    Refused(String),
+}
+
+impl<T> ReviewOutcome<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> ReviewOutcome<U> {
+        match self {
+            ReviewOutcome::Done(t) => ReviewOutcome::Done(f(t)),
+            ReviewOutcome::NotFound => ReviewOutcome::NotFound,
+            ReviewOutcome::InvalidAction => ReviewOutcome::InvalidAction,
+            ReviewOutcome::Unprivileged(p) => ReviewOutcome::Unprivileged(p),
+            ReviewOutcome::LimitReached => ReviewOutcome::LimitReached,
+            ReviewOutcome::Invalid(msg) => ReviewOutcome::Invalid(msg),
+            ReviewOutcome::Refused(msg) => ReviewOutcome::Refused(msg),
+        }
+    }
+}
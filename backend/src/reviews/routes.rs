@@ -0,0 +1,122 @@
+use super::dao::IReview;
+use super::queues::find_queue;
+use super::review::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{get, post, web, Responder};
+use validator::Validate;
+
+#[get("/review")]
+async fn get_queues(auth: Privileged, state: AppState) -> impl Responder {
+    match state.get_ref().get_queues(auth.claims.id).await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/review/{queue}")]
+async fn get_review_items(
+    params: web::Path<String>,
+    form: web::Json<ReviewItemsReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let key = params.into_inner();
+
+    let queue = match find_queue(&key) {
+        Some(q) => q,
+        None => {
+            return ApiResult::new()
+                .code(404)
+                .with_msg("Review queue not found")
+        }
+    };
+    if let Err(denied) = auth.require(queue.privilege(), &state.config.privileges) {
+        return denied;
+    }
+    match state
+        .get_ref()
+        .get_review_items(queue, auth.claims.id, &form)
+        .await
+    {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            error!("review queue {} error: {:?}", key, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/review/{queue}/{id}")]
+async fn review(
+    params: web::Path<(String, i64)>,
+    form: web::Json<ReviewTaskReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let (key, pid) = params.into_inner();
+    let form = form.into_inner();
+    let privileges = &state.config.privileges;
+
+    let queue = match find_queue(&key) {
+        Some(q) => q,
+        None => {
+            return ApiResult::new()
+                .code(404)
+                .with_msg("Review queue not found")
+        }
+    };
+    if let Err(denied) = auth.require(queue.privilege(), privileges) {
+        return denied;
+    }
+    if let Some(Err(e)) = form.edit.as_ref().map(|edit| edit.validate()) {
+        debug!("review {} {} {:?} error: {:?}", key, pid, form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    let who = Reviewer {
+        uid: auth.claims.id,
+        username: &auth.claims.username,
+        moderator: auth.moderator,
+        editor: auth.has(Privilege::EditWithoutReview, privileges),
+        trusted: auth.has(Privilege::ModerationTools, privileges),
+    };
+    match state.get_ref().review(queue, pid, who, &form).await {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("review {} {} error: {:?}", key, pid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result(
+    outcome: ReviewOutcome<ReviewTaskResponse>,
+    state: &AppState,
+) -> ApiResult<ReviewTaskResponse> {
+    match outcome {
+        ReviewOutcome::Done(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        ReviewOutcome::NotFound => ApiResult::new()
+            .code(404)
+            .with_msg("This post is not waiting for your review"),
+        ReviewOutcome::InvalidAction => ApiResult::new()
+            .code(400)
+            .with_msg("This queue does not offer that action"),
+        ReviewOutcome::Unprivileged(privilege) => privilege.denied(&state.config.privileges),
+        ReviewOutcome::LimitReached => ApiResult::new()
+            .code(429)
+            .with_msg("You have reached your daily review limit"),
+        ReviewOutcome::Invalid(msg) => ApiResult::new().code(400).with_msg(msg),
+        ReviewOutcome::Refused(msg) => ApiResult::new().code(409).with_msg(msg),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_queues);
+    cfg.service(get_review_items);
+    cfg.service(review);
+}
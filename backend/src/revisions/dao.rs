@@ -0,0 +1,249 @@
+use super::revision::*;
+use crate::questions::question::QUESTION_POST_TYPE;
+use crate::state::{AppStateRaw, SqlTx};
+use crate::tags::dao::set_post_tags;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait IRevision: std::ops::Deref<Target = AppStateRaw> {
+    async fn get_revisions(
+        &self,
+        pid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<Option<RevisionsResponse>>;
+    async fn diff_revisions(
+        &self,
+        pid: i64,
+        from: usize,
+        to: usize,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<Option<DiffResponse>>;
+    async fn rollback(
+        &self,
+        pid: i64,
+        number: usize,
+        uid: i64,
+        username: &str,
+        any_post: bool,
+        any_wiki: bool,
+    ) -> sqlx::Result<RollbackOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IRevision for &AppStateRaw {
+    async fn get_revisions(
+        &self,
+        pid: i64,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<Option<RevisionsResponse>> {
+        let mut tx = self.sql.begin().await?;
+        if !visible(&mut tx, pid, viewer, moderator).await? {
+            return Ok(None);
+        }
+        let revisions = build_revisions(history(&mut tx, pid).await?);
+        tx.commit().await?;
+
+        Ok(Some(RevisionsResponse { revisions }))
+    }
+
+    async fn diff_revisions(
+        &self,
+        pid: i64,
+        from: usize,
+        to: usize,
+        viewer: Option<i64>,
+        moderator: bool,
+    ) -> sqlx::Result<Option<DiffResponse>> {
+        let mut tx = self.sql.begin().await?;
+        if !visible(&mut tx, pid, viewer, moderator).await? {
+            return Ok(None);
+        }
+        let revisions = build_revisions(history(&mut tx, pid).await?);
+        tx.commit().await?;
+
+        let snapshot = |n: usize| {
+            n.checked_sub(1)
+                .and_then(|i| revisions.get(i))
+                .map(|r| &r.snapshot)
+        };
+        let (old, new) = match (snapshot(from), snapshot(to)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(DiffResponse {
+            from,
+            to,
+            diffs: diff_snapshots(old, new),
+        }))
+    }
+
+    async fn rollback(
+        &self,
+        pid: i64,
+        number: usize,
+        uid: i64,
+        username: &str,
+        any_post: bool,
+        any_wiki: bool,
+    ) -> sqlx::Result<RollbackOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let post = sqlx::query!(
+            r#"
+            select post_type_id, owner_user_id, title, body, tags, locked_date,
+                community_owned_date is not null as "wiki!"
+            from posts
+            where id = $1 and deletion_date is null
+            for update
+            "#,
+            pid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let post = match post {
+            Some(p) => p,
+            None => return Ok(RollbackOutcome::NotFound),
+        };
+        if post.locked_date.is_some() {
+            return Ok(RollbackOutcome::Locked);
+        }
+        if post.owner_user_id != Some(uid) && !any_post && !(post.wiki && any_wiki) {
+            return Ok(RollbackOutcome::Forbidden);
+        }
+
+        let revisions = build_revisions(history(&mut tx, pid).await?);
+        let target = match number.checked_sub(1).and_then(|i| revisions.get(i)) {
+            Some(r) => r.snapshot.clone(),
+            None => return Ok(RollbackOutcome::NotFound),
+        };
+        let current = Snapshot {
+            title: post.title.unwrap_or_default(),
+            body: post.body.unwrap_or_default(),
+            tags: post.tags.unwrap_or_default(),
+        };
+
+        let changes = target.rollback_changes(&current);
+        if changes.is_empty() {
+            return Ok(RollbackOutcome::Unchanged);
+        }
+
+        sqlx::query!(
+            r#"
+            update posts set title = nullif($1, ''), body = $2, tags = nullif($3, ''),
+                last_editor_user_id = $4, last_editor_display_name = $5, last_edit_date = now(),
+                last_activity_date = now()
+            where id = $6
+            "#,
+            target.title,
+            target.body,
+            target.tags,
+            uid,
+            username,
+            pid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if post.post_type_id == Some(QUESTION_POST_TYPE) && target.tags != current.tags {
+            let tags: Vec<String> = target.tags.split_whitespace().map(String::from).collect();
+            set_post_tags(&mut tx, pid, &tags).await?;
+        }
+
+        let comment = format!("Rollback to revision {}", number);
+        record_revision(&mut tx, pid, uid, username, Some(&comment), &changes).await?;
+
+        tx.commit().await?;
+
+        Ok(RollbackOutcome::Done)
+    }
+}
+
+// deleted posts stay visible to their author and moderators
+async fn visible(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    viewer: Option<i64>,
+    moderator: bool,
+) -> sqlx::Result<bool> {
+    let post = sqlx::query!(
+        r#"
+        select id from posts
+        where id = $1 and (deletion_date is null or $2 or owner_user_id = $3)
+        "#,
+        pid,
+        moderator,
+        viewer
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    Ok(post.is_some())
+}
+
+async fn history(tx: &mut SqlTx<'_>, pid: i64) -> sqlx::Result<Vec<HistoryRow>> {
+    sqlx::query_as!(
+        HistoryRow,
+        r#"
+        select post_history_type_id, revision_guid, creation_date, user_id, user_display_name,
+            comment, text
+        from post_history where post_id = $1
+        order by creation_date asc, id asc
+        "#,
+        pid
+    )
+    .fetch_all(&mut *tx)
+    .await
+}
+
+// Writes one post_history row per change, all grouped under a new revision GUID
+pub async fn record_revision(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    uid: i64,
+    username: &str,
+    comment: Option<&str>,
+    changes: &[Change<'_>],
+) -> sqlx::Result<Uuid> {
+    let guid = Uuid::new_v4();
+    record_changes(tx, guid, pid, uid, username, comment, changes).await?;
+
+    Ok(guid)
+}
+
+// Writes `changes` under an existing revision, for actions spanning several posts
+pub async fn record_changes(
+    tx: &mut SqlTx<'_>,
+    guid: Uuid,
+    pid: i64,
+    uid: i64,
+    username: &str,
+    comment: Option<&str>,
+    changes: &[Change<'_>],
+) -> sqlx::Result<()> {
+    for change in changes {
+        sqlx::query!(
+            r#"
+            insert into post_history (post_history_type_id, post_id, revision_guid, user_id,
+                user_display_name, comment, text)
+            values ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            change.post_history_type_id,
+            pid,
+            guid,
+            uid,
+            username,
+            comment,
+            change.text
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
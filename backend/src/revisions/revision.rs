@@ -0,0 +1,325 @@
+use chrono::NaiveDateTime;
+use similar::{ChangeTag, TextDiff};
+use uuid::Uuid;
+
+// post_history_type_id values for the content of a post
+pub const INITIAL_TITLE: i64 = 1;
+pub const INITIAL_BODY: i64 = 2;
+pub const INITIAL_TAGS: i64 = 3;
+pub const EDIT_TITLE: i64 = 4;
+pub const EDIT_BODY: i64 = 5;
+pub const EDIT_TAGS: i64 = 6;
+pub const ROLLBACK_TITLE: i64 = 7;
+pub const ROLLBACK_BODY: i64 = 8;
+pub const ROLLBACK_TAGS: i64 = 9;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Title,
+    Body,
+    Tags,
+}
+
+impl Field {
+    pub fn from_history_type(post_history_type_id: i64) -> Option<Self> {
+        match post_history_type_id {
+            INITIAL_TITLE | EDIT_TITLE | ROLLBACK_TITLE => Some(Field::Title),
+            INITIAL_BODY | EDIT_BODY | ROLLBACK_BODY => Some(Field::Body),
+            INITIAL_TAGS | EDIT_TAGS | ROLLBACK_TAGS => Some(Field::Tags),
+            _ => None,
+        }
+    }
+    pub fn initial(&self) -> i64 {
+        match self {
+            Field::Title => INITIAL_TITLE,
+            Field::Body => INITIAL_BODY,
+            Field::Tags => INITIAL_TAGS,
+        }
+    }
+    pub fn edit(&self) -> i64 {
+        match self {
+            Field::Title => EDIT_TITLE,
+            Field::Body => EDIT_BODY,
+            Field::Tags => EDIT_TAGS,
+        }
+    }
+    pub fn rollback(&self) -> i64 {
+        match self {
+            Field::Title => ROLLBACK_TITLE,
+            Field::Body => ROLLBACK_BODY,
+            Field::Tags => ROLLBACK_TAGS,
+        }
+    }
+}
+
+// One field value written to post_history as part of a revision
+#[derive(Debug)]
+pub struct Change<'a> {
+    pub post_history_type_id: i64,
+    pub text: &'a str,
+}
+
+#[derive(FromRow, Debug)]
+pub struct HistoryRow {
+    pub post_history_type_id: Option<i64>,
+    pub revision_guid: Option<Uuid>,
+    pub creation_date: Option<NaiveDateTime>,
+    pub user_id: Option<i64>,
+    pub user_display_name: Option<String>,
+    pub comment: Option<String>,
+    pub text: Option<String>,
+}
+
+// Content of a post as of one revision
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub title: String,
+    pub body: String,
+    pub tags: String,
+}
+
+impl Snapshot {
+    pub fn get(&self, field: Field) -> &str {
+        match field {
+            Field::Title => &self.title,
+            Field::Body => &self.body,
+            Field::Tags => &self.tags,
+        }
+    }
+    // history rows for a new post, answers have no title or tags
+    pub fn initial_changes(&self) -> Vec<Change<'_>> {
+        self.changes(|f| !self.get(f).is_empty(), Field::initial)
+    }
+    // history rows for the fields that differ from `old`
+    pub fn edit_changes(&self, old: &Snapshot) -> Vec<Change<'_>> {
+        self.changes(|f| self.get(f) != old.get(f), Field::edit)
+    }
+    pub fn rollback_changes(&self, current: &Snapshot) -> Vec<Change<'_>> {
+        self.changes(|f| self.get(f) != current.get(f), Field::rollback)
+    }
+    fn changes<P, T>(&self, pred: P, type_id: T) -> Vec<Change<'_>>
+    where
+        P: Fn(Field) -> bool,
+        T: Fn(&Field) -> i64,
+    {
+        [Field::Title, Field::Body, Field::Tags]
+            .into_iter()
+            .filter(|&f| pred(f))
+            .map(|f| Change {
+                post_history_type_id: type_id(&f),
+                text: self.get(f),
+            })
+            .collect()
+    }
+    fn set(&mut self, field: Field, text: String) {
+        match field {
+            Field::Title => self.title = text,
+            Field::Body => self.body = text,
+            Field::Tags => self.tags = text,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Revision {
+    // revisions are numbered from 1 in chronological order
+    pub number: usize,
+    pub revision_guid: String,
+    pub creation_date: Option<NaiveDateTime>,
+    pub user_id: String,
+    pub user_display_name: String,
+    pub comment: Option<String>,
+    pub fields: Vec<Field>,
+    pub rollback: bool,
+    #[serde(skip)]
+    pub snapshot: Snapshot,
+}
+
+// Groups history rows sharing a revision_guid into revisions, carrying
+// unchanged fields forward so that every revision has a full snapshot.
+pub fn build_revisions(rows: Vec<HistoryRow>) -> Vec<Revision> {
+    let mut revisions: Vec<Revision> = Vec::new();
+    let mut snapshot = Snapshot::default();
+
+    for row in rows {
+        let type_id = row.post_history_type_id.unwrap_or_default();
+        let field = match Field::from_history_type(type_id) {
+            Some(f) => f,
+            None => continue,
+        };
+        let guid = row.revision_guid.map(|g| g.to_string()).unwrap_or_default();
+
+        if revisions.last().map(|r| &r.revision_guid) != Some(&guid) {
+            revisions.push(Revision {
+                number: revisions.len() + 1,
+                revision_guid: guid,
+                creation_date: row.creation_date,
+                user_id: row.user_id.unwrap_or_default().to_string(),
+                user_display_name: row.user_display_name.unwrap_or_default(),
+                comment: row.comment,
+                fields: Vec::new(),
+                rollback: false,
+                snapshot: snapshot.clone(),
+            });
+        }
+
+        snapshot.set(field, row.text.unwrap_or_default());
+        let revision = revisions.last_mut().expect("revision pushed above");
+        revision.fields.push(field);
+        revision.rollback |= type_id == field.rollback();
+        revision.snapshot = snapshot.clone();
+    }
+
+    revisions
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevisionsResponse {
+    pub revisions: Vec<Revision>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DiffOp {
+    // equal, insert or delete
+    pub op: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FieldDiff {
+    pub field: Field,
+    pub ops: Vec<DiffOp>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DiffResponse {
+    pub from: usize,
+    pub to: usize,
+    pub diffs: Vec<FieldDiff>,
+}
+
+// Title and tags are compared word by word, the body line by line
+pub fn diff_snapshots(from: &Snapshot, to: &Snapshot) -> Vec<FieldDiff> {
+    [Field::Title, Field::Body, Field::Tags]
+        .into_iter()
+        .filter(|&f| from.get(f) != to.get(f))
+        .map(|field| {
+            let (old, new) = (from.get(field), to.get(field));
+            let diff = match field {
+                Field::Body => TextDiff::from_lines(old, new),
+                _ => TextDiff::from_words(old, new),
+            };
+            let ops = diff
+                .iter_all_changes()
+                .map(|c| DiffOp {
+                    op: match c.tag() {
+                        ChangeTag::Equal => "equal",
+                        ChangeTag::Insert => "insert",
+                        ChangeTag::Delete => "delete",
+                    }
+                    .to_owned(),
+                    value: c.value().to_owned(),
+                })
+                .collect();
+            FieldDiff { field, ops }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum RollbackOutcome {
+    Done,
+    NotFound,
+    Forbidden,
+    // the post already has the content of the revision
+    Unchanged,
+    Locked,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(title: &str, body: &str, tags: &str) -> Snapshot {
+        Snapshot {
+            title: title.to_owned(),
+            body: body.to_owned(),
+            tags: tags.to_owned(),
+        }
+    }
+
+    fn row(type_id: i64, guid: Uuid, text: &str) -> HistoryRow {
+        HistoryRow {
+            post_history_type_id: Some(type_id),
+            revision_guid: Some(guid),
+            creation_date: None,
+            user_id: Some(1),
+            user_display_name: Some("alice".to_owned()),
+            comment: None,
+            text: Some(text.to_owned()),
+        }
+    }
+
+    #[test]
+    fn unchanged_snapshot_has_no_diff() {
+        let s = snapshot("How do I sort?", "Like this\n", "<rust>");
+        assert!(diff_snapshots(&s, &s).is_empty());
+        assert!(s.edit_changes(&s).is_empty());
+    }
+
+    #[test]
+    fn changed_body_diffs_by_line() {
+        let from = snapshot("How do I sort?", "one\ntwo\n", "<rust>");
+        let to = snapshot("How do I sort?", "one\nthree\n", "<rust>");
+
+        let diffs = diff_snapshots(&from, &to);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, Field::Body);
+        let ops: Vec<_> = diffs[0]
+            .ops
+            .iter()
+            .map(|o| (o.op.as_str(), o.value.as_str()))
+            .collect();
+        assert_eq!(
+            ops,
+            [
+                ("equal", "one\n"),
+                ("delete", "two\n"),
+                ("insert", "three\n")
+            ]
+        );
+
+        let changes = to.edit_changes(&from);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].post_history_type_id, EDIT_BODY);
+        assert_eq!(changes[0].text, "one\nthree\n");
+    }
+
+    #[test]
+    fn revisions_carry_unchanged_fields_forward() {
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let rows = vec![
+            row(INITIAL_TITLE, first, "Title"),
+            row(INITIAL_BODY, first, "Body"),
+            row(INITIAL_TAGS, first, "<rust>"),
+            row(EDIT_BODY, second, "Better body"),
+            // not a content row
+            row(10, second, "closed"),
+            row(ROLLBACK_BODY, third, "Body"),
+        ];
+
+        let revisions = build_revisions(rows);
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(revisions[0].snapshot, snapshot("Title", "Body", "<rust>"));
+        assert_eq!(revisions[1].number, 2);
+        assert_eq!(revisions[1].fields, [Field::Body]);
+        assert!(!revisions[1].rollback);
+        assert_eq!(
+            revisions[1].snapshot,
+            snapshot("Title", "Better body", "<rust>")
+        );
+        assert!(revisions[2].rollback);
+        assert_eq!(revisions[2].snapshot, revisions[0].snapshot);
+    }
+}
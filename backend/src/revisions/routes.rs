@@ -0,0 +1,100 @@
+use super::dao::IRevision;
+use super::revision::*;
+use crate::api::ApiResult;
+use crate::middlewares::auth::AuthorizationService;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+use crate::users::dao::IUser;
+
+use actix_web::{get, post, web, Responder};
+
+#[get("/post/{id}/revisions")]
+async fn get_revisions(
+    params: web::Path<i64>,
+    auth: Option<AuthorizationService>,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+    let viewer = auth.map(|a| a.claims.id);
+    let moderator = match viewer {
+        Some(uid) => state.get_ref().is_moderator(uid).await.unwrap_or(false),
+        None => false,
+    };
+    match state.get_ref().get_revisions(pid, viewer, moderator).await {
+        Ok(Some(rrs)) => ApiResult::new().code(200).with_msg("").with_data(rrs),
+        Ok(None) => ApiResult::new().code(404).with_msg("Post not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+#[get("/post/{id}/revisions/{from}/{to}")]
+async fn diff_revisions(
+    params: web::Path<(i64, usize, usize)>,
+    auth: Option<AuthorizationService>,
+    state: AppState,
+) -> impl Responder {
+    let (pid, from, to) = params.into_inner();
+    let viewer = auth.map(|a| a.claims.id);
+    let moderator = match viewer {
+        Some(uid) => state.get_ref().is_moderator(uid).await.unwrap_or(false),
+        None => false,
+    };
+    match state
+        .get_ref()
+        .diff_revisions(pid, from, to, viewer, moderator)
+        .await
+    {
+        Ok(Some(dr)) => ApiResult::new().code(200).with_msg("").with_data(dr),
+        Ok(None) => ApiResult::new().code(404).with_msg("Revision not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+#[post("/post/{id}/rollback/{revision}")]
+async fn rollback(
+    params: web::Path<(i64, usize)>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let (pid, number) = params.into_inner();
+    let privileges = &state.config.privileges;
+    let any_post = auth.has(Privilege::EditWithoutReview, privileges);
+    let any_wiki = auth.has(Privilege::EditCommunityWiki, privileges);
+    match state
+        .get_ref()
+        .rollback(
+            pid,
+            number,
+            auth.claims.id,
+            &auth.claims.username,
+            any_post,
+            any_wiki,
+        )
+        .await
+    {
+        Ok(RollbackOutcome::Done) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Ok(RollbackOutcome::Unchanged) => ApiResult::new()
+            .code(200)
+            .with_msg("Post already matches this revision")
+            .with_data(false),
+        Ok(RollbackOutcome::NotFound) => ApiResult::new().code(404).with_msg("Revision not found"),
+        Ok(RollbackOutcome::Forbidden) => Privilege::EditWithoutReview.denied(privileges),
+        Ok(RollbackOutcome::Locked) => ApiResult::new().code(403).with_msg("This post is locked"),
+        Err(e) => {
+            error!("rollback {} to {} error: {:?}", pid, number, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_revisions);
+    cfg.service(diff_revisions);
+    cfg.service(rollback);
+}
@@ -0,0 +1,249 @@
+use super::session::*;
+use crate::how::Result;
+use crate::state::{redis, AppStateRaw};
+
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait ISession: std::ops::Deref<Target = AppStateRaw> {
+    async fn create_session(&self, uid: i64, device: &Device, remember: bool) -> Result<Issued>;
+    // swaps a refresh token for a new one
+    async fn refresh_session(&self, refresh_token: &str) -> Result<RefreshOutcome>;
+    // whether the session is live and belongs to the user, marks it as seen
+    async fn touch_session(&self, uid: i64, sid: &str) -> Result<bool>;
+    async fn get_sessions(&self, uid: i64, current: &str) -> Result<SessionsResponse>;
+    async fn end_session(&self, uid: i64, sid: &str) -> Result<bool>;
+    async fn end_sessions(&self, uid: i64) -> Result<u64>;
+}
+
+#[async_trait]
+impl ISession for &AppStateRaw {
+    // remember me only lengthens the life of the session and its refresh token
+    async fn create_session(&self, uid: i64, device: &Device, remember: bool) -> Result<Issued> {
+        let sid = Uuid::new_v4().simple().to_string();
+        let secret = Uuid::new_v4().simple().to_string();
+        let xsrf_token = Uuid::new_v4().to_string();
+        let lifetime = if remember {
+            self.config.remember_me_lifetime
+        } else {
+            self.config.session_lifetime
+        };
+        let now = Utc::now().timestamp();
+        let mut conn = self.kv.get().await?;
+
+        redis::pipe()
+            .atomic()
+            .hset_multiple(
+                session_key(&sid),
+                &[
+                    ("uid", uid.to_string()),
+                    ("device", device.agent.clone()),
+                    ("ip", device.ip.clone()),
+                    ("created", now.to_string()),
+                    ("seen", now.to_string()),
+                    ("xsrf", xsrf_token.clone()),
+                    ("refresh", refresh_digest(&secret)),
+                    ("gen", "0".to_owned()),
+                    ("lifetime", lifetime.to_string()),
+                    ("remember", (remember as i32).to_string()),
+                ],
+            )
+            .ignore()
+            .expire(session_key(&sid), lifetime.max(1) as usize)
+            .ignore()
+            .sadd(user_sessions_key(uid), &sid)
+            .ignore()
+            .expire(user_sessions_key(uid), user_sessions_ttl(&self.config))
+            .ignore()
+            .query_async::<_, ()>(&mut *conn)
+            .await?;
+
+        Ok(Issued {
+            uid,
+            refresh_token: refresh_token_of(&sid, 0, &secret),
+            sid,
+            xsrf_token,
+            remember: remember.then_some(lifetime),
+        })
+    }
+
+    // each refresh renews the session for its lifetime and moves it to the next
+    // generation, a token of an earlier generation is a sign of theft and ends the session
+    async fn refresh_session(&self, refresh_token: &str) -> Result<RefreshOutcome> {
+        let (sid, generation, secret) = match parse_refresh_token(refresh_token) {
+            Some(parts) => parts,
+            None => return Ok(RefreshOutcome::Invalid),
+        };
+        let presented = refresh_digest(secret);
+        let mut conn = self.kv.get().await?;
+
+        // the token is rotated only if no other refresh of the session got there first
+        loop {
+            redis::cmd("WATCH")
+                .arg(session_key(sid))
+                .query_async::<_, ()>(&mut *conn)
+                .await?;
+            let s: HashMap<String, String> = conn.hgetall(session_key(sid)).await?;
+            let field = |name: &str| s.get(name).and_then(|v| v.parse::<i64>().ok());
+
+            let (uid, current) = match (field("uid"), field("gen")) {
+                (Some(uid), Some(current)) if generation == current => (uid, current),
+                (Some(uid), Some(current)) if generation < current => {
+                    redis::cmd("UNWATCH")
+                        .query_async::<_, ()>(&mut *conn)
+                        .await?;
+                    warn!("refresh token of session {} of {} reused", sid, uid);
+                    self.end_session(uid, sid).await?;
+                    return Ok(RefreshOutcome::Reused);
+                }
+                _ => {
+                    redis::cmd("UNWATCH")
+                        .query_async::<_, ()>(&mut *conn)
+                        .await?;
+                    return Ok(RefreshOutcome::Invalid);
+                }
+            };
+            if s.get("refresh") != Some(&presented) {
+                redis::cmd("UNWATCH")
+                    .query_async::<_, ()>(&mut *conn)
+                    .await?;
+                return Ok(RefreshOutcome::Invalid);
+            }
+
+            let lifetime = field("lifetime").unwrap_or(self.config.session_lifetime);
+            let remember = field("remember") == Some(1);
+            let secret = Uuid::new_v4().simple().to_string();
+            let rotated: Option<()> = redis::pipe()
+                .atomic()
+                .hset_multiple(
+                    session_key(sid),
+                    &[
+                        ("refresh", refresh_digest(&secret)),
+                        ("gen", (current + 1).to_string()),
+                        ("seen", Utc::now().timestamp().to_string()),
+                    ],
+                )
+                .ignore()
+                .expire(session_key(sid), lifetime.max(1) as usize)
+                .ignore()
+                .expire(user_sessions_key(uid), user_sessions_ttl(&self.config))
+                .ignore()
+                .query_async(&mut *conn)
+                .await?;
+            if rotated.is_none() {
+                continue;
+            }
+
+            return Ok(RefreshOutcome::Done(Issued {
+                uid,
+                sid: sid.to_owned(),
+                xsrf_token: s.get("xsrf").cloned().unwrap_or_default(),
+                refresh_token: refresh_token_of(sid, current + 1, &secret),
+                remember: remember.then_some(lifetime),
+            }));
+        }
+    }
+
+    async fn touch_session(&self, uid: i64, sid: &str) -> Result<bool> {
+        if sid.is_empty() {
+            return Ok(false);
+        }
+        let mut conn = self.kv.get().await?;
+
+        // the session may end or expire between the check and the write, which
+        // must not bring it back without a TTL
+        loop {
+            redis::cmd("WATCH")
+                .arg(session_key(sid))
+                .query_async::<_, ()>(&mut *conn)
+                .await?;
+            let owner: Option<i64> = conn.hget(session_key(sid), "uid").await?;
+            if owner != Some(uid) {
+                redis::cmd("UNWATCH")
+                    .query_async::<_, ()>(&mut *conn)
+                    .await?;
+                return Ok(false);
+            }
+            let seen: Option<()> = redis::pipe()
+                .atomic()
+                .hset(session_key(sid), "seen", Utc::now().timestamp())
+                .ignore()
+                .query_async(&mut *conn)
+                .await?;
+            if seen.is_some() {
+                return Ok(true);
+            }
+        }
+    }
+
+    // drops the ids of sessions that have expired on the way
+    async fn get_sessions(&self, uid: i64, current: &str) -> Result<SessionsResponse> {
+        let mut conn = self.kv.get().await?;
+        let sids: Vec<String> = conn.smembers(user_sessions_key(uid)).await?;
+
+        let mut sessions = Vec::new();
+        for sid in sids {
+            let s: HashMap<String, String> = conn.hgetall(session_key(&sid)).await?;
+            if s.is_empty() {
+                conn.srem::<_, _, ()>(user_sessions_key(uid), &sid).await?;
+                continue;
+            }
+            let date = |field: &str| {
+                s.get(field)
+                    .and_then(|t| t.parse::<i64>().ok())
+                    .and_then(|t| DateTime::from_timestamp(t, 0))
+                    .map(|d| d.naive_utc())
+            };
+            sessions.push(SessionResponse {
+                device: s.get("device").cloned().unwrap_or_default(),
+                ip: s.get("ip").cloned().unwrap_or_default(),
+                creation_date: date("created"),
+                last_seen_date: date("seen"),
+                current: sid == current,
+                id: sid,
+            });
+        }
+        // most recently used first
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.last_seen_date));
+
+        Ok(SessionsResponse { sessions })
+    }
+
+    async fn end_session(&self, uid: i64, sid: &str) -> Result<bool> {
+        let mut conn = self.kv.get().await?;
+
+        let owner: Option<i64> = conn.hget(session_key(sid), "uid").await?;
+        if owner != Some(uid) {
+            return Ok(false);
+        }
+        redis::pipe()
+            .atomic()
+            .del(session_key(sid))
+            .ignore()
+            .srem(user_sessions_key(uid), sid)
+            .ignore()
+            .query_async::<_, ()>(&mut *conn)
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn end_sessions(&self, uid: i64) -> Result<u64> {
+        let mut conn = self.kv.get().await?;
+        let sids: Vec<String> = conn.smembers(user_sessions_key(uid)).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for sid in &sids {
+            pipe.del(session_key(sid));
+        }
+        pipe.del(user_sessions_key(uid));
+        let ended: Vec<u64> = pipe.query_async(&mut *conn).await?;
+
+        // the last reply is the set itself
+        Ok(ended.iter().rev().skip(1).sum())
+    }
+}
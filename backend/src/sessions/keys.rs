@@ -0,0 +1,269 @@
+use crate::config::{Config, JwtKey};
+use crate::how::AnyResult;
+use crate::users::user::Claims;
+
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use jsonwebtoken::errors::{ErrorKind, Result as JwtResult};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use simple_asn1::{from_der, ASN1Block};
+use std::fs::read;
+use std::path::Path;
+
+// A key access tokens are checked with, kid is None for jwt_priv
+#[derive(Clone)]
+struct VerifyingKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+// The signing keys of Config::jwt_keys
+#[derive(Clone)]
+pub struct KeySet {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    signing: EncodingKey,
+    verifying: Vec<VerifyingKey>,
+    // public keys other services verify tokens with, HS256 secrets are left out
+    jwks: JwkSet,
+}
+
+impl KeySet {
+    pub fn load(config: &Config) -> AnyResult<Self> {
+        let mut signing = None;
+        let mut verifying = vec![];
+        let mut keys = vec![];
+
+        for key in &config.jwt_keys {
+            let loaded = load_key(key).with_context(|| format!("jwt key {}", key.kid))?;
+            if !key.retired && signing.is_none() {
+                let encoding = loaded.signing.ok_or_else(|| {
+                    anyhow!("jwt key {} signs tokens but has no private key", key.kid)
+                })?;
+                signing = Some((Some(key.kid.clone()), loaded.algorithm, encoding));
+            }
+            verifying.push(VerifyingKey {
+                kid: Some(key.kid.clone()),
+                algorithm: loaded.algorithm,
+                key: loaded.verifying,
+            });
+            keys.extend(loaded.jwk);
+        }
+        // tokens without a kid are signed with jwt_priv
+        if !config.jwt_priv.is_empty() {
+            let secret = config.jwt_priv.as_bytes();
+            if signing.is_none() {
+                signing = Some((None, Algorithm::HS256, EncodingKey::from_secret(secret)));
+            }
+            verifying.push(VerifyingKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret),
+            });
+        }
+
+        let (kid, algorithm, signing) =
+            signing.ok_or_else(|| anyhow!("no jwt key to sign tokens with"))?;
+        Ok(Self {
+            kid,
+            algorithm,
+            signing,
+            verifying,
+            jwks: JwkSet { keys },
+        })
+    }
+    pub fn sign(&self, claims: &Claims) -> JwtResult<String> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.kid.clone();
+        encode(&header, claims, &self.signing)
+    }
+    // checks a token with the key named by its kid, retired keys included
+    pub fn verify(&self, token: &str) -> JwtResult<Claims> {
+        let header = decode_header(token)?;
+        let key = self
+            .verifying
+            .iter()
+            .find(|k| k.kid == header.kid)
+            .ok_or(ErrorKind::InvalidToken)?;
+        decode::<Claims>(token, &key.key, &Validation::new(key.algorithm)).map(|t| t.claims)
+    }
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+struct LoadedKey {
+    algorithm: Algorithm,
+    // None for retired keys
+    signing: Option<EncodingKey>,
+    verifying: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+fn load_key(key: &JwtKey) -> AnyResult<LoadedKey> {
+    let algorithm = match key.algorithm.as_str() {
+        "HS256" => Algorithm::HS256,
+        "RS256" => Algorithm::RS256,
+        "EdDSA" => Algorithm::EdDSA,
+        other => bail!("unsupported algorithm {}", other),
+    };
+    if algorithm == Algorithm::HS256 {
+        if key.secret.is_empty() {
+            bail!("HS256 needs a secret");
+        }
+        let secret = key.secret.as_bytes();
+        return Ok(LoadedKey {
+            algorithm,
+            signing: Some(EncodingKey::from_secret(secret)),
+            verifying: DecodingKey::from_secret(secret),
+            jwk: None,
+        });
+    }
+
+    let public_path = key
+        .public_key
+        .as_ref()
+        .ok_or_else(|| anyhow!("no public_key"))?;
+    let public = read_pem(public_path)?;
+    let private = match &key.private_key {
+        Some(path) if !key.retired => Some(read_pem(path)?),
+        _ => None,
+    };
+    let (signing, verifying, params) = if algorithm == Algorithm::RS256 {
+        let signing = private
+            .as_deref()
+            .map(EncodingKey::from_rsa_pem)
+            .transpose()?;
+        let (n, e) = rsa_components(&public)?;
+        let params = AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(n),
+            e: URL_SAFE_NO_PAD.encode(e),
+        });
+        (signing, DecodingKey::from_rsa_pem(&public)?, params)
+    } else {
+        let signing = private
+            .as_deref()
+            .map(EncodingKey::from_ed_pem)
+            .transpose()?;
+        let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(spki_key(&pem_der(&public)?)?),
+        });
+        (signing, DecodingKey::from_ed_pem(&public)?, params)
+    };
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            algorithm: Some(algorithm),
+            key_id: Some(key.kid.clone()),
+            ..CommonParameters::default()
+        },
+        algorithm: params,
+    };
+    Ok(LoadedKey {
+        algorithm,
+        signing,
+        verifying,
+        jwk: Some(jwk),
+    })
+}
+
+fn read_pem(path: &Path) -> AnyResult<Vec<u8>> {
+    read(path).with_context(|| format!("read {}", path.display()))
+}
+
+// the DER body of a PEM file
+fn pem_der(pem: &[u8]) -> AnyResult<Vec<u8>> {
+    let text = std::str::from_utf8(pem)?;
+    let body: String = text
+        .lines()
+        .skip_while(|l| !l.starts_with("-----BEGIN"))
+        .skip(1)
+        .take_while(|l| !l.starts_with("-----END"))
+        .collect();
+    Ok(STANDARD.decode(body.trim())?)
+}
+
+// the key bits of a SubjectPublicKeyInfo, or the DER itself for a PKCS#1 RSA key
+fn spki_key(der: &[u8]) -> AnyResult<Vec<u8>> {
+    match from_der(der)?.first() {
+        Some(ASN1Block::Sequence(_, items)) => match items.as_slice() {
+            [ASN1Block::Sequence(..), ASN1Block::BitString(_, _, bits)] => Ok(bits.clone()),
+            [ASN1Block::Integer(..), ASN1Block::Integer(..)] => Ok(der.to_vec()),
+            _ => bail!("not a public key"),
+        },
+        _ => bail!("not a public key"),
+    }
+}
+
+// the big endian modulus and exponent of an RSA public key
+fn rsa_components(pem: &[u8]) -> AnyResult<(Vec<u8>, Vec<u8>)> {
+    let key = spki_key(&pem_der(pem)?)?;
+    match from_der(&key)?.first() {
+        Some(ASN1Block::Sequence(_, items)) => match items.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                Ok((n.to_bytes_be().1, e.to_bytes_be().1))
+            }
+            _ => bail!("not an RSA public key"),
+        },
+        _ => bail!("not an RSA public key"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one 512 bit RSA key as SubjectPublicKeyInfo and as PKCS#1
+    const RSA_SPKI: &[u8] = b"-----BEGIN PUBLIC KEY-----
+MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAMd/xGjSMmd6UvnEpgt8cJJjj1fAXr/W
+1IBAtmnHUlb+VmtznUTZVEhrYSRbxjheQPe3cfudOCaRrel2IQkklEkCAwEAAQ==
+-----END PUBLIC KEY-----
+";
+    const RSA_PKCS1: &[u8] = b"-----BEGIN RSA PUBLIC KEY-----
+MEgCQQDHf8Ro0jJnelL5xKYLfHCSY49XwF6/1tSAQLZpx1JW/lZrc51E2VRIa2Ek
+W8Y4XkD3t3H7nTgmka3pdiEJJJRJAgMBAAE=
+-----END RSA PUBLIC KEY-----
+";
+    const ED25519_SPKI: &[u8] = b"-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEA/ZkCzM6/28DOv+cMZlOWBPJZEgiDywfaJr2L7+ht1Dk=
+-----END PUBLIC KEY-----
+";
+
+    #[test]
+    fn rsa_components_read_spki_and_pkcs1_alike() {
+        let (n, e) = rsa_components(RSA_SPKI).unwrap();
+        assert_eq!(n.len(), 64);
+        assert_eq!(&n[..4], &[0xc7, 0x7f, 0xc4, 0x68]);
+        assert_eq!(e, vec![1, 0, 1]);
+        assert_eq!(rsa_components(RSA_PKCS1).unwrap(), (n, e));
+    }
+
+    #[test]
+    fn spki_key_unwraps_spki_and_keeps_pkcs1() {
+        let pkcs1 = pem_der(RSA_PKCS1).unwrap();
+        assert_eq!(spki_key(&pem_der(RSA_SPKI).unwrap()).unwrap(), pkcs1);
+        assert_eq!(spki_key(&pkcs1).unwrap(), pkcs1);
+
+        let x = spki_key(&pem_der(ED25519_SPKI).unwrap()).unwrap();
+        assert_eq!(x.len(), 32);
+        assert_eq!(&x[..2], &[0xfd, 0x99]);
+        assert_eq!(&x[30..], &[0xd4, 0x39]);
+    }
+
+    #[test]
+    fn rsa_components_reject_other_keys() {
+        assert!(rsa_components(ED25519_SPKI).is_err());
+        assert!(spki_key(&[0x30, 0x03, 0x02, 0x01, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod dao;
+pub mod keys;
+pub mod routes;
+pub mod session;
@@ -0,0 +1,167 @@
+use super::dao::ISession;
+use super::session::*;
+use crate::api::ApiResult;
+use crate::middlewares::auth::AuthorizationService;
+use crate::state::AppState;
+use crate::users::dao::IUser;
+use crate::users::user::AccountStatus;
+
+use actix_web::{cookie::Cookie, get, post, web, HttpRequest, HttpResponse, Responder};
+
+// drops the jwt and refresh cookies
+fn remove_cookies(resp: &mut HttpResponse) {
+    for name in ["jwt", "refresh"] {
+        let cookie = Cookie::build(name, "")
+            .domain("localhost")
+            .path("/")
+            .finish();
+        if let Err(e) = resp.add_removal_cookie(&cookie) {
+            error!("remove {} cookie error: {:?}", name, e);
+        }
+    }
+}
+
+fn logged_out(ended: u64) -> HttpResponse {
+    let mut resp = ApiResult::new()
+        .code(200)
+        .with_msg("")
+        .with_data(LogoutResponse { ended })
+        .to_resp();
+    remove_cookies(&mut resp);
+    resp
+}
+
+fn refresh_refused(code: i32, msg: String) -> HttpResponse {
+    let mut resp = ApiResult::<()>::new().code(code).with_msg(msg).to_resp();
+    remove_cookies(&mut resp);
+    resp
+}
+
+// swaps the refresh cookie for a new access token and refresh token
+#[post("/refresh")]
+async fn refresh(req: HttpRequest, state: AppState) -> impl Responder {
+    let token = match req.cookie("refresh") {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return refresh_refused(401, "Unauthorized".to_owned()),
+    };
+    let issued = match state.get_ref().refresh_session(&token).await {
+        Ok(RefreshOutcome::Done(issued)) => issued,
+        Ok(RefreshOutcome::Invalid) => {
+            return refresh_refused(
+                401,
+                "Your session has ended, please log in again".to_owned(),
+            )
+        }
+        Ok(RefreshOutcome::Reused) => {
+            return refresh_refused(401, "This refresh token was already used".to_owned())
+        }
+        Err(e) => {
+            error!("refresh error: {:?}", e);
+            return refresh_refused(500, e.to_string());
+        }
+    };
+
+    let refused = match state.get_ref().get_account(issued.uid).await {
+        Ok(Some(account)) if account.status == AccountStatus::Active => None,
+        Ok(Some(account)) => Some((403, account.status.denial().unwrap_or_default())),
+        Ok(None) => Some((401, "Unauthorized".to_owned())),
+        Err(e) => Some((500, e.to_string())),
+    };
+    if let Some((code, msg)) = refused {
+        return refresh_refused(code, msg);
+    }
+    let user = match state.get_ref().user_query(&issued.uid.to_string()).await {
+        Ok(user) => user,
+        Err(e) => {
+            error!("refresh user {} error: {:?}", issued.uid, e);
+            return refresh_refused(500, e.to_string());
+        }
+    };
+
+    let [access, refresh] = issued.cookies(issued.access_token(&user, &state));
+    let mut resp = ApiResult::<()>::new().code(200).with_msg("").to_resp();
+    for cookie in [access, refresh] {
+        if let Err(e) = resp.add_cookie(&cookie) {
+            error!("add {} cookie error: {:?}", cookie.name(), e);
+        }
+    }
+    resp
+}
+
+#[post("/logout")]
+async fn logout(auth: AuthorizationService, state: AppState) -> impl Responder {
+    let claims = &auth.claims;
+    match state.get_ref().end_session(claims.id, &claims.sid).await {
+        Ok(ended) => logged_out(ended as u64),
+        Err(e) => {
+            error!("logout {} error: {:?}", claims.id, e);
+            ApiResult::<()>::new()
+                .code(500)
+                .with_msg(e.to_string())
+                .to_resp()
+        }
+    }
+}
+
+#[post("/logout-everywhere")]
+async fn logout_everywhere(auth: AuthorizationService, state: AppState) -> impl Responder {
+    match state.get_ref().end_sessions(auth.claims.id).await {
+        Ok(ended) => logged_out(ended),
+        Err(e) => {
+            error!("logout everywhere {} error: {:?}", auth.claims.id, e);
+            ApiResult::<()>::new()
+                .code(500)
+                .with_msg(e.to_string())
+                .to_resp()
+        }
+    }
+}
+
+#[get("/sessions")]
+async fn get_sessions(auth: AuthorizationService, state: AppState) -> impl Responder {
+    let claims = &auth.claims;
+    match state.get_ref().get_sessions(claims.id, &claims.sid).await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            error!("sessions of {} error: {:?}", claims.id, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+// ends one of your sessions, e.g. on a lost device
+#[post("/session/{sid}/end")]
+async fn end_session(
+    params: web::Path<String>,
+    auth: AuthorizationService,
+    state: AppState,
+) -> impl Responder {
+    let sid = params.into_inner();
+
+    match state.get_ref().end_session(auth.claims.id, &sid).await {
+        Ok(true) => ApiResult::new()
+            .code(200)
+            .with_msg("")
+            .with_data(LogoutResponse { ended: 1 }),
+        Ok(false) => ApiResult::new().code(404).with_msg("Session not found"),
+        Err(e) => {
+            error!("end session {} error: {:?}", sid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+// public keys of access tokens, served as a bare JWK set for other services
+// registered at the root of the app rather than under /api/v1
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(state: AppState) -> impl Responder {
+    HttpResponse::Ok().json(state.keys.jwks())
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(refresh);
+    cfg.service(logout);
+    cfg.service(logout_everywhere);
+    cfg.service(get_sessions);
+    cfg.service(end_session);
+}
@@ -0,0 +1,131 @@
+use crate::config::Config;
+use crate::state::State;
+use crate::users::user::{Claims, User};
+
+use actix_web::cookie::{time::Duration, Cookie};
+use chrono::{NaiveDateTime, Utc};
+use ring::digest;
+
+// redis hash of a session, expires with its refresh token
+pub fn session_key(sid: &str) -> String {
+    format!("session:{}", sid)
+}
+
+// redis set of the session ids of a user
+pub fn user_sessions_key(uid: i64) -> String {
+    format!("sessions:{}", uid)
+}
+
+// the set is kept as long as a session can last since its last refresh
+pub fn user_sessions_ttl(config: &Config) -> usize {
+    config
+        .session_lifetime
+        .max(config.remember_me_lifetime)
+        .max(1) as usize
+}
+
+// refresh tokens are kept as digests
+pub fn refresh_digest(secret: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, secret.as_bytes()))
+}
+
+// sid.gen.secret, gen counts the refreshes of the session
+pub fn refresh_token_of(sid: &str, generation: i64, secret: &str) -> String {
+    format!("{}.{}.{}", sid, generation, secret)
+}
+
+// None for a malformed token
+pub fn parse_refresh_token(token: &str) -> Option<(&str, i64, &str)> {
+    let mut parts = token.splitn(3, '.');
+    let sid = parts.next().filter(|sid| !sid.is_empty())?;
+    let generation = parts.next()?.parse().ok()?;
+    Some((sid, generation, parts.next()?))
+}
+
+// A session and its current refresh token, from a login or a refresh
+#[derive(Debug, Clone)]
+pub struct Issued {
+    pub uid: i64,
+    pub sid: String,
+    pub xsrf_token: String,
+    // sid.gen.secret
+    pub refresh_token: String,
+    // seconds the refresh cookie is kept, None ends it with the browser
+    pub remember: Option<i64>,
+}
+
+impl Issued {
+    // a short lived token for the user of the session
+    pub fn access_token(&self, user: &User, state: &State) -> String {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user.username.clone(),
+            exp: (now + state.config.access_token_lifetime) as usize,
+            email: user.email.clone(),
+            username: user.username.clone(),
+            id: self.uid,
+            xsrf_token: self.xsrf_token.clone(),
+            image_url: user.image_url.clone(),
+            iat: now as usize,
+            sid: self.sid.clone(),
+        };
+        state.keys.sign(&claims).unwrap()
+    }
+    // the access token cookie and the refresh token cookie
+    pub fn cookies(&self, access_token: String) -> [Cookie<'static>; 2] {
+        let mut refresh = Cookie::build("refresh", self.refresh_token.clone())
+            .domain("localhost")
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .finish();
+        if let Some(remember) = self.remember {
+            refresh.set_max_age(Duration::seconds(remember));
+        }
+        let access = Cookie::build("jwt", access_token)
+            .domain("localhost")
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .finish();
+        [access, refresh]
+    }
+}
+
+#[derive(Debug)]
+pub enum RefreshOutcome {
+    Done(Issued),
+    // unknown or malformed token, or an ended session
+    Invalid,
+    // a token that was already rotated came back, the session is ended
+    Reused,
+}
+
+// Where a session was opened from
+#[derive(Debug, Clone)]
+pub struct Device {
+    // the User-Agent header
+    pub agent: String,
+    pub ip: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionResponse {
+    pub id: String,
+    pub device: String,
+    pub ip: String,
+    pub creation_date: Option<NaiveDateTime>,
+    pub last_seen_date: Option<NaiveDateTime>,
+    // the session making the request
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogoutResponse {
+    pub ended: u64,
+}
@@ -6,14 +6,20 @@ pub type KvPool = mobc::Pool<RedisConnectionManager>;
 pub type SqlPool = sqlx::PgPool;
 #[cfg(any(feature = "postgres"))]
 pub type PoolOptions = sqlx::postgres::PgPoolOptions;
+#[cfg(feature = "postgres")]
+pub type SqlTx<'a> = sqlx::Transaction<'a, sqlx::Postgres>;
+#[cfg(feature = "postgres")]
+pub type SqlConnection = sqlx::PgConnection;
 
 use crate::config::Config;
+use crate::sessions::keys::KeySet;
 
 #[derive(Clone)]
 pub struct State {
     pub config: Config,
     pub sql: SqlPool,
     pub kv: KvPool,
+    pub keys: KeySet,
 }
 
 pub type AppStateRaw = std::sync::Arc<State>;
@@ -0,0 +1,539 @@
+use super::suggestion::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::config::Config;
+use crate::questions::question::{split_tags, QUESTION_POST_TYPE};
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::{diff_snapshots, Change, Snapshot};
+use crate::state::{AppStateRaw, SqlTx};
+use crate::synonyms::dao::resolve_synonyms;
+use crate::tags::dao::set_post_tags;
+
+use uuid::Uuid;
+
+#[async_trait]
+pub trait ISuggestedEdit: std::ops::Deref<Target = AppStateRaw> {
+    async fn suggest_edit(
+        &self,
+        pid: i64,
+        uid: i64,
+        snapshot: &Snapshot,
+    ) -> sqlx::Result<SuggestionOutcome<i64>>;
+    async fn get_suggested_edits(
+        &self,
+        uid: i64,
+        form: &SuggestedEditsReq,
+    ) -> sqlx::Result<SuggestedEditsResponse>;
+    async fn review_suggested_edit(
+        &self,
+        sid: i64,
+        uid: i64,
+        reviewer: bool,
+        moderator: bool,
+        vote: ReviewVote,
+    ) -> sqlx::Result<SuggestionOutcome<ReviewResponse>>;
+    async fn improve_suggested_edit(
+        &self,
+        sid: i64,
+        uid: i64,
+        username: &str,
+        reviewer: bool,
+        form: &ImproveEditReq,
+    ) -> sqlx::Result<SuggestionOutcome<()>>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl ISuggestedEdit for &AppStateRaw {
+    async fn suggest_edit(
+        &self,
+        pid: i64,
+        uid: i64,
+        snapshot: &Snapshot,
+    ) -> sqlx::Result<SuggestionOutcome<i64>> {
+        let p = sqlx::query!(
+            r#"
+            select title, body, tags from posts where id = $1 and deletion_date is null
+            "#,
+            pid
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        let old = match p {
+            Some(p) => Snapshot {
+                title: p.title.unwrap_or_default(),
+                body: p.body.unwrap_or_default(),
+                tags: p.tags.unwrap_or_default(),
+            },
+            None => return Ok(SuggestionOutcome::NotFound),
+        };
+        // approving an edit pays its author, so it has to change something
+        if snapshot.edit_changes(&old).is_empty() {
+            return Ok(SuggestionOutcome::Invalid(
+                "The suggested edit does not change the post".to_owned(),
+            ));
+        }
+
+        let r = sqlx::query!(
+            r#"
+            insert into suggested_edits (post_id, owner_user_id, title, text, tags)
+            select id, $2, nullif($3, ''), $4, nullif($5, '') from posts
+            where id = $1 and deletion_date is null
+            on conflict (post_id) where approval_date is null and rejection_date is null do nothing
+            returning id
+            "#,
+            pid,
+            uid,
+            snapshot.title,
+            snapshot.body,
+            snapshot.tags
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        Ok(match r {
+            Some(r) => SuggestionOutcome::Done(r.id),
+            None => SuggestionOutcome::Pending,
+        })
+    }
+
+    // pending edits the user has not suggested nor reviewed yet, oldest first
+    async fn get_suggested_edits(
+        &self,
+        uid: i64,
+        form: &SuggestedEditsReq,
+    ) -> sqlx::Result<SuggestedEditsResponse> {
+        let per_page = self.config.questions_per_page as i64;
+        let offset = form.page.max(0) * per_page;
+
+        let count = sqlx::query!(
+            r#"
+            select count(1) as "count!" from suggested_edits s join posts p on p.id = s.post_id
+            where s.approval_date is null and s.rejection_date is null and p.deletion_date is null
+                and s.owner_user_id <> $1
+                and not exists(select 1 from suggested_edit_votes v
+                    where v.suggested_edit_id = s.id and v.user_id = $1)
+            "#,
+            uid
+        )
+        .fetch_one(&self.sql)
+        .await?
+        .count;
+
+        let sr = sqlx::query!(
+            r#"
+            select s.id, s.post_id, s.owner_user_id, s.creation_date, s.title, s.text, s.tags,
+                u.display_name as "owner_display_name?", p.title as post_title, p.body as post_body,
+                p.tags as post_tags,
+                (select count(1) from suggested_edit_votes v
+                    where v.suggested_edit_id = s.id and v.vote_type_id = $2) as "approvals!",
+                (select count(1) from suggested_edit_votes v
+                    where v.suggested_edit_id = s.id and v.vote_type_id = $3) as "rejections!"
+            from suggested_edits s join posts p on p.id = s.post_id
+            left join users u on u.id = s.owner_user_id
+            where s.approval_date is null and s.rejection_date is null and p.deletion_date is null
+                and s.owner_user_id <> $1
+                and not exists(select 1 from suggested_edit_votes v
+                    where v.suggested_edit_id = s.id and v.user_id = $1)
+            order by s.creation_date asc, s.id asc limit $4 offset $5
+            "#,
+            uid,
+            ReviewVote::Approve.vote_type(),
+            ReviewVote::Reject.vote_type(),
+            per_page,
+            offset
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let suggested_edits = sr
+            .into_iter()
+            .map(|s| {
+                let current = Snapshot {
+                    title: s.post_title.unwrap_or_default(),
+                    body: s.post_body.unwrap_or_default(),
+                    tags: s.post_tags.unwrap_or_default(),
+                };
+                let suggested = Snapshot {
+                    title: s.title.unwrap_or_default(),
+                    body: s.text.unwrap_or_default(),
+                    tags: s.tags.unwrap_or_default(),
+                };
+                SuggestedEditResponse {
+                    id: s.id.to_string(),
+                    post_id: s.post_id.unwrap_or_default().to_string(),
+                    owner_user_id: s.owner_user_id.unwrap_or_default().to_string(),
+                    owner_display_name: s.owner_display_name.unwrap_or_default(),
+                    creation_date: s.creation_date,
+                    approvals: s.approvals,
+                    rejections: s.rejections,
+                    diffs: diff_snapshots(&current, &suggested),
+                }
+            })
+            .collect();
+
+        Ok(SuggestedEditsResponse {
+            suggested_edits,
+            count,
+        })
+    }
+
+    async fn review_suggested_edit(
+        &self,
+        sid: i64,
+        uid: i64,
+        reviewer: bool,
+        moderator: bool,
+        vote: ReviewVote,
+    ) -> sqlx::Result<SuggestionOutcome<ReviewResponse>> {
+        let mut tx = self.sql.begin().await?;
+        let outcome =
+            review_suggested_edit_tx(&mut tx, &self.config, sid, uid, reviewer, moderator, vote)
+                .await?;
+        if let SuggestionOutcome::Done(_) = outcome {
+            tx.commit().await?;
+        }
+
+        Ok(outcome)
+    }
+
+    // applies the suggestion and then the reviewer's version as a separate revision
+    async fn improve_suggested_edit(
+        &self,
+        sid: i64,
+        uid: i64,
+        username: &str,
+        reviewer: bool,
+        form: &ImproveEditReq,
+    ) -> sqlx::Result<SuggestionOutcome<()>> {
+        let mut tx = self.sql.begin().await?;
+
+        let s = match pending_suggestion(&mut tx, sid).await? {
+            Some(s) => s,
+            None => return Ok(SuggestionOutcome::NotFound),
+        };
+        if s.owner_user_id == uid {
+            return Ok(SuggestionOutcome::Forbidden);
+        }
+        if !reviewer && s.post_owner_id != Some(uid) {
+            return Ok(SuggestionOutcome::Unprivileged);
+        }
+        if let Err(e) = form.validate_for(s.post_type_id) {
+            return Ok(SuggestionOutcome::Invalid(e.to_string()));
+        }
+        if !cast_review_vote(&mut tx, sid, uid, ReviewVote::Approve).await? {
+            return Ok(SuggestionOutcome::AlreadyVoted);
+        }
+
+        if approve_suggestion(&mut tx, &s, self.config.suggested_edit_reputation).await?
+            == SuggestionState::Approved
+        {
+            apply_edit(&mut tx, s.post_id, uid, username, &form.snapshot(), &[]).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(SuggestionOutcome::Done(()))
+    }
+}
+
+struct PendingSuggestion {
+    id: i64,
+    post_id: i64,
+    post_type_id: i16,
+    post_owner_id: Option<i64>,
+    owner_user_id: i64,
+    owner_display_name: String,
+    snapshot: Snapshot,
+}
+
+async fn pending_suggestion(
+    tx: &mut SqlTx<'_>,
+    sid: i64,
+) -> sqlx::Result<Option<PendingSuggestion>> {
+    let s = sqlx::query!(
+        r#"
+        select s.id, s.post_id as "post_id!", s.owner_user_id as "owner_user_id!", s.title, s.text,
+            s.tags, p.post_type_id as "post_type_id!", p.owner_user_id as post_owner_id,
+            u.display_name as "owner_display_name?"
+        from suggested_edits s join posts p on p.id = s.post_id
+        left join users u on u.id = s.owner_user_id
+        where s.id = $1 and s.approval_date is null and s.rejection_date is null
+            and p.deletion_date is null
+        for update of s
+        "#,
+        sid
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    Ok(s.map(|s| PendingSuggestion {
+        id: s.id,
+        post_id: s.post_id,
+        post_type_id: s.post_type_id,
+        post_owner_id: s.post_owner_id,
+        owner_user_id: s.owner_user_id,
+        owner_display_name: s.owner_display_name.unwrap_or_default(),
+        snapshot: Snapshot {
+            title: s.title.unwrap_or_default(),
+            body: s.text.unwrap_or_default(),
+            tags: s.tags.unwrap_or_default(),
+        },
+    }))
+}
+
+// false when the reviewer already voted on the suggestion
+async fn cast_review_vote(
+    tx: &mut SqlTx<'_>,
+    sid: i64,
+    uid: i64,
+    vote: ReviewVote,
+) -> sqlx::Result<bool> {
+    let r = sqlx::query!(
+        r#"
+        insert into suggested_edit_votes (suggested_edit_id, user_id, vote_type_id)
+        values ($1, $2, $3) on conflict do nothing
+        "#,
+        sid,
+        uid,
+        vote.vote_type()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(r.rows_affected() > 0)
+}
+
+// Applies the suggestion as a revision by its author and awards them `reputation`
+async fn approve_suggestion(
+    tx: &mut SqlTx<'_>,
+    s: &PendingSuggestion,
+    reputation: i64,
+) -> sqlx::Result<SuggestionState> {
+    let id = s.id.to_string();
+    let applied = [Change {
+        post_history_type_id: SUGGESTED_EDIT_APPLIED,
+        text: &id,
+    }];
+    let guid = match apply_edit(
+        tx,
+        s.post_id,
+        s.owner_user_id,
+        &s.owner_display_name,
+        &s.snapshot,
+        &applied,
+    )
+    .await?
+    {
+        Some(guid) => guid,
+        None => {
+            reject_suggestion(tx, s.id).await?;
+            return Ok(SuggestionState::Rejected);
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        update suggested_edits set approval_date = now(), revision_guid = $1 where id = $2
+        "#,
+        guid,
+        s.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        insert into votes (post_id, vote_type_id, user_id, creation_date, voter_reputation_change)
+        values ($1, $2, $3, current_date, $4)
+        "#,
+        s.post_id,
+        APPROVED_EDIT_SUGGESTION_VOTE_TYPE,
+        s.owner_user_id,
+        reputation
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        update users set reputation = coalesce(reputation, 0) + $1 where id = $2
+        "#,
+        reputation,
+        s.owner_user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(SuggestionState::Approved)
+}
+
+async fn reject_suggestion(tx: &mut SqlTx<'_>, sid: i64) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        update suggested_edits set rejection_date = now() where id = $1
+        "#,
+        sid
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+// Edits a question or answer as `uid`, `extra` history rows share the revision GUID.
+// None when the post is gone.
+pub async fn apply_edit(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    uid: i64,
+    username: &str,
+    edit: &Snapshot,
+    extra: &[Change<'_>],
+) -> sqlx::Result<Option<Uuid>> {
+    let p = sqlx::query!(
+        r#"
+        select post_type_id, parent_id, title, body, tags from posts
+        where id = $1 and post_type_id in ($2, $3) and deletion_date is null
+            and locked_date is null
+        for update
+        "#,
+        pid,
+        QUESTION_POST_TYPE,
+        ANSWER_POST_TYPE
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let p = match p {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+    let old = Snapshot {
+        title: p.title.unwrap_or_default(),
+        body: p.body.unwrap_or_default(),
+        tags: p.tags.unwrap_or_default(),
+    };
+    let question = p.post_type_id == Some(QUESTION_POST_TYPE);
+    // answers have no title or tags
+    let mut snapshot = Snapshot {
+        body: edit.body.clone(),
+        ..Default::default()
+    };
+    let tags = if question {
+        snapshot.title = edit.title.clone();
+        let tags = resolve_synonyms(tx, &split_tags(Some(edit.tags.clone()))).await?;
+        snapshot.tags = tags.join(" ");
+        tags
+    } else {
+        Vec::new()
+    };
+
+    let mut changes = snapshot.edit_changes(&old);
+    if !changes.is_empty() {
+        sqlx::query!(
+            r#"
+            update posts set body = $1, last_editor_user_id = $2, last_editor_display_name = $3,
+                last_edit_date = now(), last_activity_date = now()
+            where id = $4
+            "#,
+            snapshot.body,
+            uid,
+            username,
+            pid
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if question {
+            sqlx::query!(
+                r#"
+                update posts set title = $1, tags = $2 where id = $3
+                "#,
+                snapshot.title,
+                snapshot.tags,
+                pid
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            set_post_tags(tx, pid, &tags).await?;
+        }
+
+        sqlx::query!(
+            r#"
+            update posts set last_activity_date = now() where id = $1
+            "#,
+            p.parent_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    changes.extend(extra.iter().map(|c| Change {
+        post_history_type_id: c.post_history_type_id,
+        text: c.text,
+    }));
+    let guid = record_revision(tx, pid, uid, username, None, &changes).await?;
+
+    Ok(Some(guid))
+}
+
+// Reviews suggestion `sid` in the caller's transaction, which is only to be committed
+// when the outcome is Done
+pub async fn review_suggested_edit_tx(
+    tx: &mut SqlTx<'_>,
+    config: &Config,
+    sid: i64,
+    uid: i64,
+    reviewer: bool,
+    moderator: bool,
+    vote: ReviewVote,
+) -> sqlx::Result<SuggestionOutcome<ReviewResponse>> {
+    let s = match pending_suggestion(tx, sid).await? {
+        Some(s) => s,
+        None => return Ok(SuggestionOutcome::NotFound),
+    };
+    if s.owner_user_id == uid {
+        return Ok(SuggestionOutcome::Forbidden);
+    }
+    let post_owner = s.post_owner_id == Some(uid);
+    if !reviewer && !post_owner {
+        return Ok(SuggestionOutcome::Unprivileged);
+    }
+    if !cast_review_vote(tx, sid, uid, vote).await? {
+        return Ok(SuggestionOutcome::AlreadyVoted);
+    }
+
+    let votes = sqlx::query!(
+        r#"
+        select count(1) filter (where vote_type_id = $2) as "approvals!",
+            count(1) filter (where vote_type_id = $3) as "rejections!"
+        from suggested_edit_votes where suggested_edit_id = $1
+        "#,
+        sid,
+        ReviewVote::Approve.vote_type(),
+        ReviewVote::Reject.vote_type()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // the post owner and moderators decide alone
+    let binding = post_owner || moderator;
+    let state = match vote {
+        ReviewVote::Approve if binding || votes.approvals >= config.suggested_edit_approvals => {
+            approve_suggestion(tx, &s, config.suggested_edit_reputation).await?
+        }
+        ReviewVote::Reject if binding || votes.rejections >= config.suggested_edit_rejections => {
+            reject_suggestion(tx, sid).await?;
+            SuggestionState::Rejected
+        }
+        _ => SuggestionState::Pending,
+    };
+
+    Ok(SuggestionOutcome::Done(ReviewResponse {
+        approvals: votes.approvals,
+        rejections: votes.rejections,
+        state,
+    }))
+}
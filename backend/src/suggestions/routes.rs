@@ -0,0 +1,136 @@
+use super::dao::ISuggestedEdit;
+use super::suggestion::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::revisions::revision::Snapshot;
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+use serde::Serialize;
+use validator::Validate;
+
+#[post("/suggested-edits")]
+async fn get_suggested_edits(
+    form: web::Json<SuggestedEditsReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let form = form.into_inner();
+
+    if let Err(denied) = auth.require(Privilege::EditWithoutReview, &state.config.privileges) {
+        return denied;
+    }
+    match state
+        .get_ref()
+        .get_suggested_edits(auth.claims.id, &form)
+        .await
+    {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/suggested-edit/{id}/review")]
+async fn review_suggested_edit(
+    params: web::Path<i64>,
+    form: web::Json<ReviewReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let sid = params.into_inner();
+
+    let reviewer = auth.has(Privilege::EditWithoutReview, &state.config.privileges);
+    match state
+        .get_ref()
+        .review_suggested_edit(sid, auth.claims.id, reviewer, auth.moderator, form.vote)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("review suggested edit {} error: {:?}", sid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/suggested-edit/{id}/improve")]
+async fn improve_suggested_edit(
+    params: web::Path<i64>,
+    form: web::Json<ImproveEditReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let sid = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("improve suggested edit {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    let reviewer = auth.has(Privilege::EditWithoutReview, &state.config.privileges);
+    match state
+        .get_ref()
+        .improve_suggested_edit(sid, auth.claims.id, &auth.claims.username, reviewer, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome.map(|()| true), &state),
+        Err(e) => {
+            error!("improve suggested edit {} error: {:?}", sid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+// Queues the edit of a user who may not edit the post directly
+pub async fn submit_suggested_edit(
+    state: &AppState,
+    pid: i64,
+    uid: i64,
+    snapshot: &Snapshot,
+) -> ApiResult<bool> {
+    match state.get_ref().suggest_edit(pid, uid, snapshot).await {
+        Ok(SuggestionOutcome::Done(_)) => ApiResult::new()
+            .code(202)
+            .with_msg("Your edit will be visible once it is peer reviewed")
+            .with_data(true),
+        Ok(outcome) => outcome_to_result(outcome.map(|_| true), state),
+        Err(e) => {
+            error!("suggest edit {} error: {:?}", pid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result<T: Serialize>(
+    outcome: SuggestionOutcome<T>,
+    state: &AppState,
+) -> ApiResult<T> {
+    match outcome {
+        SuggestionOutcome::Done(data) => ApiResult::new().code(200).with_msg("").with_data(data),
+        SuggestionOutcome::NotFound => ApiResult::new()
+            .code(404)
+            .with_msg("Suggested edit not found or already reviewed"),
+        SuggestionOutcome::Forbidden => ApiResult::new()
+            .code(403)
+            .with_msg("You cannot review your own suggested edit"),
+        SuggestionOutcome::Unprivileged => {
+            Privilege::EditWithoutReview.denied(&state.config.privileges)
+        }
+        SuggestionOutcome::AlreadyVoted => ApiResult::new()
+            .code(409)
+            .with_msg("You have already reviewed this suggested edit"),
+        SuggestionOutcome::Pending => ApiResult::new()
+            .code(409)
+            .with_msg("This post already has a suggested edit waiting for review"),
+        SuggestionOutcome::Invalid(msg) => ApiResult::new().code(400).with_msg(msg),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_suggested_edits);
+    cfg.service(review_suggested_edit);
+    cfg.service(improve_suggested_edit);
+}
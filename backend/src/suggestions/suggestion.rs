@@ -0,0 +1,132 @@
+use crate::questions::question::{AskQuestion, QUESTION_POST_TYPE};
+use crate::revisions::revision::{FieldDiff, Snapshot};
+use chrono::NaiveDateTime;
+use validator::{Validate, ValidationErrors};
+
+// post_history_type_id written next to the edit rows of an applied suggestion
+pub const SUGGESTED_EDIT_APPLIED: i64 = 24;
+// vote_type_id carrying the reputation awarded to the suggester
+pub const APPROVED_EDIT_SUGGESTION_VOTE_TYPE: i32 = 16;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestedEditsReq {
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestedEditResponse {
+    pub id: String,
+    pub post_id: String,
+    pub owner_user_id: String,
+    pub owner_display_name: String,
+    pub creation_date: Option<NaiveDateTime>,
+    pub approvals: i64,
+    pub rejections: i64,
+    // changes against the current version of the post
+    pub diffs: Vec<FieldDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestedEditsResponse {
+    pub suggested_edits: Vec<SuggestedEditResponse>,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewVote {
+    Approve,
+    Reject,
+}
+
+impl ReviewVote {
+    // suggested_edit_votes reuses the up and down vote types
+    pub fn vote_type(&self) -> i32 {
+        match self {
+            ReviewVote::Approve => 2,
+            ReviewVote::Reject => 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewReq {
+    pub vote: ReviewVote,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionState {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewResponse {
+    pub approvals: i64,
+    pub rejections: i64,
+    pub state: SuggestionState,
+}
+
+// the reviewer's own version, title and tags are ignored for answers
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ImproveEditReq {
+    #[serde(default)]
+    pub title: String,
+    #[validate(length(min = 30, max = 102400))]
+    pub body: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ImproveEditReq {
+    // questions need a title and tags like AskQuestion, answers only a body
+    pub fn validate_for(&self, post_type_id: i16) -> Result<(), ValidationErrors> {
+        if post_type_id != QUESTION_POST_TYPE {
+            return self.validate();
+        }
+        AskQuestion {
+            title: self.title.clone(),
+            body: self.body.clone(),
+            tags: self.tags.clone(),
+        }
+        .validate()
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            title: self.title.clone(),
+            body: self.body.clone(),
+            tags: self.tags.join(" "),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SuggestionOutcome<T> {
+    Done(T),
+    NotFound,
+    // suggesters cannot review their own edits
+    Forbidden,
+    // reviewing needs the edit privilege unless the reviewer owns the post
+    Unprivileged,
+    AlreadyVoted,
+    // the post already has a suggested edit waiting for review
+    Pending,
+    Invalid(String),
+}
+
+impl<T> SuggestionOutcome<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> SuggestionOutcome<U> {
+        match self {
+            SuggestionOutcome::Done(t) => SuggestionOutcome::Done(f(t)),
+            SuggestionOutcome::NotFound => SuggestionOutcome::NotFound,
+            SuggestionOutcome::Forbidden => SuggestionOutcome::Forbidden,
+            SuggestionOutcome::Unprivileged => SuggestionOutcome::Unprivileged,
+            SuggestionOutcome::AlreadyVoted => SuggestionOutcome::AlreadyVoted,
+            SuggestionOutcome::Pending => SuggestionOutcome::Pending,
+            SuggestionOutcome::Invalid(msg) => SuggestionOutcome::Invalid(msg),
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use super::synonym::*;
+use crate::answers::answer::ANSWER_POST_TYPE;
+use crate::questions::question::{split_tags, QUESTION_POST_TYPE};
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::{Change, EDIT_TAGS};
+use crate::state::{AppStateRaw, SqlTx};
+use crate::tags::dao::{orphan_tag_wiki, set_deleted_post_tags, set_post_tags};
+use crate::votes::vote::VoteKind;
+
+#[async_trait]
+pub trait ITagSynonym: std::ops::Deref<Target = AppStateRaw> {
+    async fn get_synonyms(&self, tag: &str) -> sqlx::Result<TagSynonymsResponse>;
+    async fn propose_synonym(
+        &self,
+        target: &str,
+        uid: i64,
+        moderator: bool,
+        form: &ProposeSynonymReq,
+    ) -> sqlx::Result<SynonymOutcome<i64>>;
+    async fn vote_synonym(
+        &self,
+        sid: i64,
+        uid: i64,
+        moderator: bool,
+        kind: VoteKind,
+    ) -> sqlx::Result<SynonymOutcome<SynonymScoreResponse>>;
+    async fn approve_synonym(
+        &self,
+        sid: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<SynonymOutcome<ApproveResponse>>;
+    async fn reject_synonym(&self, sid: i64) -> sqlx::Result<SynonymOutcome<()>>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl ITagSynonym for &AppStateRaw {
+    async fn get_synonyms(&self, tag: &str) -> sqlx::Result<TagSynonymsResponse> {
+        let sr = sqlx::query!(
+            r#"
+            select id, source_tag_name, target_tag_name, score, owner_user_id, auto_rename_count,
+                last_auto_rename, approval_date, creation_date
+            from tag_synonyms where target_tag_name = $1
+            order by approval_date is null, score desc, id asc
+            "#,
+            tag
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let synonyms = sr
+            .into_iter()
+            .map(|s| TagSynonymResponse {
+                id: s.id.to_string(),
+                source: s.source_tag_name.unwrap_or_default(),
+                target: s.target_tag_name.unwrap_or_default(),
+                score: s.score.unwrap_or_default(),
+                owner_user_id: s.owner_user_id.unwrap_or_default().to_string(),
+                auto_rename_count: s.auto_rename_count.unwrap_or_default(),
+                last_auto_rename: s.last_auto_rename,
+                approved: s.approval_date.is_some(),
+                approval_date: s.approval_date,
+                creation_date: s.creation_date,
+            })
+            .collect();
+
+        Ok(TagSynonymsResponse { synonyms })
+    }
+
+    async fn propose_synonym(
+        &self,
+        target: &str,
+        uid: i64,
+        moderator: bool,
+        form: &ProposeSynonymReq,
+    ) -> sqlx::Result<SynonymOutcome<i64>> {
+        if form.source == target {
+            return Ok(SynonymOutcome::Invalid);
+        }
+
+        let mut tx = self.sql.begin().await?;
+
+        let t = sqlx::query!(
+            r#"
+            select exists(select 1 from tags where tag_name = $1) as "exists!",
+                exists(select 1 from tag_synonyms where source_tag_name = $1
+                    or target_tag_name = $2) as "chained!"
+            "#,
+            target,
+            form.source
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        if !t.exists {
+            return Ok(SynonymOutcome::NotFound);
+        }
+        // synonyms do not chain
+        if t.chained {
+            return Ok(SynonymOutcome::Invalid);
+        }
+        if !moderator && tag_score(&mut tx, uid, target).await? < self.config.tag_synonym_min_score
+        {
+            return Ok(SynonymOutcome::LowTagScore);
+        }
+
+        sqlx::query!(
+            r#"
+            insert into tags (tag_name) values ($1) on conflict (tag_name) do nothing
+            "#,
+            form.source
+        )
+        .execute(&mut tx)
+        .await?;
+
+        let s = sqlx::query!(
+            r#"
+            insert into tag_synonyms (source_tag_name, target_tag_name, owner_user_id, score)
+            values ($1, $2, $3, 0)
+            on conflict (source_tag_name) do nothing
+            returning id
+            "#,
+            form.source,
+            target,
+            uid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let s = match s {
+            Some(s) => s,
+            None => return Ok(SynonymOutcome::Conflict),
+        };
+
+        tx.commit().await?;
+
+        Ok(SynonymOutcome::Done(s.id))
+    }
+
+    async fn vote_synonym(
+        &self,
+        sid: i64,
+        uid: i64,
+        moderator: bool,
+        kind: VoteKind,
+    ) -> sqlx::Result<SynonymOutcome<SynonymScoreResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let s = sqlx::query!(
+            r#"
+            select target_tag_name, approval_date from tag_synonyms where id = $1
+            for update
+            "#,
+            sid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let s = match s {
+            Some(s) => s,
+            None => return Ok(SynonymOutcome::NotFound),
+        };
+        if s.approval_date.is_some() {
+            return Ok(SynonymOutcome::Conflict);
+        }
+        let target = s.target_tag_name.unwrap_or_default();
+        if !moderator && tag_score(&mut tx, uid, &target).await? < self.config.tag_synonym_min_score
+        {
+            return Ok(SynonymOutcome::LowTagScore);
+        }
+
+        let previous = sqlx::query!(
+            r#"
+            delete from tag_synonym_votes where tag_synonym_id = $1 and user_id = $2
+            returning vote
+            "#,
+            sid,
+            uid
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .map(|v| v.vote as i32)
+        .unwrap_or_default();
+
+        let vote = kind.score() as i32;
+        if vote != 0 {
+            sqlx::query!(
+                r#"
+                insert into tag_synonym_votes (tag_synonym_id, user_id, vote) values ($1, $2, $3)
+                "#,
+                sid,
+                uid,
+                vote as i16
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        let r = sqlx::query!(
+            r#"
+            update tag_synonyms set score = coalesce(score, 0) + $1 where id = $2
+            returning score as "score!"
+            "#,
+            vote - previous,
+            sid
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(SynonymOutcome::Done(SynonymScoreResponse { score: r.score }))
+    }
+
+    async fn approve_synonym(
+        &self,
+        sid: i64,
+        uid: i64,
+        username: &str,
+    ) -> sqlx::Result<SynonymOutcome<ApproveResponse>> {
+        let mut tx = self.sql.begin().await?;
+
+        let s = sqlx::query!(
+            r#"
+            update tag_synonyms set approved_by_user_id = $1, approval_date = now()
+            where id = $2 and approval_date is null
+            returning source_tag_name as "source!", target_tag_name as "target!"
+            "#,
+            uid,
+            sid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let s = match s {
+            Some(s) => s,
+            None => return Ok(SynonymOutcome::NotFound),
+        };
+
+        let qr = sqlx::query!(
+            r#"
+            select p.id, p.tags, p.deletion_date is not null as "deleted!" from posts p
+            join post_tags pt on pt.post_id = p.id
+            join tags t on t.id = pt.tag_id
+            where t.tag_name = $1 and p.post_type_id = $2
+            for update of p
+            "#,
+            s.source,
+            QUESTION_POST_TYPE
+        )
+        .fetch_all(&mut tx)
+        .await?;
+
+        let comment = format!("merged [{}] into [{}]", s.source, s.target);
+        for q in qr.iter() {
+            let mut tags: Vec<String> = Vec::new();
+            for tag in split_tags(q.tags.clone()) {
+                let tag = if tag == s.source { s.target.clone() } else { tag };
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            let joined = tags.join(" ");
+
+            sqlx::query!(
+                r#"
+                update posts set tags = $1 where id = $2
+                "#,
+                joined,
+                q.id
+            )
+            .execute(&mut tx)
+            .await?;
+
+            // deleted questions are retagged too but stay out of the counts
+            if q.deleted {
+                set_deleted_post_tags(&mut tx, q.id, &tags).await?;
+            } else {
+                set_post_tags(&mut tx, q.id, &tags).await?;
+            }
+
+            let changes = [Change {
+                post_history_type_id: EDIT_TAGS,
+                text: &joined,
+            }];
+            record_revision(&mut tx, q.id, uid, username, Some(&comment), &changes).await?;
+        }
+
+        orphan_tag_wiki(&mut tx, &s.source).await?;
+
+        tx.commit().await?;
+
+        Ok(SynonymOutcome::Done(ApproveResponse {
+            retagged: qr.len() as i64,
+        }))
+    }
+
+    async fn reject_synonym(&self, sid: i64) -> sqlx::Result<SynonymOutcome<()>> {
+        let mut tx = self.sql.begin().await?;
+
+        sqlx::query!(
+            r#"
+            delete from tag_synonym_votes where tag_synonym_id = $1
+            "#,
+            sid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        let deleted = sqlx::query!(
+            r#"
+            delete from tag_synonyms where id = $1 and approval_date is null
+            "#,
+            sid
+        )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        if deleted != 1 {
+            return Ok(SynonymOutcome::NotFound);
+        }
+
+        tx.commit().await?;
+
+        Ok(SynonymOutcome::Done(()))
+    }
+}
+
+// Total score of the user's answers on questions tagged `tag`
+async fn tag_score(tx: &mut SqlTx<'_>, uid: i64, tag: &str) -> sqlx::Result<i64> {
+    let r = sqlx::query!(
+        r#"
+        select coalesce(sum(a.score), 0)::bigint as "score!" from posts a
+        join post_tags pt on pt.post_id = a.parent_id
+        join tags t on t.id = pt.tag_id
+        where t.tag_name = $1 and a.owner_user_id = $2 and a.post_type_id = $3
+            and a.deletion_date is null
+        "#,
+        tag,
+        uid,
+        ANSWER_POST_TYPE
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    Ok(r.score)
+}
+
+// Replaces tags that are approved synonyms with their target, counting each rename
+pub async fn resolve_synonyms(tx: &mut SqlTx<'_>, tags: &[String]) -> sqlx::Result<Vec<String>> {
+    let renames = sqlx::query!(
+        r#"
+        update tag_synonyms set auto_rename_count = coalesce(auto_rename_count, 0) + 1,
+            last_auto_rename = now()
+        where source_tag_name = any($1) and approval_date is not null
+        returning source_tag_name as "source!", target_tag_name as "target!"
+        "#,
+        tags
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut resolved: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = renames
+            .iter()
+            .find(|r| &r.source == tag)
+            .map(|r| r.target.clone())
+            .unwrap_or_else(|| tag.clone());
+        if !resolved.contains(&tag) {
+            resolved.push(tag);
+        }
+    }
+
+    Ok(resolved)
+}
@@ -0,0 +1,143 @@
+use super::dao::ITagSynonym;
+use super::synonym::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::Privileged;
+use crate::state::AppState;
+use crate::votes::vote::VoteReq;
+
+use actix_web::{get, post, web, Responder};
+use serde::Serialize;
+use validator::Validate;
+
+#[get("/tag/{name}/synonyms")]
+async fn get_synonyms(params: web::Path<String>, state: AppState) -> impl Responder {
+    let tag = params.into_inner();
+    match state.get_ref().get_synonyms(&tag).await {
+        Ok(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/tag/{name}/synonym")]
+async fn propose_synonym(
+    params: web::Path<String>,
+    form: web::Json<ProposeSynonymReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let target = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("propose synonym {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    match state
+        .get_ref()
+        .propose_synonym(&target, auth.claims.id, auth.moderator, &form)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome.map(|id| id.to_string()), &state),
+        Err(e) => {
+            error!("propose synonym {} {:?} error: {:?}", target, form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/tag-synonym/{id}/vote")]
+async fn vote_synonym(
+    params: web::Path<i64>,
+    form: web::Json<VoteReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let sid = params.into_inner();
+    match state
+        .get_ref()
+        .vote_synonym(sid, auth.claims.id, auth.moderator, form.vote)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("vote synonym {} error: {:?}", sid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/tag-synonym/{id}/approve")]
+async fn approve_synonym(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let sid = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can approve tag synonyms");
+    }
+    match state
+        .get_ref()
+        .approve_synonym(sid, auth.claims.id, &auth.claims.username)
+        .await
+    {
+        Ok(outcome) => outcome_to_result(outcome, &state),
+        Err(e) => {
+            error!("approve synonym {} error: {:?}", sid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/tag-synonym/{id}/reject")]
+async fn reject_synonym(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let sid = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can reject tag synonyms");
+    }
+    match state.get_ref().reject_synonym(sid).await {
+        Ok(outcome) => outcome_to_result(outcome.map(|()| true), &state),
+        Err(e) => {
+            error!("reject synonym {} error: {:?}", sid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn outcome_to_result<T: Serialize>(outcome: SynonymOutcome<T>, state: &AppState) -> ApiResult<T> {
+    match outcome {
+        SynonymOutcome::Done(data) => ApiResult::new().code(200).with_msg("").with_data(data),
+        SynonymOutcome::NotFound => ApiResult::new().code(404).with_msg("Not found"),
+        SynonymOutcome::LowTagScore => ApiResult::new().code(403).with_msg(format!(
+            "Tag synonyms require an answer score of {} in the tag",
+            state.config.tag_synonym_min_score
+        )),
+        SynonymOutcome::Conflict => ApiResult::new()
+            .code(409)
+            .with_msg("The tag already has a synonym or it is already approved"),
+        SynonymOutcome::Invalid => ApiResult::new()
+            .code(400)
+            .with_msg("A tag cannot be a synonym of itself or of another synonym"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_synonyms);
+    cfg.service(propose_synonym);
+    cfg.service(vote_synonym);
+    cfg.service(approve_synonym);
+    cfg.service(reject_synonym);
+}
@@ -0,0 +1,62 @@
+use crate::questions::question::validate_tag;
+use chrono::NaiveDateTime;
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ProposeSynonymReq {
+    #[validate(custom = "validate_tag")]
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagSynonymResponse {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub score: i32,
+    pub owner_user_id: String,
+    pub auto_rename_count: i32,
+    pub last_auto_rename: Option<NaiveDateTime>,
+    pub approved: bool,
+    pub approval_date: Option<NaiveDateTime>,
+    pub creation_date: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagSynonymsResponse {
+    pub synonyms: Vec<TagSynonymResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SynonymScoreResponse {
+    pub score: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApproveResponse {
+    // questions moved from the source to the target tag
+    pub retagged: i64,
+}
+
+#[derive(Debug)]
+pub enum SynonymOutcome<T> {
+    Done(T),
+    NotFound,
+    // the user's answer score in the target tag is below Config::tag_synonym_min_score
+    LowTagScore,
+    // the source already has a synonym, or the synonym is already approved
+    Conflict,
+    // a tag cannot be its own synonym nor point at another synonym
+    Invalid,
+}
+
+impl<T> SynonymOutcome<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> SynonymOutcome<U> {
+        match self {
+            SynonymOutcome::Done(t) => SynonymOutcome::Done(f(t)),
+            SynonymOutcome::NotFound => SynonymOutcome::NotFound,
+            SynonymOutcome::LowTagScore => SynonymOutcome::LowTagScore,
+            SynonymOutcome::Conflict => SynonymOutcome::Conflict,
+            SynonymOutcome::Invalid => SynonymOutcome::Invalid,
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use super::tag::*;
+use crate::questions::question::{split_tags, QR, QUESTION_POST_TYPE};
+use crate::revisions::dao::record_revision;
+use crate::revisions::revision::Snapshot;
+use crate::state::{AppStateRaw, SqlTx};
+use crate::utils::slug::create_slug;
+
+#[async_trait]
+pub trait ITag: std::ops::Deref<Target = AppStateRaw> {
+    async fn get_tags(&self, form: &TagsReq) -> sqlx::Result<TagsResponse>;
+    async fn get_tag(&self, name: &str, form: &TagReq) -> sqlx::Result<TagResponse>;
+    async fn get_tag_wiki(&self, name: &str) -> sqlx::Result<TagWikiResponse>;
+    async fn set_tag_wiki(
+        &self,
+        name: &str,
+        uid: i64,
+        username: &str,
+        form: &TagWikiReq,
+    ) -> sqlx::Result<TagOutcome>;
+    async fn delete_tag(&self, name: &str) -> sqlx::Result<TagOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl ITag for &AppStateRaw {
+    async fn get_tags(&self, form: &TagsReq) -> sqlx::Result<TagsResponse> {
+        let per_page = self.config.tags_per_page;
+        let offset = form.page.max(0) * per_page;
+
+        let count = sqlx::query!(
+            r#"
+            select count(1) as "count!" from tags
+            "#
+        )
+        .fetch_one(&self.sql)
+        .await?
+        .count;
+
+        let sql = format!(
+            "select id, tag_name, count,
+                (select body from posts where posts.id = tags.excerpt_post_id) as excerpt
+            from tags order by {} limit $1 offset $2;",
+            form.sort.order_by()
+        );
+        let rows: Vec<TagRow> = sqlx::query_as(&sql)
+            .bind(per_page)
+            .bind(offset)
+            .fetch_all(&self.sql)
+            .await?;
+
+        Ok(TagsResponse {
+            tags: rows.into_iter().map(TR::from).collect(),
+            count,
+        })
+    }
+
+    async fn get_tag(&self, name: &str, form: &TagReq) -> sqlx::Result<TagResponse> {
+        let per_page = self.config.questions_per_page as i64;
+        let offset = form.page.max(0) * per_page;
+
+        let tag = sqlx::query_as!(
+            TagRow,
+            r#"
+            select id, tag_name, count,
+                (select body from posts where posts.id = tags.excerpt_post_id) as excerpt
+            from tags where tag_name = $1
+            "#,
+            name
+        )
+        .fetch_one(&self.sql)
+        .await?;
+
+        let qr = sqlx::query!(
+            r#"
+            select p.id, p.title, p.tags, p.score, p.view_count, p.answer_count, p.owner_user_id,
+                p.owner_display_name, p.last_activity_date
+            from posts p join post_tags pt on pt.post_id = p.id
+            where pt.tag_id = $1 and p.post_type_id = $2 and p.deletion_date is null
+            order by p.last_activity_date desc, p.id desc limit $3 offset $4
+            "#,
+            tag.id,
+            QUESTION_POST_TYPE,
+            per_page,
+            offset
+        )
+        .fetch_all(&self.sql)
+        .await?;
+
+        let mut questions = Vec::new();
+        for q in qr {
+            let title = q.title.unwrap_or_default();
+            let slug = create_slug(&title).await;
+            questions.push(QR {
+                id: q.id.to_string(),
+                title,
+                slug,
+                tags: split_tags(q.tags),
+                score: q.score.unwrap_or_default(),
+                view_count: q.view_count.unwrap_or_default(),
+                answer_count: q.answer_count.unwrap_or_default(),
+                owner_user_id: q.owner_user_id.unwrap_or_default().to_string(),
+                owner_display_name: q.owner_display_name.unwrap_or_default(),
+                last_activity_date: q.last_activity_date,
+            });
+        }
+
+        Ok(TagResponse {
+            tag: TR::from(tag),
+            questions,
+        })
+    }
+
+    async fn get_tag_wiki(&self, name: &str) -> sqlx::Result<TagWikiResponse> {
+        let w = sqlx::query!(
+            r#"
+            select t.tag_name as "tag_name!", t.excerpt_post_id, t.wiki_post_id,
+                e.body as "excerpt?", w.body as "body?",
+                w.last_editor_display_name as "last_editor_display_name?",
+                w.last_edit_date as "last_edit_date?"
+            from tags t
+            left join posts e on e.id = t.excerpt_post_id
+            left join posts w on w.id = t.wiki_post_id
+            where t.tag_name = $1
+            "#,
+            name
+        )
+        .fetch_one(&self.sql)
+        .await?;
+
+        Ok(TagWikiResponse {
+            tag: w.tag_name,
+            excerpt: w.excerpt,
+            body: w.body,
+            excerpt_post_id: w.excerpt_post_id.map(|id| id.to_string()),
+            wiki_post_id: w.wiki_post_id.map(|id| id.to_string()),
+            last_editor_display_name: w.last_editor_display_name,
+            last_edit_date: w.last_edit_date,
+        })
+    }
+
+    async fn set_tag_wiki(
+        &self,
+        name: &str,
+        uid: i64,
+        username: &str,
+        form: &TagWikiReq,
+    ) -> sqlx::Result<TagOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let tag = sqlx::query!(
+            r#"
+            select id, excerpt_post_id, wiki_post_id from tags where tag_name = $1 for update
+            "#,
+            name
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let tag = match tag {
+            Some(t) => t,
+            None => return Ok(TagOutcome::NotFound),
+        };
+
+        let excerpt_post_id = save_wiki_post(
+            &mut tx,
+            tag.excerpt_post_id,
+            TAG_WIKI_EXCERPT_POST_TYPE,
+            &form.excerpt,
+            uid,
+            username,
+        )
+        .await?;
+        let wiki_post_id = save_wiki_post(
+            &mut tx,
+            tag.wiki_post_id,
+            TAG_WIKI_POST_TYPE,
+            &form.body,
+            uid,
+            username,
+        )
+        .await?;
+
+        sqlx::query!(
+            r#"
+            update tags set excerpt_post_id = $1, wiki_post_id = $2 where id = $3
+            "#,
+            excerpt_post_id,
+            wiki_post_id,
+            tag.id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(TagOutcome::Done)
+    }
+
+    async fn delete_tag(&self, name: &str) -> sqlx::Result<TagOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let tag = sqlx::query!(
+            r#"
+            select id, coalesce(count, 0) as "count!",
+                exists(select 1 from tag_synonyms
+                    where source_tag_name = $1 or target_tag_name = $1) as "synonym!"
+            from tags where tag_name = $1
+            for update
+            "#,
+            name
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let tag = match tag {
+            Some(t) => t,
+            None => return Ok(TagOutcome::NotFound),
+        };
+        if tag.count > 0 || tag.synonym {
+            return Ok(TagOutcome::InUse);
+        }
+
+        orphan_tag_wiki(&mut tx, name).await?;
+
+        // post_tags may still hold rows of deleted questions
+        sqlx::query!(
+            r#"
+            delete from post_tags where tag_id = $1
+            "#,
+            tag.id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            delete from tags where id = $1
+            "#,
+            tag.id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(TagOutcome::Done)
+    }
+}
+
+// Creates or edits one of the wiki posts of a tag, recording the revision
+async fn save_wiki_post(
+    tx: &mut SqlTx<'_>,
+    pid: Option<i64>,
+    post_type: i16,
+    body: &str,
+    uid: i64,
+    username: &str,
+) -> sqlx::Result<i64> {
+    let snapshot = Snapshot {
+        body: body.to_owned(),
+        ..Default::default()
+    };
+
+    if let Some(pid) = pid {
+        let old = sqlx::query!(
+            r#"
+            update posts p set body = $1, last_editor_user_id = $2, last_editor_display_name = $3,
+                last_edit_date = now(), last_activity_date = now()
+            from posts o where p.id = $4 and o.id = p.id
+            returning o.body
+            "#,
+            body,
+            uid,
+            username,
+            pid
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(old) = old {
+            let old = Snapshot {
+                body: old.body.unwrap_or_default(),
+                ..Default::default()
+            };
+            let changes = snapshot.edit_changes(&old);
+            record_revision(tx, pid, uid, username, None, &changes).await?;
+            return Ok(pid);
+        }
+    }
+
+    let p = sqlx::query!(
+        r#"
+        insert into posts (post_type_id, body, owner_user_id, owner_display_name, score, view_count)
+        values ($1, $2, $3, $4, 0, 0) returning id
+        "#,
+        post_type,
+        body,
+        uid,
+        username
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let changes = snapshot.initial_changes();
+    record_revision(tx, p.id, uid, username, None, &changes).await?;
+
+    Ok(p.id)
+}
+
+// Turns the wiki posts of a deleted or merged tag into orphaned wikis
+pub async fn orphan_tag_wiki(tx: &mut SqlTx<'_>, name: &str) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        update posts set post_type_id = $1
+        where id in (select excerpt_post_id from tags where tag_name = $2
+            union select wiki_post_id from tags where tag_name = $2)
+        "#,
+        ORPHANED_TAG_WIKI_POST_TYPE,
+        name
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        update tags set excerpt_post_id = null, wiki_post_id = null where tag_name = $1
+        "#,
+        name
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+// Replaces the tags of a post with `tags` inside the caller's transaction,
+// creating missing tags and keeping tags.count in step with post_tags.
+pub async fn set_post_tags(tx: &mut SqlTx<'_>, pid: i64, tags: &[String]) -> sqlx::Result<()> {
+    replace_post_tags(tx, pid, tags, true).await
+}
+
+// Like set_post_tags for a deleted post, which archive() already took out of tags.count
+// and restore() counts again under its tags at that time.
+pub async fn set_deleted_post_tags(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    tags: &[String],
+) -> sqlx::Result<()> {
+    replace_post_tags(tx, pid, tags, false).await
+}
+
+async fn replace_post_tags(
+    tx: &mut SqlTx<'_>,
+    pid: i64,
+    tags: &[String],
+    counted: bool,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        insert into tags (tag_name) select unnest($1::text[]) on conflict (tag_name) do nothing
+        "#,
+        tags
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let removed: Vec<i64> = sqlx::query!(
+        r#"
+        delete from post_tags pt using tags t
+        where pt.post_id = $1 and pt.tag_id = t.id and t.tag_name <> all($2::text[])
+        returning pt.tag_id as "tag_id!"
+        "#,
+        pid,
+        tags
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|r| r.tag_id)
+    .collect();
+
+    let added: Vec<i64> = sqlx::query!(
+        r#"
+        insert into post_tags (post_id, tag_id)
+        select $1, id from tags where tag_name = any($2::text[])
+        on conflict do nothing
+        returning tag_id as "tag_id!"
+        "#,
+        pid,
+        tags
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|r| r.tag_id)
+    .collect();
+
+    if !counted {
+        return Ok(());
+    }
+    sqlx::query!(
+        r#"
+        update tags set count = coalesce(count, 0) - 1 where id = any($1)
+        "#,
+        &removed
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        update tags set count = coalesce(count, 0) + 1 where id = any($1)
+        "#,
+        &added
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
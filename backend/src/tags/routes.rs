@@ -0,0 +1,116 @@
+use super::dao::ITag;
+use super::tag::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{get, post, web, Responder};
+use validator::Validate;
+
+#[post("/tags")]
+async fn get_tags(form: web::Json<TagsReq>, state: AppState) -> impl Responder {
+    let form = form.into_inner();
+    match state.get_ref().get_tags(&form).await {
+        Ok(trs) => ApiResult::new().code(200).with_msg("").with_data(trs),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+#[post("/tag/{name}")]
+async fn get_tag(
+    params: web::Path<String>,
+    form: web::Json<TagReq>,
+    state: AppState,
+) -> impl Responder {
+    let name = params.into_inner();
+    let form = form.into_inner();
+    match state.get_ref().get_tag(&name, &form).await {
+        Ok(tr) => ApiResult::new().code(200).with_msg("").with_data(tr),
+        Err(sqlx::Error::RowNotFound) => ApiResult::new().code(404).with_msg("Tag not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(400).with_msg("Bad request!")
+        }
+    }
+}
+
+#[get("/tag/{name}/wiki")]
+async fn get_tag_wiki(params: web::Path<String>, state: AppState) -> impl Responder {
+    let name = params.into_inner();
+    match state.get_ref().get_tag_wiki(&name).await {
+        Ok(w) => ApiResult::new().code(200).with_msg("").with_data(w),
+        Err(sqlx::Error::RowNotFound) => ApiResult::new().code(404).with_msg("Tag not found"),
+        Err(e) => {
+            debug!("{:?}", e.to_string());
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/tag/{name}/wiki")]
+async fn edit_tag_wiki(
+    params: web::Path<String>,
+    form: web::Json<TagWikiReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let name = params.into_inner();
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("edit tag wiki {:?} error: {:?}", form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    if let Err(denied) = auth.require(Privilege::EditWithoutReview, &state.config.privileges) {
+        return denied;
+    }
+    match state
+        .get_ref()
+        .set_tag_wiki(&name, auth.claims.id, &auth.claims.username, &form)
+        .await
+    {
+        Ok(TagOutcome::Done) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Ok(_) => ApiResult::new().code(404).with_msg("Tag not found"),
+        Err(e) => {
+            error!("edit tag wiki {} error: {:?}", name, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/delete-tag/{name}")]
+async fn delete_tag(
+    params: web::Path<String>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let name = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can delete tags");
+    }
+    match state.get_ref().delete_tag(&name).await {
+        Ok(TagOutcome::Done) => ApiResult::new().code(200).with_msg("").with_data(true),
+        Ok(TagOutcome::NotFound) => ApiResult::new().code(404).with_msg("Tag not found"),
+        Ok(TagOutcome::InUse) => ApiResult::new()
+            .code(409)
+            .with_msg("The tag is still used by questions or synonyms"),
+        Err(e) => {
+            error!("delete tag {} error: {:?}", name, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_tags);
+    cfg.service(get_tag);
+    cfg.service(get_tag_wiki);
+    cfg.service(edit_tag_wiki);
+    cfg.service(delete_tag);
+}
@@ -0,0 +1,107 @@
+use crate::questions::question::QR;
+use chrono::NaiveDateTime;
+
+// post_type_id of tag wiki posts, a wiki is orphaned when its tag is deleted or merged away
+pub const ORPHANED_TAG_WIKI_POST_TYPE: i16 = 3;
+pub const TAG_WIKI_EXCERPT_POST_TYPE: i16 = 4;
+pub const TAG_WIKI_POST_TYPE: i16 = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagSort {
+    #[default]
+    Popular,
+    Name,
+    New,
+}
+
+impl TagSort {
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            TagSort::Popular => "count desc, tag_name asc",
+            TagSort::Name => "tag_name asc",
+            TagSort::New => "id desc",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagsReq {
+    #[serde(default)]
+    pub sort: TagSort,
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(FromRow, Debug)]
+pub struct TagRow {
+    pub id: i64,
+    pub tag_name: Option<String>,
+    pub count: Option<i64>,
+    pub excerpt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TR {
+    pub id: String,
+    pub name: String,
+    pub count: i64,
+    pub excerpt: Option<String>,
+}
+
+impl From<TagRow> for TR {
+    fn from(t: TagRow) -> Self {
+        Self {
+            id: t.id.to_string(),
+            name: t.tag_name.unwrap_or_default(),
+            count: t.count.unwrap_or_default(),
+            excerpt: t.excerpt,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagsResponse {
+    pub tags: Vec<TR>,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagReq {
+    #[serde(default)]
+    pub page: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagResponse {
+    pub tag: TR,
+    pub questions: Vec<QR>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct TagWikiReq {
+    #[validate(length(min = 1, max = 500))]
+    pub excerpt: String,
+    #[validate(length(min = 1, max = 102400))]
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagWikiResponse {
+    pub tag: String,
+    pub excerpt: Option<String>,
+    pub body: Option<String>,
+    // the wiki posts carry the revision history
+    pub excerpt_post_id: Option<String>,
+    pub wiki_post_id: Option<String>,
+    pub last_editor_display_name: Option<String>,
+    pub last_edit_date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub enum TagOutcome {
+    Done,
+    NotFound,
+    // the tag still has questions or synonyms
+    InUse,
+}
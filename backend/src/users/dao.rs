@@ -15,14 +15,26 @@ pub trait IUser: std::ops::Deref<Target = AppStateRaw> {
     async fn get_links(&self, uid: i64) -> sqlx::Result<LinksResponse>;
     async fn update_links(&self, uid: i64, form: &LinksResponse) -> sqlx::Result<bool>;
     async fn verify_email(&self, who: &str) -> sqlx::Result<bool>;
+    async fn is_moderator(&self, uid: i64) -> sqlx::Result<bool>;
+    async fn get_standing(&self, uid: i64) -> sqlx::Result<Option<Standing>>;
+    async fn update_password_hash(&self, uid: i64, hash: &str, salt: &str) -> sqlx::Result<bool>;
+    async fn get_account(&self, uid: i64) -> sqlx::Result<Option<Account>>;
+    async fn reset_password(
+        &self,
+        uid: i64,
+        fingerprint: &str,
+        form: &ResetPasswordReq,
+    ) -> sqlx::Result<ResetOutcome>;
+    async fn suspend_user(&self, uid: i64, form: &SuspendReq) -> sqlx::Result<SuspendOutcome>;
+    async fn reinstate_user(&self, uid: i64) -> sqlx::Result<SuspendOutcome>;
     async fn user_query(&self, who: &str) -> sqlx::Result<User> {
         let (column, placeholder) = column_placeholder(who);
 
         let sql = format!(
-            "SELECT id, display_name, email, password_hash, email_verified, profile_image_url, creation_date
+            "SELECT {}
             FROM users
             where {} = {};",
-            column, placeholder
+            USER_COLUMNS, column, placeholder
         );
 
         sqlx::query_as(&sql).bind(who).fetch_one(&self.sql).await
@@ -31,8 +43,8 @@ pub trait IUser: std::ops::Deref<Target = AppStateRaw> {
         let (column, placeholder) = column_placeholder(who);
 
         let sql = format!(
-            "update users set status='deleted' where {}={} RETURNING *;",
-            column, placeholder
+            "update users set status='{}' where {}={} RETURNING {};",
+            DELETED_STATUS, column, placeholder, USER_COLUMNS
         );
 
         sqlx::query_as(&sql).bind(who).fetch_one(&self.sql).await
@@ -43,19 +55,20 @@ pub trait IUser: std::ops::Deref<Target = AppStateRaw> {
 #[async_trait]
 impl IUser for &AppStateRaw {
     async fn user_add(&self, form: &Register) -> sqlx::Result<u64> {
-        let passh = form.passhash();
+        let (passh, salt) = form.passhash(&self.config.password_hashing);
         let email_hash = compute(&form.email.as_bytes());
         // TODO: move it to config
         let image_url =
             "https://www.gravatar.com/avatar/".to_string() + &format!("{:x}", email_hash);
         sqlx::query!(
             r#"
-        INSERT INTO users (display_name, email, password_hash, profile_image_url)
-        VALUES ($1 ,$2 ,$3, $4)
+        INSERT INTO users (display_name, email, password_hash, salt, profile_image_url)
+        VALUES ($1 ,$2 ,$3, $4, $5)
                 "#,
             form.username,
             form.email,
             passh,
+            salt,
             image_url
         )
         .execute(&self.sql)
@@ -207,6 +220,215 @@ impl IUser for &AppStateRaw {
         Ok(true)
     }
 
+    async fn is_moderator(&self, uid: i64) -> sqlx::Result<bool> {
+        let r = sqlx::query!(
+            r#"
+            select is_moderator from users where id=$1
+            "#,
+            uid
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        Ok(r.and_then(|r| r.is_moderator).unwrap_or(false))
+    }
+
+    async fn get_standing(&self, uid: i64) -> sqlx::Result<Option<Standing>> {
+        sqlx::query_as!(
+            Standing,
+            r#"
+            select coalesce(reputation, 0) as "reputation!", coalesce(is_moderator, false) as "moderator!"
+            from users where id=$1
+            "#,
+            uid
+        )
+        .fetch_optional(&self.sql)
+        .await
+    }
+
+    async fn update_password_hash(&self, uid: i64, hash: &str, salt: &str) -> sqlx::Result<bool> {
+        sqlx::query!(
+            r#"
+            update users set password_hash=$1, salt=$2 where id=$3
+            "#,
+            hash,
+            salt,
+            uid
+        )
+        .execute(&self.sql)
+        .await?;
+
+        Ok(true)
+    }
+
+    // a suspension that has run out is lifted here
+    async fn get_account(&self, uid: i64) -> sqlx::Result<Option<Account>> {
+        let r = sqlx::query!(
+            r#"
+            select status, suspended_until, coalesce(suspension_reason, '') as "reason!",
+                coalesce(suspended_until <= now(), true) as "lapsed!",
+                floor(extract(epoch from password_changed_date::timestamptz))::bigint
+                    as password_changed
+            from users where id=$1
+            "#,
+            uid
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        let r = match r {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        let status = match (r.status.as_str(), r.suspended_until) {
+            (DELETED_STATUS, _) => AccountStatus::Deleted,
+            (SUSPENDED_STATUS, Some(until)) if !r.lapsed => AccountStatus::Suspended {
+                until,
+                reason: r.reason,
+            },
+            (SUSPENDED_STATUS, _) => {
+                sqlx::query!(
+                    r#"
+                    update users set status=$1, suspended_until=null, suspension_reason=null
+                    where id=$2 and status=$3 and coalesce(suspended_until <= now(), true)
+                    "#,
+                    ACTIVE_STATUS,
+                    uid,
+                    SUSPENDED_STATUS
+                )
+                .execute(&self.sql)
+                .await?;
+                AccountStatus::Active
+            }
+            _ => AccountStatus::Active,
+        };
+
+        Ok(Some(Account {
+            status,
+            password_changed: r.password_changed,
+        }))
+    }
+
+    // the token holds the fingerprint of the hash it was made for, so it works once
+    async fn reset_password(
+        &self,
+        uid: i64,
+        fingerprint: &str,
+        form: &ResetPasswordReq,
+    ) -> sqlx::Result<ResetOutcome> {
+        let u = sqlx::query!(
+            r#"
+            select password_hash, status from users where id=$1
+            "#,
+            uid
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        let u = match u {
+            Some(u) if passhash_fingerprint(&u.password_hash) == fingerprint => u,
+            _ => return Ok(ResetOutcome::InvalidToken),
+        };
+        if u.status == DELETED_STATUS {
+            return Ok(ResetOutcome::Refused(AccountStatus::Deleted));
+        }
+
+        let (hash, salt) = form.passhash(&self.config.password_hashing);
+        let r = sqlx::query!(
+            r#"
+            update users set password_hash=$1, salt=$2, password_changed_date=now()
+            where id=$3 and password_hash=$4
+            "#,
+            hash,
+            salt,
+            uid,
+            u.password_hash
+        )
+        .execute(&self.sql)
+        .await?;
+
+        // another reset with the same token got there first
+        if r.rows_affected() == 0 {
+            return Ok(ResetOutcome::InvalidToken);
+        }
+        Ok(ResetOutcome::Done)
+    }
+
+    // a new suspension replaces the current one
+    async fn suspend_user(&self, uid: i64, form: &SuspendReq) -> sqlx::Result<SuspendOutcome> {
+        let u = sqlx::query!(
+            r#"
+            select status, coalesce(is_moderator, false) as "moderator!" from users where id=$1
+            "#,
+            uid
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        match u {
+            None => return Ok(SuspendOutcome::NotFound),
+            Some(u) if u.status == DELETED_STATUS => return Ok(SuspendOutcome::Deleted),
+            Some(u) if u.moderator => return Ok(SuspendOutcome::Moderator),
+            Some(_) => {}
+        }
+
+        let r = sqlx::query!(
+            r#"
+            update users set status=$1, suspended_until=now() + make_interval(days => $2),
+                suspension_reason=$3
+            where id=$4
+            returning status, suspended_until, suspension_reason
+            "#,
+            SUSPENDED_STATUS,
+            form.days,
+            form.reason,
+            uid
+        )
+        .fetch_one(&self.sql)
+        .await?;
+
+        Ok(SuspendOutcome::Done(SuspensionResponse {
+            user_id: uid.to_string(),
+            status: r.status,
+            suspended_until: r.suspended_until,
+            suspension_reason: r.suspension_reason,
+        }))
+    }
+
+    async fn reinstate_user(&self, uid: i64) -> sqlx::Result<SuspendOutcome> {
+        let u = sqlx::query!(
+            r#"
+            select status from users where id=$1
+            "#,
+            uid
+        )
+        .fetch_optional(&self.sql)
+        .await?;
+
+        match u {
+            None => return Ok(SuspendOutcome::NotFound),
+            Some(u) if u.status == DELETED_STATUS => return Ok(SuspendOutcome::Deleted),
+            Some(_) => {}
+        }
+
+        sqlx::query!(
+            r#"
+            update users set status=$1, suspended_until=null, suspension_reason=null where id=$2
+            "#,
+            ACTIVE_STATUS,
+            uid
+        )
+        .execute(&self.sql)
+        .await?;
+
+        Ok(SuspendOutcome::Done(SuspensionResponse {
+            user_id: uid.to_string(),
+            status: ACTIVE_STATUS.to_owned(),
+            suspended_until: None,
+            suspension_reason: None,
+        }))
+    }
+
     async fn update_title(&self, uid: i64, title: &String) -> sqlx::Result<bool> {
         sqlx::query!(
             r#"
@@ -309,8 +531,15 @@ impl IUser for &AppStateRaw {
     }
 }
 
+// the users columns under the field names of User
+const USER_COLUMNS: &str = "id, coalesce(display_name, '') as username, email, \
+    password_hash as pass, status, coalesce(profile_image_url, '') as image_url, \
+    coalesce(email_verified, false) as email_verified, \
+    coalesce(creation_date at time zone 'UTC', now()) as create_dt, \
+    coalesce(last_access_date at time zone 'UTC', now()) as update_dt";
+
 fn column_placeholder(id_or_name_or_email: &str) -> (&'static str, &'static str) {
-    let mut column = "name";
+    let mut column = "display_name";
 
     if id_or_name_or_email.contains("@") {
         column = "email";
@@ -320,10 +549,13 @@ fn column_placeholder(id_or_name_or_email: &str) -> (&'static str, &'static str)
 
     // postgres: $1, $2 ..
     // mysql/sqlite: ?, ? ..
-    let placeholder = if cfg!(feature = "postgres") {
-        "$1"
-    } else {
+    // ids are bound as text
+    let placeholder = if !cfg!(feature = "postgres") {
         "?"
+    } else if column == "id" {
+        "$1::bigint"
+    } else {
+        "$1"
     };
 
     (column, placeholder)
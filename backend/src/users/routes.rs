@@ -2,16 +2,15 @@ use super::dao::IUser;
 use super::user::*;
 use crate::api::ApiResult;
 use crate::middlewares::auth::AuthorizationService;
+use crate::middlewares::privilege::Privileged;
+use crate::sessions::dao::ISession;
+use crate::sessions::session::Device;
 use crate::state::AppState;
-use crate::utils::security::{check_signature, sign};
+use crate::utils::security::{check_signature, check_signature_within, sign};
+use crate::utils::send_email::send_email;
 use crate::utils::verify_user::verify_profile_user;
 
-use actix_web::{cookie::Cookie, get, post, web, Error, HttpResponse, Responder};
-use lettre::{
-    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
-    Tokio1Executor,
-};
-use uuid::Uuid;
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse, Responder};
 use validator::Validate;
 
 #[post("/register")]
@@ -28,20 +27,6 @@ async fn register(form: web::Json<Register>, state: AppState) -> impl Responder
     match state.get_ref().user_add(&form).await {
         Ok(res) => {
             info!("register {:?} res: {}", form, res);
-            // TODO: move it out to a common logic
-            let smtp_credentials = Credentials::new(
-                state.config.mail_username.clone(),
-                state.config.mail_password.clone(),
-            );
-
-            let mailer =
-                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&state.config.mail_host)
-                    .unwrap()
-                    .credentials(smtp_credentials)
-                    .build();
-
-            let from = state.config.from_name.clone() + "<" + &state.config.from_email + ">";
-            let to = form.email.clone();
             let subject = "Registration at Kunjika";
 
             // Sign an arbitrary string.
@@ -57,20 +42,8 @@ Thanks,
 Shiv",
                 form.username, state.config.host, token
             );
-            debug!("{:?}, {:?}", from, to);
-            let email = Message::builder()
-                .from(from.parse().unwrap())
-                .to(to.parse().unwrap())
-                .subject(subject)
-                .body(body.to_string())
-                .unwrap();
-
-            debug!("Sending email");
-            match mailer.send(email).await {
-                Ok(_r) => {
-                    debug!("{:?}", _r);
-                    ApiResult::new().with_msg("ok").with_data(res)
-                }
+            match send_email(&state, &form.email, subject, body).await {
+                Ok(()) => ApiResult::new().with_msg("ok").with_data(res),
                 Err(_e) => {
                     debug!("{:?}", _e);
                     ApiResult::new().code(502).with_msg("ok").with_data(0)
@@ -93,13 +66,9 @@ pub struct LoginResponse {
 
 // curl -v --data '{"name": "Bob", "email": "Bob@google.com", "password": "Bobpass"}' -H "Content-Type: application/json" -X POST localhost:8080/user/login
 #[post("/login")]
-async fn login(form: web::Json<Login>, state: AppState) -> impl Responder {
+async fn login(form: web::Json<Login>, req: HttpRequest, state: AppState) -> impl Responder {
     let form = form.into_inner();
 
-    use chrono::{DateTime, Duration, Utc};
-    use jsonwebtoken::{encode, EncodingKey, Header};
-
-    // todo: distable login for deleted and blocked users
     match state.get_ref().user_query(&form.email).await {
         Ok(user) => {
             info!("find user {:?} ok: {:?}", form, user);
@@ -108,42 +77,67 @@ async fn login(form: web::Json<Login>, state: AppState) -> impl Responder {
                 return HttpResponse::Unauthorized().finish();
             }
 
-            if form.verify(&user.pass) {
-                let exp: DateTime<Utc> = Utc::now()
-                    + if form.rememberme {
-                        Duration::days(30)
-                    } else {
-                        Duration::days(365 * 100)
-                    };
-
-                let uuid = Uuid::new_v4().to_string();
-                let my_claims = Claims {
-                    sub: user.username.clone(),
-                    exp: exp.timestamp() as usize,
-                    email: form.email,
-                    username: user.username.clone(),
-                    id: user.id,
-                    xsrf_token: uuid,
-                    image_url: user.image_url,
+            let hashing = &state.config.password_hashing;
+            let check = form.verify(&user.pass, hashing);
+            if check != PasswordCheck::Invalid {
+                if check == PasswordCheck::Outdated {
+                    let (hash, salt) = form.passhash(hashing);
+                    if let Err(e) = state
+                        .get_ref()
+                        .update_password_hash(user.id, &hash, &salt)
+                        .await
+                    {
+                        error!("rehash password of {} error: {:?}", user.id, e);
+                    }
+                }
+                match state.get_ref().get_account(user.id).await {
+                    Ok(Some(Account {
+                        status: AccountStatus::Active,
+                        ..
+                    })) => {}
+                    Ok(Some(Account { status, .. })) => {
+                        return ApiResult::<()>::new()
+                            .code(403)
+                            .with_msg(status.denial().unwrap_or_default())
+                            .to_resp();
+                    }
+                    Ok(None) => return HttpResponse::Unauthorized().finish(),
+                    Err(e) => {
+                        error!("account status of {} error: {:?}", user.id, e);
+                        return HttpResponse::InternalServerError().finish();
+                    }
+                }
+                let device = Device {
+                    agent: req
+                        .headers()
+                        .get("User-Agent")
+                        .and_then(|h| h.to_str().ok())
+                        .unwrap_or_default()
+                        .to_owned(),
+                    ip: req
+                        .connection_info()
+                        .realip_remote_addr()
+                        .unwrap_or_default()
+                        .to_owned(),
+                };
+                let issued = match state
+                    .get_ref()
+                    .create_session(user.id, &device, form.rememberme)
+                    .await
+                {
+                    Ok(issued) => issued,
+                    Err(e) => {
+                        error!("create session of {} error: {:?}", user.id, e);
+                        return HttpResponse::InternalServerError().finish();
+                    }
                 };
-                let key = state.config.jwt_priv.as_bytes();
-                let token = encode(
-                    &Header::default(),
-                    &my_claims,
-                    &EncodingKey::from_secret(key),
-                )
-                .unwrap();
+                let token = issued.access_token(&user, &state);
                 let r = LoginResponse { success: true };
+                let [access, refresh] = issued.cookies(token);
                 let resp = match serde_json::to_string(&r) {
                     Ok(json) => HttpResponse::Ok()
-                        .cookie(
-                            Cookie::build("jwt", token)
-                                .domain("localhost")
-                                .path("/")
-                                .secure(true)
-                                .http_only(true)
-                                .finish(),
-                        )
+                        .cookie(access)
+                        .cookie(refresh)
                         .content_type("application/json")
                         .body(json),
                     Err(e) => Error::from(e).into(),
@@ -185,14 +179,15 @@ async fn check_username_availability(form: web::Json<UserName>, state: AppState)
 #[get("/confirm-email/{token}")]
 async fn confirm_email(form: web::Path<String>, state: AppState) -> impl Responder {
     let token = form.into_inner();
-    let email = check_signature(&token, &state).await;
-    if &email == "Signature was expired" {
-        ApiResult::new()
-            .code(400)
-            .with_msg("Bad request")
-            .with_data("".to_string())
-    } else {
-        match state.get_ref().verify_email(&email).await {
+    match check_signature(&token, &state).await {
+        Err(e) => {
+            debug!("confirm email {} error: {}", token, e);
+            ApiResult::new()
+                .code(400)
+                .with_msg("Bad request")
+                .with_data("".to_string())
+        }
+        Ok(email) => match state.get_ref().verify_email(&email).await {
             Ok(_user) => {
                 debug!("User found, username unavailable");
                 ApiResult::new().code(200).with_msg("Email verified")
@@ -203,10 +198,100 @@ async fn confirm_email(form: web::Path<String>, state: AppState) -> impl Respond
                     .code(400)
                     .with_msg("Your email is not registered with us!")
             }
+        },
+    }
+}
+
+// answers the same whether or not the email is registered
+#[post("/forgot-password")]
+async fn forgot_password(form: web::Json<ForgotPasswordReq>, state: AppState) -> impl Responder {
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("forgot password {:?} error: {:?}", form, e);
+        return ApiResult::<()>::new().code(400).with_msg(e.to_string());
+    }
+    match state.get_ref().user_query(&form.email).await {
+        Ok(user) if user.status != DELETED_STATUS => {
+            let token = sign(
+                &format!("{}:{}", user.id, passhash_fingerprint(&user.pass)),
+                &state,
+            )
+            .await;
+            let body = format!(
+                "Hi {},
+
+Someone asked to reset the password of your Kunjika account.
+Your password reset link is https://{}/reset-password/{}.
+This link works once and expires in {} minutes. If you did not ask for it, ignore this email.
+
+Thanks,
+Shiv",
+                user.username,
+                state.config.host,
+                token,
+                state.config.password_reset_expiry_time / 60
+            );
+            let subject = "Password reset at Kunjika";
+            if let Err(e) = send_email(&state, &user.email, subject, body).await {
+                error!("password reset mail to {} error: {:?}", user.id, e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => debug!("forgot password {:?} error: {:?}", form, e),
+    }
+    ApiResult::new()
+        .code(200)
+        .with_msg("If this email is registered, a password reset link is on its way")
+}
+
+#[post("/reset-password")]
+async fn reset_password(form: web::Json<ResetPasswordReq>, state: AppState) -> impl Responder {
+    let form = form.into_inner();
+
+    if let Err(e) = form.validate() {
+        debug!("reset password error: {:?}", e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    if !form.match_password() {
+        return ApiResult::new().code(400).with_msg("Passwords are bad!");
+    }
+    let max_age = state.config.password_reset_expiry_time;
+    let signed = check_signature_within(&form.token, max_age, &state).await;
+    let (uid, fingerprint) = match signed.as_deref().map(|s| s.split_once(':')) {
+        Ok(Some((uid, fingerprint))) => match uid.parse::<i64>() {
+            Ok(uid) => (uid, fingerprint.to_owned()),
+            Err(_) => return invalid_reset_token(),
+        },
+        _ => return invalid_reset_token(),
+    };
+    match state.get_ref().reset_password(uid, &fingerprint, &form).await {
+        Ok(ResetOutcome::Done) => {
+            info!("password of {} reset", uid);
+            if let Err(e) = state.get_ref().end_sessions(uid).await {
+                error!("end sessions of {} error: {:?}", uid, e);
+            }
+            ApiResult::new()
+                .code(200)
+                .with_msg("Your password has been reset, please log in")
+        }
+        Ok(ResetOutcome::InvalidToken) => invalid_reset_token(),
+        Ok(ResetOutcome::Refused(status)) => ApiResult::new()
+            .code(403)
+            .with_msg(status.denial().unwrap_or_default()),
+        Err(e) => {
+            error!("reset password of {} error: {:?}", uid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
         }
     }
 }
 
+fn invalid_reset_token() -> ApiResult {
+    ApiResult::new()
+        .code(400)
+        .with_msg("This reset link is invalid, expired or already used")
+}
+
 #[post("/users")]
 async fn get_users(form: web::Json<UsersReq>, state: AppState) -> impl Responder {
     let last_user = form.into_inner();
@@ -385,11 +470,82 @@ async fn update_links(
     }
 }
 
+#[post("/user/{id}/suspend")]
+async fn suspend_user(
+    params: web::Path<i64>,
+    form: web::Json<SuspendReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let uid = params.into_inner();
+    let form = form.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can suspend users");
+    }
+    if let Err(e) = form.validate() {
+        debug!("suspend {} {:?} error: {:?}", uid, form, e);
+        return ApiResult::new().code(400).with_msg(e.to_string());
+    }
+    match state.get_ref().suspend_user(uid, &form).await {
+        Ok(outcome) => {
+            info!("user {} suspended {}: {:?}", auth.claims.id, uid, form);
+            suspend_outcome_to_result(outcome)
+        }
+        Err(e) => {
+            error!("suspend {} error: {:?}", uid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+#[post("/user/{id}/reinstate")]
+async fn reinstate_user(
+    params: web::Path<i64>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let uid = params.into_inner();
+
+    if !auth.moderator {
+        return ApiResult::new()
+            .code(403)
+            .with_msg("Only moderators can reinstate users");
+    }
+    match state.get_ref().reinstate_user(uid).await {
+        Ok(outcome) => {
+            info!("user {} reinstated {}", auth.claims.id, uid);
+            suspend_outcome_to_result(outcome)
+        }
+        Err(e) => {
+            error!("reinstate {} error: {:?}", uid, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+fn suspend_outcome_to_result(outcome: SuspendOutcome) -> ApiResult<SuspensionResponse> {
+    match outcome {
+        SuspendOutcome::Done(res) => ApiResult::new().code(200).with_msg("").with_data(res),
+        SuspendOutcome::NotFound => ApiResult::new().code(404).with_msg("User not found"),
+        SuspendOutcome::Deleted => ApiResult::new()
+            .code(409)
+            .with_msg("This account has been deleted"),
+        SuspendOutcome::Moderator => ApiResult::new()
+            .code(409)
+            .with_msg("Moderators cannot be suspended"),
+    }
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(login);
     cfg.service(register);
     cfg.service(check_username_availability);
     cfg.service(confirm_email);
+    cfg.service(forgot_password);
+    cfg.service(reset_password);
     cfg.service(get_users);
     cfg.service(get_profile);
     cfg.service(update_username);
@@ -399,4 +555,6 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(update_location);
     cfg.service(get_links);
     cfg.service(update_links);
+    cfg.service(suspend_user);
+    cfg.service(reinstate_user);
 }
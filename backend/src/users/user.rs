@@ -1,18 +1,24 @@
 // PBKDF2 < bcrypt < scrypt < argon2
-use argon2::{self, Config};
+use crate::config::PasswordHashing;
 use chrono::Utc;
 use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
 
-fn passhash(pass: &str) -> String {
-    let config = Config::default();
-    const CREDENTIAL_LEN: usize = digest::SHA512_OUTPUT_LEN;
-    let salt = [0u8; CREDENTIAL_LEN];
-    let hash = argon2::hash_encoded(pass.as_bytes(), &salt, &config).unwrap();
+// hashes with a fresh random salt, returns the encoded hash and the hex salt
+fn passhash(pass: &str, hashing: &PasswordHashing) -> (String, String) {
+    let mut salt = vec![0u8; hashing.salt_length];
+    SystemRandom::new().fill(&mut salt).expect("random salt");
+    let hash = argon2::hash_encoded(pass.as_bytes(), &salt, &hashing.argon2())
+        .expect("password_hashing is validated at startup");
     // info!("{}{}: {}", name, pass, hash);
-    hash
+    (hash, hex::encode(salt))
 }
 fn passhash_verify(pass: &str, hash: &str) -> bool {
-    argon2::verify_encoded(&hash, pass.as_bytes()).unwrap()
+    argon2::verify_encoded(hash, pass.as_bytes()).unwrap_or(false)
+}
+// names a password hash in reset tokens, which stop working once the hash changes
+pub fn passhash_fingerprint(hash: &str) -> String {
+    hex::encode(&digest::digest(&digest::SHA256, hash.as_bytes()).as_ref()[..8])
 }
 
 #[cfg(any(feature = "postgres"))]
@@ -44,9 +50,26 @@ pub struct Login {
     pub rememberme: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    Invalid,
+    Valid,
+    // the password matches a hash made with an old salt or old parameters
+    Outdated,
+}
+
 impl Login {
-    pub fn verify(&self, hash: &str) -> bool {
-        passhash_verify(&self.password, hash)
+    pub fn verify(&self, hash: &str, hashing: &PasswordHashing) -> PasswordCheck {
+        if !passhash_verify(&self.password, hash) {
+            PasswordCheck::Invalid
+        } else if hashing.is_current(hash) {
+            PasswordCheck::Valid
+        } else {
+            PasswordCheck::Outdated
+        }
+    }
+    pub fn passhash(&self, hashing: &PasswordHashing) -> (String, String) {
+        passhash(&self.password, hashing)
     }
 }
 
@@ -59,7 +82,13 @@ pub struct Claims {
     pub username: String,
     pub id: i64,
     pub xsrf_token: String,
-    pub image_url: String
+    pub image_url: String,
+    // issued at, tokens from before the last password change are refused
+    #[serde(default)]
+    pub iat: usize,
+    // the redis session backing the token, ended sessions are refused
+    #[serde(default)]
+    pub sid: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Validate)]
@@ -92,8 +121,8 @@ fn validate_username(username: &str) -> Result<(), ValidationError> {
 }
 
 impl Register {
-    pub fn passhash(&self) -> String {
-        passhash(&self.password)
+    pub fn passhash(&self, hashing: &PasswordHashing) -> (String, String) {
+        passhash(&self.password, hashing)
     }
     pub fn match_password(&self) -> bool {
         self.password == self.confirm_password
@@ -104,6 +133,38 @@ pub fn first_char_is_number(s: &str) -> bool {
     s.get(0..1).and_then(|c| c.parse::<u8>().ok()).is_some()
 }
 
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ForgotPasswordReq {
+    #[validate(length(min = 6, max = 256), email)]
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ResetPasswordReq {
+    pub token: String,
+    #[validate(length(min = 16, max = 64))]
+    pub password: String,
+    #[validate(length(min = 16, max = 64))]
+    pub confirm_password: String,
+}
+
+impl ResetPasswordReq {
+    pub fn passhash(&self, hashing: &PasswordHashing) -> (String, String) {
+        passhash(&self.password, hashing)
+    }
+    pub fn match_password(&self) -> bool {
+        self.password == self.confirm_password
+    }
+}
+
+#[derive(Debug)]
+pub enum ResetOutcome {
+    Done,
+    // the token is malformed, expired or was made for an older password
+    InvalidToken,
+    Refused(AccountStatus),
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AvailabilityResponse {
     pub success: bool,
@@ -150,4 +211,87 @@ pub struct LinksResponse {
     pub website: String,
     pub git: String,
     pub twitter: String
-}
\ No newline at end of file
+}
+// users.status
+// the user taking the actions of the site itself
+pub const COMMUNITY_USER_ID: i64 = -1;
+pub const COMMUNITY_USER_NAME: &str = "Community";
+
+pub const ACTIVE_STATUS: &str = "active";
+pub const SUSPENDED_STATUS: &str = "suspended";
+pub const DELETED_STATUS: &str = "deleted";
+
+// What a session is checked against on each request
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub status: AccountStatus,
+    // whole seconds since the epoch, rounded down like iat
+    pub password_changed: Option<i64>,
+}
+
+impl Account {
+    // sessions issued before the second of the last password change are over,
+    // so the token issued along with the change stays valid
+    pub fn session_ended(&self, claims: &Claims) -> bool {
+        self.password_changed.is_some_and(|changed| (claims.iat as i64) < changed)
+    }
+}
+
+// Whether an account may sign in and act
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountStatus {
+    Active,
+    Suspended {
+        until: chrono::NaiveDateTime,
+        reason: String,
+    },
+    Deleted,
+}
+
+impl AccountStatus {
+    // why the account is turned away, None for active accounts
+    pub fn denial(&self) -> Option<String> {
+        match self {
+            AccountStatus::Active => None,
+            AccountStatus::Suspended { until, reason } => Some(format!(
+                "Your account is suspended until {}: {}",
+                until.format("%Y-%m-%d %H:%M"),
+                reason
+            )),
+            AccountStatus::Deleted => Some("This account has been deleted".to_owned()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct SuspendReq {
+    #[validate(range(min = 1, max = 365))]
+    pub days: i32,
+    #[validate(length(min = 10, max = 500))]
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuspensionResponse {
+    pub user_id: String,
+    pub status: String,
+    pub suspended_until: Option<chrono::NaiveDateTime>,
+    pub suspension_reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SuspendOutcome {
+    Done(SuspensionResponse),
+    NotFound,
+    // deleted accounts stay deleted
+    Deleted,
+    // moderators are not suspended by other moderators
+    Moderator,
+}
+
+// What privilege checks are made against
+#[derive(Debug)]
+pub struct Standing {
+    pub reputation: i64,
+    pub moderator: bool,
+}
@@ -1,3 +1,4 @@
 pub mod security;
+pub mod send_email;
 pub mod slug;
 pub mod verify_user;
\ No newline at end of file
@@ -10,13 +10,24 @@ pub async fn sign(text: &str, state: &AppState) -> String {
     signer.sign(text)
 }
 
-pub async fn check_signature(text: &str, state: &AppState) -> String {
+// checks an email verification token
+pub async fn check_signature(text: &str, state: &AppState) -> Result<String, &'static str> {
+    check_signature_within(text, state.config.email_verification_expiry_time, state).await
+}
+
+// checks a token signed at most max_age seconds ago
+pub async fn check_signature_within(
+    text: &str,
+    max_age: u64,
+    state: &AppState,
+) -> Result<String, &'static str> {
     let signer = default_builder(state.config.secret_key.clone())
         .build()
         .into_timestamp_signer();
 
-    let unsigned = signer.unsign(&text).expect("Signature was not valid");
+    let unsigned = signer.unsign(text).map_err(|_| "Signature was not valid")?;
     unsigned
-        .value_if_not_expired(Duration::from_secs(state.config.email_verification_expiry_time))
-        .expect("Signature was expired").to_string()
+        .value_if_not_expired(Duration::from_secs(max_age))
+        .map(|value| value.to_string())
+        .map_err(|_| "Signature was expired")
 }
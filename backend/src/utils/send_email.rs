@@ -0,0 +1,37 @@
+use crate::how::AnyResult;
+use crate::state::AppState;
+
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+
+// sends a plain text email from the configured sender
+pub async fn send_email(
+    state: &AppState,
+    to: &str,
+    subject: &str,
+    body: String,
+) -> AnyResult<()> {
+    let smtp_credentials = Credentials::new(
+        state.config.mail_username.clone(),
+        state.config.mail_password.clone(),
+    );
+
+    let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&state.config.mail_host)?
+        .credentials(smtp_credentials)
+        .build();
+
+    let from = state.config.from_name.clone() + "<" + &state.config.from_email + ">";
+    debug!("{:?}, {:?}", from, to);
+    let email = Message::builder()
+        .from(from.parse()?)
+        .to(to.parse()?)
+        .subject(subject)
+        .body(body)?;
+
+    debug!("Sending email");
+    let r = mailer.send(email).await?;
+    debug!("{:?}", r);
+    Ok(())
+}
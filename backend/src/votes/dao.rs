@@ -0,0 +1,169 @@
+use super::vote::*;
+use crate::badges::dao::check_badges;
+use crate::state::AppStateRaw;
+
+#[async_trait]
+pub trait IVote: std::ops::Deref<Target = AppStateRaw> {
+    async fn vote(&self, pid: i64, uid: i64, kind: VoteKind) -> sqlx::Result<VoteOutcome>;
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl IVote for &AppStateRaw {
+    async fn vote(&self, pid: i64, uid: i64, kind: VoteKind) -> sqlx::Result<VoteOutcome> {
+        let mut tx = self.sql.begin().await?;
+
+        let post = sqlx::query!(
+            r#"
+            select owner_user_id, coalesce(score, 0) as "score!", locked_date,
+                community_owned_date is not null as "wiki!"
+            from posts
+            where id = $1 and post_type_id in (1, 2) and deletion_date is null
+            for update
+            "#,
+            pid
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let post = match post {
+            Some(p) => p,
+            None => return Ok(VoteOutcome::NotFound),
+        };
+        if post.owner_user_id == Some(uid) {
+            return Ok(VoteOutcome::OwnPost);
+        }
+        if post.locked_date.is_some() {
+            return Ok(VoteOutcome::Locked);
+        }
+
+        let existing = sqlx::query!(
+            r#"
+            select id, vote_type_id, owner_reputation_change, voter_reputation_change from votes
+            where post_id = $1 and user_id = $2 and vote_type_id in ($3, $4)
+            for update
+            "#,
+            pid,
+            uid,
+            UP_VOTE_TYPE,
+            DOWN_VOTE_TYPE
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let current = VoteKind::from_vote_type(existing.as_ref().and_then(|v| v.vote_type_id));
+        if current == kind {
+            return Ok(VoteOutcome::Voted(VoteResponse {
+                score: post.score,
+                vote: kind,
+            }));
+        }
+
+        let mut score = post.score;
+
+        // undo the previous vote with the reputation that was applied for it
+        if let Some(v) = existing {
+            let owner_change = v.owner_reputation_change.unwrap_or_default();
+            let voter_change = v.voter_reputation_change.unwrap_or_default();
+            score -= current.score();
+
+            sqlx::query!(
+                r#"
+                delete from votes where id = $1
+                "#,
+                v.id
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                update users set reputation = coalesce(reputation, 0) - $1 where id = $2
+                "#,
+                owner_change,
+                post.owner_user_id
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                update users set reputation = coalesce(reputation, 0) - $1,
+                    upvotes = coalesce(upvotes, 0) - $2, downvotes = coalesce(downvotes, 0) - $3
+                where id = $4
+                "#,
+                voter_change,
+                (current == VoteKind::Up) as i32,
+                (current == VoteKind::Down) as i32,
+                uid
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        if let Some(vote_type_id) = kind.vote_type() {
+            // community wiki posts earn and cost no reputation
+            let (owner_change, voter_change) = match kind {
+                _ if post.wiki => (0, 0),
+                VoteKind::Up => (self.config.vote_karma_gain, 0),
+                _ => (self.config.vote_karma_loss, self.config.downvote_karma_loss),
+            };
+            score += kind.score();
+
+            sqlx::query!(
+                r#"
+                insert into votes (post_id, vote_type_id, user_id, creation_date,
+                    owner_reputation_change, voter_reputation_change)
+                values ($1, $2, $3, current_date, $4, $5)
+                "#,
+                pid,
+                vote_type_id,
+                uid,
+                owner_change,
+                voter_change
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                update users set reputation = coalesce(reputation, 0) + $1 where id = $2
+                "#,
+                owner_change,
+                post.owner_user_id
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                update users set reputation = coalesce(reputation, 0) + $1,
+                    upvotes = coalesce(upvotes, 0) + $2, downvotes = coalesce(downvotes, 0) + $3
+                where id = $4
+                "#,
+                voter_change,
+                (kind == VoteKind::Up) as i32,
+                (kind == VoteKind::Down) as i32,
+                uid
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            update posts set score = $1 where id = $2
+            "#,
+            score,
+            pid
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        check_badges(self, post.owner_user_id).await;
+
+        Ok(VoteOutcome::Voted(VoteResponse { score, vote: kind }))
+    }
+}
@@ -0,0 +1,3 @@
+pub mod dao;
+pub mod routes;
+pub mod vote;
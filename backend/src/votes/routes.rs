@@ -0,0 +1,45 @@
+use super::dao::IVote;
+use super::vote::*;
+use crate::api::ApiResult;
+use crate::middlewares::privilege::{Privilege, Privileged};
+use crate::state::AppState;
+
+use actix_web::{post, web, Responder};
+
+#[post("/vote/{id}")]
+async fn vote(
+    params: web::Path<i64>,
+    form: web::Json<VoteReq>,
+    auth: Privileged,
+    state: AppState,
+) -> impl Responder {
+    let pid = params.into_inner();
+    let form = form.into_inner();
+
+    let privilege = match form.vote {
+        VoteKind::Up => Some(Privilege::Upvote),
+        VoteKind::Down => Some(Privilege::Downvote),
+        VoteKind::None => None,
+    };
+    if let Some(privilege) = privilege {
+        if let Err(e) = auth.require(privilege, &state.config.privileges) {
+            return e;
+        }
+    }
+    match state.get_ref().vote(pid, auth.claims.id, form.vote).await {
+        Ok(VoteOutcome::Voted(res)) => ApiResult::new().code(200).with_msg("").with_data(res),
+        Ok(VoteOutcome::NotFound) => ApiResult::new().code(404).with_msg("Post not found"),
+        Ok(VoteOutcome::OwnPost) => ApiResult::new()
+            .code(403)
+            .with_msg("You cannot vote on your own post"),
+        Ok(VoteOutcome::Locked) => ApiResult::new().code(403).with_msg("This post is locked"),
+        Err(e) => {
+            error!("vote {} {:?} error: {:?}", pid, form, e);
+            ApiResult::new().code(500).with_msg(e.to_string())
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(vote);
+}
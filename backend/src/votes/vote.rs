@@ -0,0 +1,55 @@
+// vote_type_id of up and down votes in the votes table
+pub const UP_VOTE_TYPE: i32 = 2;
+pub const DOWN_VOTE_TYPE: i32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteKind {
+    Up,
+    Down,
+    // retract the current vote, if any
+    None,
+}
+
+impl VoteKind {
+    pub fn from_vote_type(vote_type_id: Option<i32>) -> Self {
+        match vote_type_id {
+            Some(UP_VOTE_TYPE) => VoteKind::Up,
+            Some(DOWN_VOTE_TYPE) => VoteKind::Down,
+            _ => VoteKind::None,
+        }
+    }
+    pub fn vote_type(&self) -> Option<i32> {
+        match self {
+            VoteKind::Up => Some(UP_VOTE_TYPE),
+            VoteKind::Down => Some(DOWN_VOTE_TYPE),
+            VoteKind::None => None,
+        }
+    }
+    pub fn score(&self) -> i64 {
+        match self {
+            VoteKind::Up => 1,
+            VoteKind::Down => -1,
+            VoteKind::None => 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VoteReq {
+    pub vote: VoteKind,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VoteResponse {
+    pub score: i64,
+    pub vote: VoteKind,
+}
+
+#[derive(Debug)]
+pub enum VoteOutcome {
+    Voted(VoteResponse),
+    NotFound,
+    OwnPost,
+    Locked,
+}